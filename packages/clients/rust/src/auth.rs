@@ -0,0 +1,30 @@
+//! UCP Token Authentication
+//!
+//! Pluggable source of short-lived credentials, such as OAuth access
+//! tokens. When `UcpConfig::token_provider` is set, each request carries
+//! the provider's current token (formatted per `UcpConfig::auth_header`)
+//! instead of the static `api_key`. A request rejected with `401` has the
+//! token refreshed and is re-sent once; a second `401` fails with
+//! `UcpError::AuthenticationError`. Streamed uploads, which cannot be
+//! re-sent, refresh the token before sending instead.
+
+use super::types::Result;
+use std::fmt;
+use std::future::Future;
+use std::pin::Pin;
+
+/// Future returned by `TokenProvider` methods
+pub type Token<'a> = Pin<Box<dyn Future<Output = Result<String>> + Send + 'a>>;
+
+/// Source of bearer tokens for requests
+pub trait TokenProvider: fmt::Debug + Send + Sync {
+    /// Token to send with the next request, called once per request
+    /// attempt, so implementations should cache it
+    fn token(&self) -> Token<'_>;
+
+    /// Obtain a new token after the server rejected the current one
+    ///
+    /// Concurrent requests may each see a `401` and call this; providers
+    /// should coalesce refreshes if their token endpoint is rate limited.
+    fn refresh(&self) -> Token<'_>;
+}
@@ -0,0 +1,111 @@
+//! UCP Search Cache
+//!
+//! Client-side cache of search results, enabled by
+//! `UcpConfig::search_cache`. `search_memories` answers a query identical
+//! to one answered within the last `ttl_ms` from the cache, and
+//! `UcpClient::prefetch` fills it ahead of queries an agent expects to make.
+//!
+//! This is best-effort: entries are not invalidated by writes, so a cached
+//! result can miss memories stored (or include ones deleted) up to `ttl_ms`
+//! ago, and the cache is local to one client instance and its clones.
+
+use super::types::{MemoryResponse, SearchCacheConfig};
+use std::collections::HashMap;
+use std::sync::{Mutex, MutexGuard};
+use std::time::{Duration, Instant};
+
+/// Cached results keyed by their serialized query, with when they were stored
+type Entries = HashMap<String, (Instant, Vec<MemoryResponse>)>;
+
+/// Recent search results
+#[derive(Debug)]
+pub(crate) struct SearchCache {
+    capacity: usize,
+    ttl: Duration,
+    entries: Mutex<Entries>,
+}
+
+impl SearchCache {
+    pub(crate) fn new(config: &SearchCacheConfig) -> Self {
+        Self {
+            capacity: config.capacity,
+            ttl: Duration::from_millis(config.ttl_ms),
+            entries: Mutex::default(),
+        }
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Entries> {
+        self.entries.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Results cached for `key`, if they are still fresh at `now`
+    pub(crate) fn get(&self, key: &str, now: Instant) -> Option<Vec<MemoryResponse>> {
+        let entries = self.lock();
+        let (stored_at, results) = entries.get(key)?;
+        (now.saturating_duration_since(*stored_at) < self.ttl).then(|| results.clone())
+    }
+
+    /// Cache results, dropping expired entries and, if still full, the oldest
+    pub(crate) fn insert(&self, key: String, results: Vec<MemoryResponse>, now: Instant) {
+        let mut entries = self.lock();
+        entries.retain(|_, (stored_at, _)| now.saturating_duration_since(*stored_at) < self.ttl);
+
+        if entries.len() >= self.capacity && !entries.contains_key(&key) {
+            let oldest = entries
+                .iter()
+                .min_by_key(|(_, (stored_at, _))| *stored_at)
+                .map(|(key, _)| key.clone());
+            if let Some(oldest) = oldest {
+                entries.remove(&oldest);
+            }
+        }
+        entries.insert(key, (now, results));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cache(capacity: usize) -> SearchCache {
+        SearchCache::new(&SearchCacheConfig {
+            capacity,
+            ttl_ms: 1_000,
+        })
+    }
+
+    fn results(id: &str) -> Vec<MemoryResponse> {
+        vec![serde_json::from_value(serde_json::json!({
+            "id": id,
+            "content": "c",
+            "timestamp": 0
+        }))
+        .unwrap()]
+    }
+
+    #[test]
+    fn test_entries_expire_after_ttl() {
+        let cache = cache(4);
+        let start = Instant::now();
+
+        cache.insert("q".to_string(), results("a"), start);
+        assert_eq!(cache.get("q", start + Duration::from_millis(999)).unwrap()[0].id, "a");
+        assert!(cache.get("q", start + Duration::from_millis(1_000)).is_none());
+        assert!(cache.get("other", start).is_none());
+    }
+
+    #[test]
+    fn test_evicts_oldest_when_full() {
+        let cache = cache(2);
+        let start = Instant::now();
+
+        cache.insert("a".to_string(), results("a"), start);
+        cache.insert("b".to_string(), results("b"), start + Duration::from_millis(1));
+        cache.insert("c".to_string(), results("c"), start + Duration::from_millis(2));
+
+        let now = start + Duration::from_millis(3);
+        assert!(cache.get("a", now).is_none());
+        assert!(cache.get("b", now).is_some());
+        assert!(cache.get("c", now).is_some());
+    }
+}
@@ -0,0 +1,117 @@
+//! UCP Response Charsets
+//!
+//! Detection of the `charset` parameter of a response's `Content-Type`
+//! and conversion of non-UTF-8 bodies to UTF-8 before parsing. Converting
+//! needs the `charset` feature; without it, a body in another charset is
+//! rejected with an error naming the charset.
+
+use super::types::{Result, UcpError};
+use bytes::Bytes;
+use reqwest::header::{HeaderMap, CONTENT_TYPE};
+
+/// Charset named by the `Content-Type` header, if any
+pub(crate) fn from_headers(headers: &HeaderMap) -> Option<String> {
+    let content_type = headers.get(CONTENT_TYPE)?.to_str().ok()?;
+
+    content_type.split(';').skip(1).find_map(|param| {
+        let (name, value) = param.split_once('=')?;
+        name.trim()
+            .eq_ignore_ascii_case("charset")
+            .then(|| value.trim().trim_matches('"').to_string())
+    })
+}
+
+fn is_utf8(charset: &str) -> bool {
+    charset.eq_ignore_ascii_case("utf-8") || charset.eq_ignore_ascii_case("utf8")
+}
+
+/// Convert a body in `charset` to UTF-8 (UTF-8 and unlabeled bodies pass
+/// through unchanged)
+pub(crate) fn to_utf8(body: Bytes, charset: Option<&str>) -> Result<Bytes> {
+    let charset = match charset {
+        Some(charset) if !is_utf8(charset) => charset,
+        _ => return Ok(body),
+    };
+
+    convert(body, charset)
+}
+
+#[cfg(feature = "charset")]
+fn convert(body: Bytes, charset: &str) -> Result<Bytes> {
+    let encoding = encoding_rs::Encoding::for_label(charset.as_bytes()).ok_or_else(|| {
+        UcpError::CodecError(format!("response body uses unknown charset {:?}", charset))
+    })?;
+
+    let (text, _, had_errors) = encoding.decode(&body);
+    if had_errors {
+        return Err(UcpError::CodecError(format!(
+            "response body is not valid {}",
+            encoding.name()
+        )));
+    }
+    Ok(Bytes::from(text.into_owned()))
+}
+
+#[cfg(not(feature = "charset"))]
+fn convert(body: Bytes, charset: &str) -> Result<Bytes> {
+    // Bodies that are UTF-8 anyway (e.g. plain ASCII) need no conversion
+    if std::str::from_utf8(&body).is_ok() {
+        return Ok(body);
+    }
+
+    Err(UcpError::CodecError(format!(
+        "response body uses charset {:?}; enable the `charset` feature to decode it",
+        charset
+    )))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use reqwest::header::HeaderValue;
+
+    fn headers(content_type: &'static str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert(CONTENT_TYPE, HeaderValue::from_static(content_type));
+        headers
+    }
+
+    #[test]
+    fn test_from_headers() {
+        assert_eq!(
+            from_headers(&headers("application/json; charset=ISO-8859-1")).as_deref(),
+            Some("ISO-8859-1")
+        );
+        assert_eq!(
+            from_headers(&headers("application/json;Charset=\"utf-16le\"")).as_deref(),
+            Some("utf-16le")
+        );
+        assert_eq!(from_headers(&headers("application/json")), None);
+        assert_eq!(from_headers(&HeaderMap::new()), None);
+    }
+
+    #[test]
+    fn test_to_utf8_latin1() {
+        let body = Bytes::from_static(b"{\"content\":\"caf\xe9\"}");
+        let converted = to_utf8(body, Some("ISO-8859-1"));
+
+        #[cfg(feature = "charset")]
+        assert_eq!(&converted.unwrap()[..], "{\"content\":\"café\"}".as_bytes());
+
+        #[cfg(not(feature = "charset"))]
+        match converted {
+            Err(UcpError::CodecError(message)) => assert!(message.contains("ISO-8859-1")),
+            other => panic!("expected CodecError, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_to_utf8_passes_through_utf8_and_ascii() {
+        let body = Bytes::from_static("{\"content\":\"café\"}".as_bytes());
+        assert_eq!(to_utf8(body.clone(), None).unwrap(), body);
+        assert_eq!(to_utf8(body.clone(), Some("UTF-8")).unwrap(), body);
+
+        let ascii = Bytes::from_static(b"{\"content\":\"cafe\"}");
+        assert_eq!(to_utf8(ascii.clone(), Some("latin1")).unwrap(), ascii);
+    }
+}
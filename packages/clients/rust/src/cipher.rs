@@ -0,0 +1,120 @@
+//! UCP Content Encryption
+//!
+//! Client-side encryption of memory content, for setups where the server
+//! must only ever see ciphertext. When `UcpConfig::content_cipher` is set,
+//! content is encrypted just before it is sent and decrypted as memories
+//! are received, by every method returning memories or their versions.
+//! Binary payloads (`store_memory_bytes`, `get_memory_raw`) bypass the
+//! cipher and are sent and returned as-is.
+//!
+//! The server cannot embed or rank ciphertext meaningfully, so encrypted
+//! memories are meant to be fetched by ID (`get_memory`), not found by
+//! semantic search. Metadata and tags are sent in the clear. An `embedder`
+//! sees the plaintext, and the vector it produces is sent as-is and can
+//! reveal something about the content.
+
+use super::types::Result;
+use std::fmt;
+
+/// Reversible transformation of memory content
+pub trait ContentCipher: fmt::Debug + Send + Sync {
+    /// Encrypt content before it is stored
+    fn encrypt(&self, plaintext: &str) -> Result<String>;
+
+    /// Decrypt content received from the server
+    fn decrypt(&self, ciphertext: &str) -> Result<String>;
+}
+
+#[cfg(feature = "encrypt")]
+pub use aes::AesGcmCipher;
+
+#[cfg(feature = "encrypt")]
+mod aes {
+    use super::ContentCipher;
+    use crate::types::{Result, UcpError};
+    use aes_gcm::aead::{Aead, AeadCore, KeyInit, OsRng};
+    use aes_gcm::{Aes256Gcm, Key, Nonce};
+    use base64::engine::general_purpose::STANDARD as BASE64;
+    use base64::Engine;
+    use std::fmt;
+
+    /// Length of an AES-GCM nonce in bytes
+    const NONCE_LEN: usize = 12;
+
+    /// AES-256-GCM with a random nonce per message (`encrypt` feature)
+    ///
+    /// Ciphertext is the base64 (standard alphabet, padded) of the 12-byte
+    /// nonce followed by the encrypted content and its authentication tag,
+    /// so tampered or foreign content fails to decrypt.
+    #[derive(Clone)]
+    pub struct AesGcmCipher {
+        cipher: Aes256Gcm,
+    }
+
+    impl AesGcmCipher {
+        /// Cipher using a 256-bit key
+        pub fn new(key: &[u8; 32]) -> Self {
+            Self {
+                cipher: Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(key)),
+            }
+        }
+    }
+
+    impl fmt::Debug for AesGcmCipher {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            f.debug_struct("AesGcmCipher").finish_non_exhaustive()
+        }
+    }
+
+    impl ContentCipher for AesGcmCipher {
+        fn encrypt(&self, plaintext: &str) -> Result<String> {
+            let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+            let ciphertext = self
+                .cipher
+                .encrypt(&nonce, plaintext.as_bytes())
+                .map_err(|_| UcpError::CipherError("encryption failed".to_string()))?;
+
+            let mut sealed = nonce.to_vec();
+            sealed.extend_from_slice(&ciphertext);
+            Ok(BASE64.encode(sealed))
+        }
+
+        fn decrypt(&self, ciphertext: &str) -> Result<String> {
+            let sealed = BASE64
+                .decode(ciphertext)
+                .map_err(|e| UcpError::CipherError(format!("content is not base64: {}", e)))?;
+            if sealed.len() < NONCE_LEN {
+                return Err(UcpError::CipherError("content is too short to decrypt".to_string()));
+            }
+
+            let (nonce, ciphertext) = sealed.split_at(NONCE_LEN);
+            let plaintext = self
+                .cipher
+                .decrypt(Nonce::from_slice(nonce), ciphertext)
+                .map_err(|_| {
+                    UcpError::CipherError("content failed to decrypt (wrong key or tampered)".to_string())
+                })?;
+            String::from_utf8(plaintext)
+                .map_err(|_| UcpError::CipherError("decrypted content is not UTF-8".to_string()))
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        #[test]
+        fn test_round_trip_and_wrong_key() {
+            let cipher = AesGcmCipher::new(&[7; 32]);
+
+            let sealed = cipher.encrypt("patient notes").unwrap();
+            assert!(!sealed.contains("patient"));
+            assert_ne!(sealed, cipher.encrypt("patient notes").unwrap());
+            assert_eq!(cipher.decrypt(&sealed).unwrap(), "patient notes");
+
+            let other = AesGcmCipher::new(&[8; 32]);
+            assert!(matches!(other.decrypt(&sealed), Err(UcpError::CipherError(_))));
+            assert!(matches!(cipher.decrypt("plain text"), Err(UcpError::CipherError(_))));
+        }
+    }
+}
@@ -3,12 +3,58 @@
 //! Provides async HTTP client for communicating with the UCP server,
 //! including streaming support and proper error handling.
 
-use super::types::{UcpConfig, MemoryRequest, MemoryResponse, VectorQuery, UcpError, Result};
-use reqwest::{Client, Response};
+use super::cache::SearchCache;
+use super::clock::{Clock, SystemClock};
+use super::codec::{JsonCodec, WireCodec};
+use super::compression::{accept_compressed, body_stream, ChunkError};
+use super::dedup::{DedupKey, DedupWindow};
+use super::diagnostics::Diagnostics;
+use super::job::{JobHandle, JobStatus};
+use super::latency::{adaptive_timeout, LatencyTracker};
+use super::limiter::ConcurrencyLimiter;
+use super::retry::{backoff_delay, RetryBudget};
+use super::types::{
+    ActivityEvent, AggBucket, ApiVersion, AuthHeaderStyle, BatchOptions, BatchResult, MemoryBundle, BUNDLE_FORMAT_VERSION, ErrorCode, ImportAck, MemoryFilter, Priority, ProbeResult, ProbeSpec, RedirectPolicy, RequestOptions, MAX_REDIRECTS, SessionQuery, UcpConfig, MemoryRequest, MemoryResponse, MemoryUpdate, MemoryVersion, StreamOptions, VectorQuery, UcpError,
+    Result,
+};
+use super::utils::{group_duplicates, hash_content, sort_by_chunk_index, DuplicateGroup};
+use super::writer::SessionWriter;
+use bytes::Bytes;
+use reqwest::{Client, Method, RequestBuilder, Response};
+use serde::de::DeserializeOwned;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, Mutex};
 use std::time::Duration;
 use tokio_stream::{Stream, StreamExt};
+use tokio_util::sync::CancellationToken;
+
+/// API versions this client can speak, highest first
+const SUPPORTED_API_VERSIONS: [ApiVersion; 2] = [ApiVersion::V2, ApiVersion::V1];
+
+/// Number of projects requested per page when listing projects
+pub const PROJECTS_PAGE_LIMIT: u32 = 100;
+
+/// Number of memories requested per page when iterating memories
+pub const MEMORIES_PAGE_LIMIT: u32 = 100;
+
+/// Number of prefetched searches in flight at once
+pub const PREFETCH_CONCURRENCY: usize = 4;
+
+/// Content type of newline-delimited JSON bodies
+const NDJSON_CONTENT_TYPE: &str = "application/x-ndjson";
+
+/// Header carrying `RequestOptions::priority`
+const PRIORITY_HEADER: &str = "x-priority";
+
+/// Header naming the real method of a request tunnelled through POST
+const METHOD_OVERRIDE_HEADER: &str = "x-http-method-override";
+
+/// Header marking a request as safe to replay
+const IDEMPOTENCY_KEY_HEADER: &str = "idempotency-key";
+
+/// Maximum number of bytes of a response body kept in error diagnostics
+const BODY_SNIPPET_LIMIT: usize = 512;
 
 /// UCP HTTP Client
 #[derive(Debug, Clone)]
@@ -17,28 +63,67 @@ pub struct UcpClient {
     client: Client,
     /// Client configuration
     config: UcpConfig,
+    /// Wire codec for request and response bodies
+    codec: Arc<dyn WireCodec>,
+    /// Time source for backoff and latency tracking
+    clock: Arc<dyn Clock>,
+    /// Options applied to every request of this handle
+    options: RequestOptions,
+    /// Retry budget shared by all clones of this client
+    retry_budget: Arc<RetryBudget>,
+    /// Cap on in-flight requests shared by all clones of this client
+    concurrency: Option<Arc<ConcurrencyLimiter>>,
+    /// Observed latencies shared by all clones of this client
+    latency: Arc<LatencyTracker>,
+    /// Server capabilities from the last `get_capabilities` call, shared by
+    /// all clones of this client
+    capabilities: Arc<Mutex<Option<ServerCapabilities>>>,
+    /// Recent stores shared by all clones of this client, when
+    /// `dedup_window` is set
+    dedup: Option<Arc<DedupWindow>>,
+    /// Recent search results shared by all clones of this client, when
+    /// `search_cache` is set
+    search_cache: Option<Arc<SearchCache>>,
+    /// In-flight searches shared by all clones of this client
+    #[cfg(feature = "coalesce")]
+    coalescer: Arc<super::coalesce::Coalescer>,
 }
 
 impl UcpClient {
     /// Create a new UCP client with the given configuration
     pub fn new(config: UcpConfig) -> Result<Self> {
+        #[cfg(not(feature = "tls"))]
+        if config.base_url.get(..8).is_some_and(|scheme| scheme.eq_ignore_ascii_case("https://")) {
+            return Err(UcpError::ConfigError(format!(
+                "base_url {} uses HTTPS, but this build has no TLS support (enable the `tls` feature)",
+                config.base_url
+            )));
+        }
+
         let client_builder = Client::builder()
             .timeout(Duration::from_secs(config.timeout_secs))
+            .redirect(redirect_policy(config.redirect_policy, &config.auth_header))
             .user_agent("RAFT/0.1.0");
 
-        // Add default headers
+        let codec = config
+            .codec
+            .clone()
+            .unwrap_or_else(|| Arc::new(JsonCodec));
+        let clock = config
+            .clock
+            .clone()
+            .unwrap_or_else(|| Arc::new(SystemClock));
+
+        // Add default headers; Content-Type is only set on requests with a body
         let mut default_headers = reqwest::header::HeaderMap::new();
         default_headers.insert(
-            reqwest::header::CONTENT_TYPE,
-            reqwest::header::HeaderValue::from_static("application/json"),
+            reqwest::header::ACCEPT,
+            reqwest::header::HeaderValue::from_static(codec.content_type()),
         );
 
         if let Some(ref api_key) = config.api_key {
-            default_headers.insert(
-                reqwest::header::AUTHORIZATION,
-                reqwest::header::HeaderValue::from_str(&format!("Bearer {}", api_key))
-                    .map_err(|_| UcpError::ConfigError("Invalid API key format".to_string()))?,
-            );
+            let (name, value) = auth_header(&config.auth_header, api_key)?;
+            default_headers.insert(name, value);
         }
 
         let client = client_builder
@@ -46,47 +131,691 @@ impl UcpClient {
             .build()
             .map_err(UcpError::HttpError)?;
 
-        Ok(Self { client, config })
+        let retry_budget = Arc::new(RetryBudget::new(&config.retry_policy));
+        let concurrency = match config.max_concurrent_requests {
+            Some(0) => {
+                return Err(UcpError::ConfigError(
+                    "max_concurrent_requests must be at least 1".to_string(),
+                ))
+            }
+            Some(max) => Some(Arc::new(ConcurrencyLimiter::new(max))),
+            None => None,
+        };
+
+        let latency = Arc::new(LatencyTracker::new(config.adaptive_timeout.as_ref()));
+        let dedup = match config.dedup_window {
+            Some(0) => {
+                return Err(UcpError::ConfigError(
+                    "dedup_window must be at least 1".to_string(),
+                ))
+            }
+            Some(capacity) => Some(Arc::new(DedupWindow::new(capacity))),
+            None => None,
+        };
+        let search_cache = match config.search_cache {
+            Some(ref cache) if cache.capacity == 0 => {
+                return Err(UcpError::ConfigError(
+                    "search_cache capacity must be at least 1".to_string(),
+                ))
+            }
+            Some(ref cache) => Some(Arc::new(SearchCache::new(cache))),
+            None => None,
+        };
+
+        Ok(Self {
+            client,
+            config,
+            codec,
+            clock,
+            options: RequestOptions::default(),
+            retry_budget,
+            concurrency,
+            latency,
+            capabilities: Arc::default(),
+            dedup,
+            search_cache,
+            #[cfg(feature = "coalesce")]
+            coalescer: Arc::default(),
+        })
+    }
+
+    /// Effective configuration for bug reports, without credentials
+    pub fn diagnostics(&self) -> Diagnostics {
+        Diagnostics::new(&self.config)
+    }
+
+    /// Handle to this client that applies `options` to every request
+    ///
+    /// The handle shares the connection pool and all other state with this
+    /// client, e.g. `client.with_options(RequestOptions { priority:
+    /// Some(Priority::Low) })` for a background import.
+    pub fn with_options(&self, options: RequestOptions) -> Self {
+        Self {
+            options,
+            ..self.clone()
+        }
     }
 
     /// Store memory content in UCP
-    pub async fn store_memory(&self, request: MemoryRequest) -> Result<MemoryResponse> {
-        let url = format!("{}/api/v1/memory", self.config.base_url);
+    ///
+    /// If the server accepts the memory without returning it (e.g. 202 with
+    /// an empty body), the request is echoed back with an empty `id`; see
+    /// `MemoryResponse::is_pending`.
+    ///
+    /// With `dedup_window` set, storing content just stored to the same
+    /// project and session returns the earlier response (possibly a pending
+    /// one) without sending a request.
+    pub async fn store_memory(&self, mut request: MemoryRequest) -> Result<MemoryResponse> {
+        if self.config.reject_empty_content && request.content.trim().is_empty() {
+            return Err(UcpError::ConfigError(
+                "memory content is empty (reject_empty_content is set)".to_string(),
+            ));
+        }
+
+        self.validate_memory_request(&request)?;
+        self.rewrite_content(&mut request);
+
+        let dedup_key = self.dedup.as_ref().map(|_| DedupKey::new(&request));
+        if let (Some(dedup), Some(key)) = (&self.dedup, &dedup_key) {
+            if let Some(memory) = dedup.get(key) {
+                return Ok(memory);
+            }
+        }
+
+        self.embed_content(&mut request).await?;
+        self.sample_store(&request);
+        self.encrypt_content(&mut request)?;
+
+        let url = self.url("memory");
         
         let response = self
+            .execute(self.with_body(self.client.post(&url), &request)?)
+            .await?;
+
+        // Servers storing asynchronously may answer 202 with no body
+        let memory = match self.handle_optional_response(response).await? {
+            Some(memory) => memory,
+            None => pending_response(request, self.clock.as_ref()),
+        };
+        let memory = self.decrypted(memory)?;
+
+        if let (Some(dedup), Some(key)) = (&self.dedup, dedup_key) {
+            dedup.insert(key, memory.clone());
+        }
+        Ok(memory)
+    }
+
+    /// Store several memories in one request
+    ///
+    /// The server stores each memory independently; the result reports
+    /// which batch indices were stored and why the others were rejected.
+    /// With `reject_empty_content` set, memories with empty content are
+    /// reported as failed without being sent, and the rest are stored.
+    pub async fn store_memories_batch(&self, requests: Vec<MemoryRequest>) -> Result<BatchResult> {
+        self.store_memories_batch_with(requests, BatchOptions::default()).await
+    }
+
+    /// Store several memories in one request with explicit batch options
+    ///
+    /// With `fail_fast` set, the server is asked to stop at the first
+    /// rejected memory, and that memory's error is returned instead of a
+    /// `BatchResult`. Memories stored before it are kept unless the server
+    /// rolls the batch back; the client never deletes them itself. Empty
+    /// content rejected by `reject_empty_content` fails the whole batch
+    /// before anything is sent.
+    pub async fn store_memories_batch_with(
+        &self,
+        requests: Vec<MemoryRequest>,
+        options: BatchOptions,
+    ) -> Result<BatchResult> {
+        let mut result = BatchResult::default();
+
+        // Batch index of each memory that is sent
+        let mut indices = Vec::with_capacity(requests.len());
+        let mut requests: Vec<MemoryRequest> = requests
+            .into_iter()
+            .enumerate()
+            .filter_map(|(index, request)| {
+                if self.config.reject_empty_content && request.content.trim().is_empty() {
+                    let error = UcpError::ConfigError(
+                        "memory content is empty (reject_empty_content is set)".to_string(),
+                    );
+                    result.failed.push((index, error));
+                    None
+                } else {
+                    indices.push(index);
+                    Some(request)
+                }
+            })
+            .collect();
+
+        if options.fail_fast && !result.failed.is_empty() {
+            let empty: Vec<usize> = result.failed.iter().map(|(index, _)| *index).collect();
+            return Err(UcpError::ConfigError(format!(
+                "memory content is empty at batch indices {:?} (reject_empty_content is set)",
+                empty
+            )));
+        }
+        if requests.is_empty() {
+            return Ok(result);
+        }
+
+        for (&index, request) in indices.iter().zip(&requests) {
+            self.validate_memory_request(request).map_err(|e| match e {
+                UcpError::ConfigError(message) => {
+                    UcpError::ConfigError(format!("batch index {}: {}", index, message))
+                }
+                other => other,
+            })?;
+        }
+
+        for request in &mut requests {
+            self.rewrite_content(request);
+            self.embed_content(request).await?;
+            self.sample_store(request);
+            self.encrypt_content(request)?;
+        }
+
+        let url = self.url("memory/batch");
+        let body = BatchStoreRequest {
+            memories: &requests,
+            fail_fast: options.fail_fast,
+        };
+
+        let response = self
+            .execute(self.with_body(self.client.post(&url), &body)?)
+            .await?;
+
+        let batch_response: BatchStoreResponse = self.handle_response(response).await?;
+
+        let mut indices = indices.into_iter();
+        // Results first, so an exhausted response leaves the rest of
+        // `indices` unconsumed
+        for (item, index) in batch_response.results.into_iter().zip(indices.by_ref()) {
+            match item {
+                // Stored either way, so a decryption failure must not hide
+                // the other items' outcomes
+                BatchItem::Stored(memory) => match self.decrypted(*memory) {
+                    Ok(memory) => result.succeeded.push((index, memory)),
+                    Err(error) => result.failed.push((index, error)),
+                },
+                BatchItem::Failed { error } => {
+                    let message = error.message.clone();
+                    let error = error_from_parts(error.status, Some(error), message);
+                    if options.fail_fast {
+                        return Err(error);
+                    }
+                    result.failed.push((index, error));
+                }
+            }
+        }
+
+        // A short response must not pass for a complete batch
+        for index in indices {
+            let error = UcpError::ServerError {
+                status: None,
+                code: None,
+                message: format!("no result returned for batch index {}", index),
+            };
+            if options.fail_fast {
+                return Err(error);
+            }
+            result.failed.push((index, error));
+        }
+        result.failed.sort_by_key(|(index, _)| *index);
+        Ok(result)
+    }
+
+    /// Start a buffered writer appending memories to one session
+    ///
+    /// Appends are sent through `store_memories_batch` in groups; see
+    /// `SessionWriter` for when batches are flushed.
+    pub fn session_writer(&self, project: &str, session: &str) -> SessionWriter {
+        SessionWriter::new(self.clone(), project.to_string(), session.to_string())
+    }
+
+    /// Import memories as NDJSON, streaming back one acknowledgement per row
+    ///
+    /// Rows are uploaded as they are produced and acknowledged as the server
+    /// stores them, so progress is visible during large imports. Rejected
+    /// rows are reported through `ImportAck::error` rather than failing the
+    /// stream. The upload cannot be replayed, so the request is not retried;
+    /// with a `token_provider`, the token is refreshed up front instead of
+    /// after a `401`.
+    pub async fn import_memories_stream<S>(
+        &self,
+        memories: S,
+    ) -> Result<impl Stream<Item = Result<ImportAck>>>
+    where
+        S: Stream<Item = MemoryRequest> + Send + Sync + 'static,
+    {
+        let url = self.url("memory/import");
+
+        let client = self.clone();
+        let rows = memories.map(move |mut request| -> Result<Vec<u8>> {
+            client.rewrite_content(&mut request);
+            client.sample_store(&request);
+            client.encrypt_content(&mut request)?;
+            let mut line = serde_json::to_vec(&request)?;
+            line.push(b'\n');
+            Ok(line)
+        });
+
+        let request = self
             .client
             .post(&url)
-            .json(&request)
-            .send()
-            .await?;
+            .header(reqwest::header::CONTENT_TYPE, NDJSON_CONTENT_TYPE)
+            .header(reqwest::header::ACCEPT, NDJSON_CONTENT_TYPE)
+            .body(reqwest::Body::wrap_stream(rows));
+        let request = accept_compressed(request);
 
-        self.handle_response(response).await
+        if let Some(ref provider) = self.config.token_provider {
+            provider.refresh().await?;
+        }
+        let response = self.execute_once(request).await?;
+        Ok(ndjson_stream(response))
     }
 
     /// Retrieve memory by ID
     pub async fn get_memory(&self, project: &str, memory_id: &str) -> Result<MemoryResponse> {
-        let url = format!(
-            "{}/api/v1/memory/{}/{}",
-            self.config.base_url, project, memory_id
-        );
+        let url = self.url(&format!("memory/{}/{}", project, memory_id));
+
+        let response = self.execute(self.client.get(&url)).await?;
+        self.decrypted(self.handle_response(response).await?)
+    }
+
+    /// Retrieve the raw body of a memory and its content type
+    ///
+    /// Bypasses JSON decoding, for memories holding binary payloads.
+    pub async fn get_memory_raw(
+        &self,
+        project: &str,
+        memory_id: &str,
+    ) -> Result<(Bytes, Option<String>)> {
+        let url = self.url(&format!("memory/{}/{}/raw", project, memory_id));
+
+        let response = self
+            .execute(self.client.get(&url).header(reqwest::header::ACCEPT, "*/*"))
+            .await?;
+
+        let content_type = response
+            .headers()
+            .get(reqwest::header::CONTENT_TYPE)
+            .and_then(|value| value.to_str().ok())
+            .map(str::to_string);
+        let body = read_body(response).await?;
+
+        Ok((body, content_type))
+    }
+
+    /// Store a memory from a `MemoryBundle` into `project` and `session`
+    ///
+    /// Content, metadata, tags and any embedding are kept; the store goes
+    /// through `store_memory`, so configured content rewriting still
+    /// applies. Bundles from a newer format than `BUNDLE_FORMAT_VERSION`
+    /// fail with `UcpError::ConfigError` without contacting the server.
+    pub async fn store_bundle(
+        &self,
+        project: &str,
+        session: &str,
+        bundle: MemoryBundle,
+    ) -> Result<MemoryResponse> {
+        if bundle.format_version > BUNDLE_FORMAT_VERSION {
+            return Err(UcpError::ConfigError(format!(
+                "memory bundle format version {} is newer than the supported version {}",
+                bundle.format_version, BUNDLE_FORMAT_VERSION
+            )));
+        }
+
+        let mut request = MemoryRequest::new(project, session, bundle.content);
+        request.metadata = bundle.metadata;
+        request.tags = bundle.tags;
+        request.embedding = bundle.embedding;
+        self.store_memory(request).await
+    }
+
+    /// Store `value` serialized as JSON content
+    ///
+    /// Shorthand for `store_memory(MemoryRequest::from_json(..)?)`; read
+    /// the content back with `MemoryResponse::content_as`.
+    pub async fn store_memory_typed<T: Serialize>(
+        &self,
+        project: &str,
+        session: &str,
+        value: &T,
+    ) -> Result<MemoryResponse> {
+        self.store_memory(MemoryRequest::from_json(project, session, value)?).await
+    }
+
+    /// Store a binary payload with an explicit content type
+    pub async fn store_memory_bytes(
+        &self,
+        project: &str,
+        session: &str,
+        data: impl Into<Bytes>,
+        content_type: &str,
+    ) -> Result<MemoryResponse> {
+        let url = self.url(&format!("memory/{}/raw", project));
+
+        let request = self
+            .client
+            .post(&url)
+            .query(&[("session", session)])
+            .header(reqwest::header::CONTENT_TYPE, content_type)
+            .body(data.into());
 
-        let response = self.client.get(&url).send().await?;
+        let response = self.execute(request).await?;
         self.handle_response(response).await
     }
 
+    /// Update an existing memory
+    ///
+    /// When `version` is given it is sent as `If-Match`, and the update fails
+    /// with `UcpError::Conflict` if the memory has changed since.
+    pub async fn update_memory(
+        &self,
+        project: &str,
+        memory_id: &str,
+        mut update: MemoryUpdate,
+        version: Option<u64>,
+    ) -> Result<MemoryResponse> {
+        let url = self.url(&format!("memory/{}/{}", project, memory_id));
+
+        if let (Some(cipher), Some(content)) = (&self.config.content_cipher, &mut update.content) {
+            *content = cipher.encrypt(content)?;
+        }
+
+        let request = self.with_body(self.client.patch(&url), &update)?;
+        let response = self.execute(if_match(request, version)).await?;
+        self.decrypted(self.handle_response(response).await?)
+    }
+
+    /// Restore an earlier version of a memory as its current state
+    ///
+    /// The restore is written as a new version, so history is kept. Fails
+    /// with `UcpError::VersionNotFound` if the memory has no such version
+    /// and `UcpError::NotFound` if the memory itself does not exist.
+    pub async fn rollback_memory(
+        &self,
+        project: &str,
+        memory_id: &str,
+        version: u64,
+    ) -> Result<MemoryResponse> {
+        let url = self.url(&format!("memory/{}/{}/rollback", project, memory_id));
+
+        let request = self.with_body(self.client.post(&url), &RollbackRequest { version })?;
+        let response = self.execute(request).await.map_err(|e| {
+            if e.error_code() == Some(ErrorCode::VersionNotFound) {
+                UcpError::VersionNotFound {
+                    memory_id: memory_id.to_string(),
+                    version,
+                }
+            } else {
+                e
+            }
+        })?;
+        self.decrypted(self.handle_response(response).await?)
+    }
+
+    /// Set a memory to expire `ttl_secs` from now, replacing any earlier
+    /// expiry
+    pub async fn set_ttl(
+        &self,
+        project: &str,
+        memory_id: &str,
+        ttl_secs: u64,
+    ) -> Result<MemoryResponse> {
+        let url = self.url(&format!("memory/{}/{}/ttl", project, memory_id));
+
+        let request = self.with_body(self.client.put(&url), &TtlUpdate { ttl_secs })?;
+        let response = self.execute(request).await?;
+        self.decrypted(self.handle_response(response).await?)
+    }
+
+    /// Archive a memory: keep it, but leave it out of searches that do not
+    /// set `include_archived`
+    ///
+    /// A recoverable alternative to `delete_memory`; see `restore_memory`.
+    pub async fn archive_memory(&self, project: &str, memory_id: &str) -> Result<MemoryResponse> {
+        let url = self.url(&format!("memory/{}/{}/archive", project, memory_id));
+
+        let response = self.execute(self.client.post(&url)).await?;
+        self.decrypted(self.handle_response(response).await?)
+    }
+
+    /// Return an archived memory to default search results
+    pub async fn restore_memory(&self, project: &str, memory_id: &str) -> Result<MemoryResponse> {
+        let url = self.url(&format!("memory/{}/{}/restore", project, memory_id));
+
+        let response = self.execute(self.client.post(&url)).await?;
+        self.decrypted(self.handle_response(response).await?)
+    }
+
+    /// Retrieve a memory's version history, oldest first
+    ///
+    /// See `diff::diff_versions` to compare two versions.
+    pub async fn get_memory_versions(
+        &self,
+        project: &str,
+        memory_id: &str,
+    ) -> Result<Vec<MemoryVersion>> {
+        let url = self.url(&format!("memory/{}/{}/versions", project, memory_id));
+
+        let response = self.execute(self.client.get(&url)).await?;
+        let versions_response: MemoryVersionsResponse = self.handle_response(response).await?;
+
+        let mut versions = versions_response.versions;
+        if let Some(ref cipher) = self.config.content_cipher {
+            for version in &mut versions {
+                version.content = cipher.decrypt(&version.content)?;
+            }
+        }
+        versions.sort_by_key(|version| version.version);
+        Ok(versions)
+    }
+
+    /// Recompute a memory's embedding from its stored content, e.g. after
+    /// the server's embedding model changed
+    pub async fn reembed_memory(&self, project: &str, memory_id: &str) -> Result<MemoryResponse> {
+        let url = self.url(&format!("memory/{}/{}/reembed", project, memory_id));
+
+        let response = self.execute(self.client.post(&url)).await?;
+        self.decrypted(self.handle_response(response).await?)
+    }
+
+    /// Start recomputing the embeddings of every memory in a project
+    ///
+    /// The server runs this in the background; the returned handle
+    /// identifies the job.
+    pub async fn reembed_project(&self, project: &str) -> Result<JobHandle> {
+        let url = self.url(&format!("memory/{}/reembed", project));
+
+        let response = self.execute(self.client.post(&url)).await?;
+        self.accepted_job(response).await
+    }
+
     /// Search memories using vector similarity
+    ///
+    /// With the `coalesce` feature, concurrent identical queries share a
+    /// single request; see the `coalesce` module for the staleness tradeoff.
+    /// With `search_cache` set, a query answered recently is served from
+    /// the cache; see the `cache` module.
     pub async fn search_memories(&self, query: VectorQuery) -> Result<Vec<MemoryResponse>> {
-        let url = format!("{}/api/v1/search", self.config.base_url);
+        let query = self.with_default_filter(query);
+        self.validate_query(&query)?;
+
+        let Some(ref cache) = self.search_cache else {
+            return self.search_memories_coalesced(&query).await;
+        };
+
+        let key = serde_json::to_string(&query)?;
+        if let Some(results) = cache.get(&key, self.clock.now()) {
+            return Ok(results);
+        }
+        let results = self.search_memories_coalesced(&query).await?;
+        cache.insert(key, results.clone(), self.clock.now());
+        Ok(results)
+    }
+
+    /// Run searches in the background to warm the search cache
+    ///
+    /// Returns immediately; the queries are sent at low priority, at most
+    /// `PREFETCH_CONCURRENCY` at a time, so that a later `search_memories`
+    /// with the same query is answered from the cache. This is best-effort:
+    /// failed prefetches are ignored, and nothing is sent unless
+    /// `search_cache` is set. Must be called within a Tokio runtime.
+    pub fn prefetch(&self, queries: Vec<VectorQuery>) {
+        if self.search_cache.is_none() || queries.is_empty() {
+            return;
+        }
+
+        let client = self.with_options(RequestOptions {
+            priority: Some(Priority::Low),
+        });
+        let semaphore = Arc::new(tokio::sync::Semaphore::new(PREFETCH_CONCURRENCY));
+        for query in queries {
+            let (client, semaphore) = (client.clone(), semaphore.clone());
+            tokio::spawn(async move {
+                let _permit = semaphore.acquire_owned().await.expect("semaphore is never closed");
+                let _ = client.search_memories(query).await;
+            });
+        }
+    }
+
+    /// Send a search, sharing an identical in-flight one under the
+    /// `coalesce` feature
+    async fn search_memories_coalesced(&self, query: &VectorQuery) -> Result<Vec<MemoryResponse>> {
+        #[cfg(feature = "coalesce")]
+        {
+            let key = serde_json::to_string(query)?;
+            return self
+                .coalescer
+                .run(key, || self.search_memories_uncoalesced(query))
+                .await;
+        }
+
+        #[cfg(not(feature = "coalesce"))]
+        self.search_memories_uncoalesced(query).await
+    }
+
+    /// Search memories, giving up as soon as `cancel` is cancelled
+    ///
+    /// Cancelling drops the in-flight request, closing its connection, and
+    /// fails with `UcpError::Cancelled`; use this when the caller may lose
+    /// interest (e.g. the user navigated away). With the `coalesce`
+    /// feature, a shared search keeps running for the other callers.
+    pub async fn search_memories_cancellable(
+        &self,
+        query: VectorQuery,
+        cancel: &CancellationToken,
+    ) -> Result<Vec<MemoryResponse>> {
+        tokio::select! {
+            biased;
+            _ = cancel.cancelled() => Err(UcpError::Cancelled),
+            result = self.search_memories(query) => result,
+        }
+    }
+
+    /// Send a search request
+    async fn search_memories_uncoalesced(&self, query: &VectorQuery) -> Result<Vec<MemoryResponse>> {
+        Ok(self.send_search(query).await?.results)
+    }
+
+    /// Search memories, grouping the results by session
+    ///
+    /// Sets `group_by_session` on the query. Within a session, results keep
+    /// the server's order (by score unless configured otherwise). Results
+    /// without a session are grouped under the empty string.
+    pub async fn search_grouped(
+        &self,
+        mut query: VectorQuery,
+    ) -> Result<HashMap<String, Vec<MemoryResponse>>> {
+        query.group_by_session = true;
+
+        let mut groups: HashMap<String, Vec<MemoryResponse>> = HashMap::new();
+        for memory in self.search_memories(query).await? {
+            groups
+                .entry(memory.session.clone().unwrap_or_default())
+                .or_default()
+                .push(memory);
+        }
+        Ok(groups)
+    }
+
+    /// Search memories, returning the results with the server's totals
+    /// and timings
+    ///
+    /// `SearchResponse::timings` breaks the server time down by phase
+    /// (e.g. embedding, vector search, reranking) when the server reports
+    /// it. Each call sends its own request, even with the `coalesce`
+    /// feature, so the timings describe this search.
+    pub async fn search_memories_detailed(&self, query: VectorQuery) -> Result<SearchResponse> {
+        let query = self.with_default_filter(query);
+        self.validate_query(&query)?;
+        self.send_search(&query).await
+    }
+
+    async fn send_search(&self, query: &VectorQuery) -> Result<SearchResponse> {
+        let url = self.url("search");
 
         let response = self
-            .client
-            .post(&url)
-            .json(&query)
-            .send()
+            .execute_read(self.with_body(self.client.post(&url), &query)?)
+            .await?;
+
+        let mut search_response: SearchResponse = self.handle_response(response).await?;
+        search_response.results = self.decrypted_all(search_response.results)?;
+        Ok(search_response)
+    }
+
+    /// Search for matching memory IDs and scores only
+    ///
+    /// Asks the server for an `id,score` projection, which keeps responses
+    /// small when the results only feed a later stage such as a reranker.
+    pub async fn search_ids(&self, query: VectorQuery) -> Result<Vec<(String, Option<f64>)>> {
+        let query = self.with_default_filter(query);
+        self.validate_query(&query)?;
+        let url = self.url("search");
+
+        let response = self
+            .execute_read(
+                self.with_body(self.client.post(&url), &query)?
+                    .query(&[("fields", "id,score")]),
+            )
             .await?;
 
-        let search_response: SearchResponse = self.handle_response(response).await?;
-        Ok(search_response.results)
+        let search_response: SearchIdsResponse = self.handle_response(response).await?;
+        Ok(search_response
+            .results
+            .into_iter()
+            .map(|hit| (hit.id, hit.score))
+            .collect())
+    }
+
+    /// Count how many results a query yields at each of several thresholds
+    ///
+    /// Runs the query once with threshold 0.0 (fetching only IDs and
+    /// scores) and filters client-side, so trying thresholds costs a single
+    /// round trip. Counts are capped by the query's `limit`; raise it to see
+    /// counts at low thresholds. Unscored results only count at thresholds
+    /// of 0.0 or below.
+    pub async fn threshold_sweep(
+        &self,
+        mut query: VectorQuery,
+        thresholds: &[f64],
+    ) -> Result<Vec<(f64, usize)>> {
+        query.threshold = 0.0;
+        let hits = self.search_ids(query).await?;
+
+        Ok(thresholds
+            .iter()
+            .map(|&threshold| {
+                let count = hits
+                    .iter()
+                    .filter(|(_, score)| score.filter(|s| !s.is_nan()).unwrap_or(0.0) >= threshold)
+                    .count();
+                (threshold, count)
+            })
+            .collect())
     }
 
     /// Stream search results for large result sets
@@ -94,211 +823,5119 @@ impl UcpClient {
         &self,
         query: VectorQuery,
     ) -> Result<impl Stream<Item = Result<MemoryResponse>>> {
-        let url = format!("{}/api/v1/search/stream", self.config.base_url);
+        self.search_memories_stream_with(query, StreamOptions::default())
+            .await
+    }
 
-        let response = self
-            .client
-            .post(&url)
-            .json(&query)
-            .header("Accept", "application/x-ndjson")
-            .send()
-            .await?;
+    /// Stream search results with explicit stream options
+    ///
+    /// With `reconnect` enabled, a connection dropped mid-stream is re-issued
+    /// up to `max_retries` times. The retry carries the ID of the last
+    /// received result as `Last-Event-Id` so servers can resume, and results
+    /// already yielded are skipped if the server starts over.
+    pub async fn search_memories_stream_with(
+        &self,
+        query: VectorQuery,
+        options: StreamOptions,
+    ) -> Result<impl Stream<Item = Result<MemoryResponse>>> {
+        let query = self.with_default_filter(query);
+        self.validate_query(&query)?;
+        let url = self.url("search/stream");
 
-        if !response.status().is_success() {
-            return Err(self.handle_error_response(response).await);
-        }
+        let request = self
+            .with_body(self.client.post(&url), &query)?
+            .header("Accept", "application/x-ndjson");
+        let request = accept_compressed(request);
+        let retry_request = request.try_clone().filter(|_| options.reconnect);
+
+        let response = self.execute(request).await?;
+
+        let client = self.clone();
+        let max_reconnects = self.config.max_retries;
+        let idle_timeout = options.stream_idle_timeout;
+        let take = options.take;
 
         // Create a simple line-based stream parser
         let stream = async_stream::stream! {
-            let mut buffer = Vec::new();
-            let mut bytes_stream = response.bytes_stream();
-            
-            while let Some(chunk_result) = bytes_stream.next().await {
-                match chunk_result {
-                    Ok(chunk) => {
-                        buffer.extend_from_slice(&chunk);
-                        
-                        // Process complete lines
-                        while let Some(newline_pos) = buffer.iter().position(|&b| b == b'\n') {
-                            let line = buffer.drain(..=newline_pos).collect::<Vec<_>>();
-                            let line_str = match std::str::from_utf8(&line[..line.len().saturating_sub(1)]) {
-                                Ok(s) => s,
-                                Err(_) => {
-                                    yield Err(UcpError::ServerError {
-                                        message: "Invalid UTF-8 in response".to_string(),
-                                    });
-                                    continue;
-                                }
-                            };
+            let mut response = response;
+            let mut reconnects = 0;
+            let mut last_id: Option<String> = None;
+            let mut seen = HashSet::new();
+            let mut yielded = 0;
+            if take == Some(0) {
+                return;
+            }
+
+            loop {
+                let mut buffer = Vec::new();
+                let mut bytes_stream = body_stream(response);
+                let mut disconnect = None;
+
+                loop {
+                    let next = match idle_timeout {
+                        Some(idle) => match tokio::time::timeout(idle, bytes_stream.next()).await {
+                            Ok(next) => next,
+                            Err(_) => {
+                                yield Err(UcpError::Timeout(idle));
+                                return;
+                            }
+                        },
+                        None => bytes_stream.next().await,
+                    };
 
-                            if !line_str.trim().is_empty() {
-                                match serde_json::from_str::<MemoryResponse>(line_str) {
-                                    Ok(memory_response) => yield Ok(memory_response),
-                                    Err(e) => yield Err(UcpError::JsonError(e)),
+                    // A clean close ends any unterminated last line
+                    let mut ended = false;
+                    let chunk = match next {
+                        None if buffer.iter().all(u8::is_ascii_whitespace) => break,
+                        None => {
+                            ended = true;
+                            Bytes::from_static(b"\n")
+                        }
+                        Some(Ok(chunk)) => chunk,
+                        Some(Err(ChunkError::Transport(e))) => {
+                            disconnect = Some(e);
+                            break;
+                        }
+                        Some(Err(ChunkError::Decode(message))) => {
+                            yield Err(UcpError::CodecError(message));
+                            return;
+                        }
+                    };
+                    buffer.extend_from_slice(&chunk);
+
+                    // Process complete lines
+                    while let Some(newline_pos) = buffer.iter().position(|&b| b == b'\n') {
+                        let line = buffer.drain(..=newline_pos).collect::<Vec<_>>();
+                        let line_str = match std::str::from_utf8(&line[..line.len().saturating_sub(1)]) {
+                            Ok(s) => s,
+                            Err(_) => {
+                                yield Err(UcpError::ServerError {
+                                    status: None,
+                                    code: None,
+                                    message: "Invalid UTF-8 in response".to_string(),
+                                });
+                                continue;
+                            }
+                        };
+
+                        if !line_str.trim().is_empty() {
+                            match serde_json::from_str::<MemoryResponse>(line_str) {
+                                Ok(memory_response) => {
+                                    if retry_request.is_some() {
+                                        // Skip results replayed after a reconnect
+                                        if !seen.insert(memory_response.id.clone()) {
+                                            continue;
+                                        }
+                                        last_id = Some(memory_response.id.clone());
+                                    }
+                                    yield client.decrypted(memory_response);
+
+                                    yielded += 1;
+                                    if take == Some(yielded) {
+                                        // Dropping the response closes the connection
+                                        return;
+                                    }
                                 }
+                                Err(e) if ended => yield Err(unterminated_record_error(e, line_str.len())),
+                                Err(e) => yield Err(UcpError::JsonError(e)),
                             }
                         }
                     }
-                    Err(e) => {
-                        yield Err(UcpError::HttpError(e));
+
+                    if ended {
+                        break;
+                    }
+                }
+
+                let Some(e) = disconnect else {
+                    break;
+                };
+
+                let retry = match retry_request.as_ref().and_then(|r| r.try_clone()) {
+                    Some(retry) if reconnects < max_reconnects => retry,
+                    _ => {
+                        yield Err(body_read_error(e));
+                        break;
+                    }
+                };
+
+                reconnects += 1;
+                #[cfg(feature = "tracing")]
+                tracing::warn!(attempt = reconnects, error = %e, "search stream disconnected, reconnecting");
+
+                let retry = match last_id {
+                    Some(ref id) => retry.header("Last-Event-Id", id.as_str()),
+                    None => retry,
+                };
+
+                match client.execute(retry).await {
+                    Ok(next) => response = next,
+                    Err(err) => {
+                        yield Err(err);
                         break;
                     }
                 }
             }
         };
 
-        Ok(stream)
+        Ok(stream)
+    }
+
+    /// Delete memory by ID
+    ///
+    /// When `version` is given it is sent as `If-Match`, and the delete fails
+    /// with `UcpError::Conflict` if the memory has changed since.
+    pub async fn delete_memory(
+        &self,
+        project: &str,
+        memory_id: &str,
+        version: Option<u64>,
+    ) -> Result<()> {
+        let url = self.url(&format!("memory/{}/{}", project, memory_id));
+
+        self.execute(if_match(self.client.delete(&url), version)).await?;
+        Ok(())
+    }
+
+    /// Delete memory by ID and return the deleted entry
+    ///
+    /// Fails with `UcpError::NotFound` if no such memory exists.
+    pub async fn delete_memory_returning(
+        &self,
+        project: &str,
+        memory_id: &str,
+    ) -> Result<MemoryResponse> {
+        let url = self.url(&format!("memory/{}/{}", project, memory_id));
+
+        let response = self
+            .execute(self.client.delete(&url).query(&[("return", "true")]))
+            .await?;
+        self.decrypted(self.handle_response(response).await?)
+    }
+
+    /// Delete several memories of a project in one request
+    ///
+    /// Returns the number of memories the server deleted.
+    pub async fn delete_memories_batch(
+        &self,
+        project: &str,
+        memory_ids: &[String],
+    ) -> Result<u64> {
+        let url = self.url("memory/batch/delete");
+        let body = BatchDeleteRequest {
+            project,
+            ids: memory_ids,
+        };
+
+        let response = self
+            .execute(self.with_body(self.client.post(&url), &body)?)
+            .await?;
+
+        let delete_response: BatchDeleteResponse = self.handle_response(response).await?;
+        Ok(delete_response.deleted)
+    }
+
+    /// Delete every memory in a project, keeping the project itself
+    ///
+    /// Returns the number of memories removed. `confirm` must be `true`;
+    /// otherwise this fails with `UcpError::ConfigError` without contacting
+    /// the server, guarding against accidental calls.
+    pub async fn clear_project(&self, project: &str, confirm: bool) -> Result<u64> {
+        if !confirm {
+            return Err(UcpError::ConfigError(format!(
+                "refusing to clear project {:?} without confirm = true",
+                project
+            )));
+        }
+
+        let url = self.url(&format!("memory/{}", project));
+
+        let response = self
+            .execute(self.client.delete(&url).query(&[("all", "true")]))
+            .await?;
+
+        let delete_response: BatchDeleteResponse = self.handle_response(response).await?;
+        Ok(delete_response.deleted)
+    }
+
+    /// Add and remove tags on every memory in `project` matching `filter`
+    ///
+    /// The server applies the change in one operation and returns the
+    /// number of memories modified. Tags in both `add` and `remove` end up
+    /// removed. Nothing is sent when both lists are empty.
+    pub async fn retag(
+        &self,
+        project: &str,
+        filter: MemoryFilter,
+        add: Vec<String>,
+        remove: Vec<String>,
+    ) -> Result<u64> {
+        if add.is_empty() && remove.is_empty() {
+            return Ok(0);
+        }
+
+        let url = self.url(&format!("memory/{}/retag", project));
+        let body = RetagRequest {
+            filter: &filter,
+            add: &add,
+            remove: &remove,
+        };
+
+        let response = self
+            .execute(self.with_body(self.client.post(&url), &body)?)
+            .await?;
+
+        let retag_response: RetagResponse = self.handle_response(response).await?;
+        Ok(retag_response.modified)
+    }
+
+    /// Retrieve a page of a session's memories
+    ///
+    /// Memories are ordered by timestamp, ascending unless `query.order` is
+    /// `Order::Desc` (e.g. to load the tail of a long conversation first).
+    /// Pass the returned `next_cursor` back in `query.cursor` for the next
+    /// page.
+    pub async fn get_session(
+        &self,
+        project: &str,
+        session: &str,
+        query: &SessionQuery,
+    ) -> Result<SessionPage> {
+        let url = self.url(&format!("sessions/{}/{}", project, session));
+
+        let response = self.execute(self.client.get(&url).query(query)).await?;
+        let mut page: SessionPage = self.handle_response(response).await?;
+        page.memories = self.decrypted_all(page.memories)?;
+        Ok(page)
+    }
+
+    /// Retrieve the direct children of a memory
+    pub async fn get_children(
+        &self,
+        project: &str,
+        parent_id: &str,
+    ) -> Result<Vec<MemoryResponse>> {
+        let url = self.url(&format!("memory/{}/{}/children", project, parent_id));
+
+        let response = self.execute(self.client.get(&url)).await?;
+        let children_response: MemoriesResponse = self.handle_response(response).await?;
+        self.decrypted_all(children_response.memories)
+    }
+
+    /// Retrieve every chunk of a source document
+    ///
+    /// Returns the memories whose `doc_id` metadata is `doc_id`, ordered by
+    /// their `chunk_index`; see `utils::reassemble_content` to rebuild the
+    /// document's text.
+    pub async fn get_document(&self, project: &str, doc_id: &str) -> Result<Vec<MemoryResponse>> {
+        let url = self.url(&format!("memory/{}/documents/{}", project, doc_id));
+
+        let response = self.execute(self.client.get(&url)).await?;
+        let document_response: MemoriesResponse = self.handle_response(response).await?;
+
+        let mut chunks = self.decrypted_all(document_response.memories)?;
+        sort_by_chunk_index(&mut chunks);
+        Ok(chunks)
+    }
+
+    /// Keep only the `keep_last` most recent memories of a session
+    ///
+    /// Older memories are deleted in one batch; returns the number removed.
+    pub async fn trim_session(
+        &self,
+        project: &str,
+        session: &str,
+        keep_last: usize,
+    ) -> Result<u64> {
+        let mut memories = Vec::new();
+        let mut query = SessionQuery::default();
+        loop {
+            let page = self.get_session(project, session, &query).await?;
+            memories.extend(page.memories);
+            match page.next_cursor {
+                Some(cursor) => query.cursor = Some(cursor),
+                None => break,
+            }
+        }
+
+        if memories.len() <= keep_last {
+            return Ok(0);
+        }
+
+        memories.sort_by_key(|memory| std::cmp::Reverse(memory.timestamp));
+        let stale: Vec<String> = memories
+            .into_iter()
+            .skip(keep_last)
+            .map(|memory| memory.id)
+            .collect();
+
+        self.delete_memories_batch(project, &stale).await
+    }
+
+    /// List all projects available
+    ///
+    /// Convenience wrapper that follows every page of `list_projects_stream`.
+    pub async fn list_projects(&self) -> Result<Vec<String>> {
+        self.list_projects_stream().collect().await
+    }
+
+    /// Fetch one page of projects, starting at `cursor` (or the beginning)
+    ///
+    /// Pages hold up to `PROJECTS_PAGE_LIMIT` (100) projects.
+    pub async fn list_projects_page(&self, cursor: Option<&str>) -> Result<ProjectsPage> {
+        let url = self.url("projects");
+
+        let mut request = self
+            .client
+            .get(&url)
+            .query(&[("limit", PROJECTS_PAGE_LIMIT)]);
+        if let Some(cursor) = cursor {
+            request = request.query(&[("cursor", cursor)]);
+        }
+
+        let response = self.execute(request).await?;
+        self.handle_response(response).await
+    }
+
+    /// Fetch recent changes to a project's memories, newest first
+    ///
+    /// Returns up to `limit` events, only those after `since` (Unix time)
+    /// when given. Unlike a live subscription, this reads stored history.
+    pub async fn recent_activity(
+        &self,
+        project: &str,
+        limit: u32,
+        since: Option<u64>,
+    ) -> Result<Vec<ActivityEvent>> {
+        let url = self.url(&format!("projects/{}/activity", project));
+
+        let mut request = self.client.get(&url).query(&[("limit", limit)]);
+        if let Some(since) = since {
+            request = request.query(&[("since", since)]);
+        }
+
+        let response = self.execute(request).await?;
+        let activity: ActivityResponse = self.handle_response(response).await?;
+
+        let mut events = activity.events;
+        events.sort_by_key(|event| std::cmp::Reverse(event.timestamp));
+        Ok(events)
+    }
+
+    /// Count a project's memories grouped by the value of a metadata field
+    ///
+    /// The server computes the counts, so nothing but the buckets is
+    /// transferred. With a `filter`, only matching memories are counted.
+    /// Memories without `group_by` in their metadata are counted under a
+    /// `null` key. Buckets are ordered by count, highest first.
+    pub async fn aggregate(
+        &self,
+        project: &str,
+        group_by: &str,
+        filter: Option<MemoryFilter>,
+    ) -> Result<Vec<AggBucket>> {
+        let url = self.url(&format!("projects/{}/aggregate", project));
+        let body = AggregateRequest {
+            group_by,
+            filter: filter.as_ref(),
+        };
+
+        let response = self
+            .execute_read(self.with_body(self.client.post(&url), &body)?)
+            .await?;
+        let aggregate: AggregateResponse = self.handle_response(response).await?;
+
+        let mut buckets = aggregate.buckets;
+        buckets.sort_by_key(|bucket| std::cmp::Reverse(bucket.count));
+        Ok(buckets)
+    }
+
+    /// Stream all project names, fetching further pages lazily
+    pub fn list_projects_stream(&self) -> impl Stream<Item = Result<String>> + '_ {
+        async_stream::try_stream! {
+            let mut cursor: Option<String> = None;
+
+            loop {
+                let page = self.list_projects_page(cursor.as_deref()).await?;
+                for project in page.projects {
+                    yield project;
+                }
+
+                match page.next_cursor {
+                    Some(next) => cursor = Some(next),
+                    None => break,
+                }
+            }
+        }
+    }
+
+    /// Fetch one page of the memories in `project` matching `filter`,
+    /// starting at `cursor` (or the beginning)
+    ///
+    /// Pages hold up to `MEMORIES_PAGE_LIMIT` (100) memories.
+    pub async fn list_memories_page(
+        &self,
+        project: &str,
+        filter: &MemoryFilter,
+        cursor: Option<&str>,
+    ) -> Result<MemoriesPage> {
+        self.fetch_memories_page(project, filter, cursor, false).await
+    }
+
+    /// Fetch one page of memories, asking for their embeddings if
+    /// `include_embeddings`
+    async fn fetch_memories_page(
+        &self,
+        project: &str,
+        filter: &MemoryFilter,
+        cursor: Option<&str>,
+        include_embeddings: bool,
+    ) -> Result<MemoriesPage> {
+        let url = self.url(&format!("memory/{}/list", project));
+
+        let body = ListMemoriesRequest {
+            filter,
+            limit: MEMORIES_PAGE_LIMIT,
+            cursor,
+            include_embeddings,
+        };
+        let request = self.with_body(self.client.post(&url), &body)?;
+        let response = self.execute_read(request).await?;
+        let mut page: MemoriesPage = self.handle_response(response).await?;
+        page.memories = self.decrypted_all(page.memories)?;
+        Ok(page)
+    }
+
+    /// Stream every memory in `project` matching `filter`, fetching further
+    /// pages lazily
+    ///
+    /// Only one page is held at a time, so this suits exports and
+    /// reindexing of projects too large to load at once.
+    pub fn iter_memories<'a>(
+        &'a self,
+        project: &'a str,
+        filter: MemoryFilter,
+    ) -> impl Stream<Item = Result<MemoryResponse>> + 'a {
+        self.memories_stream(project, filter, false)
+    }
+
+    /// Stream every memory in `project` matching `filter`, asking for
+    /// their embeddings if `include_embeddings`
+    fn memories_stream<'a>(
+        &'a self,
+        project: &'a str,
+        filter: MemoryFilter,
+        include_embeddings: bool,
+    ) -> impl Stream<Item = Result<MemoryResponse>> + 'a {
+        async_stream::try_stream! {
+            let mut cursor: Option<String> = None;
+
+            loop {
+                let page = self
+                    .fetch_memories_page(project, &filter, cursor.as_deref(), include_embeddings)
+                    .await?;
+                for memory in page.memories {
+                    yield memory;
+                }
+
+                match page.next_cursor {
+                    Some(next) => cursor = Some(next),
+                    None => break,
+                }
+            }
+        }
+    }
+
+    /// Find groups of near-identical memories in `project`
+    ///
+    /// Computed client-side: every memory is listed (see `iter_memories`)
+    /// and the embeddings are compared pairwise with `group_duplicates`,
+    /// so both the transfer and the comparison grow with the project, the
+    /// latter quadratically; for large projects, narrow the scan with
+    /// `iter_memories` and a `MemoryFilter` instead. The listing asks for
+    /// embeddings; a memory returned without one fails the call rather
+    /// than being silently left out. Remove unwanted members with
+    /// `delete_memories_batch`.
+    pub async fn find_duplicates(
+        &self,
+        project: &str,
+        similarity_threshold: f64,
+    ) -> Result<Vec<DuplicateGroup>> {
+        let memories: Vec<MemoryResponse> = self
+            .memories_stream(project, MemoryFilter::new(), true)
+            .collect::<Result<_>>()
+            .await?;
+        if let Some(memory) = memories.iter().find(|memory| memory.embedding.is_none()) {
+            return Err(UcpError::ServerError {
+                status: None,
+                code: None,
+                message: format!("memory {} was listed without an embedding", memory.id),
+            });
+        }
+        Ok(group_duplicates(&memories, similarity_threshold))
+    }
+
+    /// Get statistics for a project
+    pub async fn get_stats(&self, project: &str) -> Result<ProjectStats> {
+        let url = self.url(&format!("stats/{}", project));
+
+        let response = self.execute(self.client.get(&url)).await?;
+        self.handle_response(response).await
+    }
+
+    /// Get statistics for several projects, at most `max_concurrency` at
+    /// a time
+    ///
+    /// Returns one result per project, in the order given, so a failure
+    /// for one project does not hide the others. A fetch whose task was
+    /// cancelled (e.g. by runtime shutdown) yields `UcpError::Cancelled`.
+    pub async fn get_stats_many(
+        &self,
+        projects: &[String],
+        max_concurrency: usize,
+    ) -> Vec<(String, Result<ProjectStats>)> {
+        let semaphore = Arc::new(tokio::sync::Semaphore::new(max_concurrency.max(1)));
+        let mut tasks = tokio::task::JoinSet::new();
+        // Batch index of each task, to report tasks that never finished
+        let mut task_indices = HashMap::with_capacity(projects.len());
+        for (index, project) in projects.iter().enumerate() {
+            let (client, project, semaphore) = (self.clone(), project.clone(), semaphore.clone());
+            let task = tasks.spawn(async move {
+                let _permit = semaphore.acquire_owned().await.expect("semaphore is never closed");
+                let stats = client.get_stats(&project).await;
+                (index, project, stats)
+            });
+            task_indices.insert(task.id(), index);
+        }
+
+        let mut results = Vec::with_capacity(projects.len());
+        while let Some(joined) = tasks.join_next().await {
+            match joined {
+                Ok(result) => results.push(result),
+                Err(e) if e.is_panic() => std::panic::resume_unwind(e.into_panic()),
+                // Cancelled, e.g. by a runtime shutting down
+                Err(e) => {
+                    let index = task_indices[&e.id()];
+                    results.push((index, projects[index].clone(), Err(UcpError::Cancelled)));
+                }
+            }
+        }
+
+        results.sort_by_key(|(index, _, _)| *index);
+        results
+            .into_iter()
+            .map(|(_, project, stats)| (project, stats))
+            .collect()
+    }
+
+    /// Fetch the server's limits and supported features
+    ///
+    /// The result is remembered, so later stores can check embeddings
+    /// against the server's `embedding_dim`.
+    pub async fn get_capabilities(&self) -> Result<ServerCapabilities> {
+        let url = self.url("capabilities");
+
+        let response = self.execute(self.client.get(&url)).await?;
+        let capabilities: ServerCapabilities = self.handle_response(response).await?;
+
+        *self.capabilities.lock().unwrap_or_else(|e| e.into_inner()) = Some(capabilities.clone());
+        Ok(capabilities)
+    }
+
+    /// Check that `dim`-dimensional embeddings match the server's index
+    ///
+    /// Fetches fresh capabilities (remembering them like
+    /// `get_capabilities`) and fails with a `ConfigError` naming both
+    /// dimensions on a mismatch. Succeeds when the server does not report
+    /// an embedding dimension.
+    pub async fn assert_embedding_dim(&self, dim: usize) -> Result<()> {
+        let capabilities = self.get_capabilities().await?;
+        check_embedding_dim(dim, capabilities.embedding_dim)
+    }
+
+    /// Negotiate the API version with the server
+    ///
+    /// Fetches `/api/versions` and switches this client to the highest
+    /// version both sides support. Clones made earlier keep their version.
+    pub async fn negotiate_version(&mut self) -> Result<ApiVersion> {
+        let url = format!("{}/api/versions", self.config.base_url);
+
+        let response = self.execute(self.client.get(&url)).await?;
+        let versions: VersionsResponse = self.handle_response(response).await?;
+
+        let version = SUPPORTED_API_VERSIONS
+            .iter()
+            .find(|supported| versions.versions.iter().any(|v| v == supported.as_str()))
+            .cloned()
+            .ok_or_else(|| {
+                UcpError::ConfigError(format!(
+                    "No mutually supported API version (server offers {:?})",
+                    versions.versions
+                ))
+            })?;
+
+        self.config.api_version = version.clone();
+        Ok(version)
+    }
+
+    /// Health check endpoint
+    pub async fn health_check(&self) -> Result<HealthStatus> {
+        let url = self.url("health");
+
+        let response = self.execute(self.client.get(&url)).await?;
+        self.handle_response(response).await
+    }
+
+    /// Search for a known canary memory and report where it ranks
+    ///
+    /// Stores the canary first unless `canary.memory_id` is set, then
+    /// searches for its text with no score threshold. Run periodically,
+    /// this tracks relevance drift: a healthy index returns the canary as
+    /// the top hit. Results are matched by ID, or by content when the
+    /// server stored the canary without assigning an ID yet.
+    pub async fn probe_retrieval(&self, project: &str, canary: &ProbeSpec) -> Result<ProbeResult> {
+        let memory_id = match canary.memory_id {
+            Some(ref id) => id.clone(),
+            None => {
+                let request = MemoryRequest::new(project, canary.session.as_str(), canary.content.as_str());
+                self.store_memory(request).await?.id
+            }
+        };
+
+        let query = VectorQuery::builder(project, canary.query.as_deref().unwrap_or(&canary.content))
+            .limit(canary.limit)
+            .threshold(0.0)
+            .build();
+        let results = self.search_memories(query).await?;
+
+        let position = results.iter().position(|memory| {
+            if memory_id.is_empty() {
+                memory.content == canary.content
+            } else {
+                memory.id == memory_id
+            }
+        });
+
+        Ok(ProbeResult {
+            memory_id,
+            rank: position.map(|index| index + 1),
+            score: position.and_then(|index| results[index].score),
+        })
+    }
+
+    /// Moving average of the time until response headers arrived for
+    /// requests with the given HTTP method, shared across client clones
+    pub fn observed_latency(&self, method: &Method) -> Option<Duration> {
+        self.latency.average(method)
+    }
+
+    /// Open a connection to the server ahead of the first real request
+    ///
+    /// Sends a `HEAD` request to the health endpoint so DNS resolution and
+    /// connection (and TLS) setup happen now; the connection then stays in
+    /// the client's pool, shared by its clones, for later requests. This is
+    /// best-effort: failures are ignored, and idle connections may still
+    /// be closed by either side before they are reused.
+    pub async fn warmup(&self) {
+        let url = self.url("health");
+        let _ = self.send(self.client.head(&url)).await;
+    }
+
+    /// Check that the server is reachable and accepts the API key
+    ///
+    /// Sends one authenticated request without retries. Fails with
+    /// `UcpError::ConnectionError` if the server cannot be reached and
+    /// `UcpError::AuthenticationError` if the key is rejected.
+    pub async fn verify(&self) -> Result<()> {
+        let url = self.url("auth/verify");
+
+        match self.execute_once(self.client.get(&url)).await {
+            Ok(_) => Ok(()),
+            Err(UcpError::HttpError(e)) if e.is_connect() => Err(UcpError::ConnectionError(e)),
+            Err(err) => Err(err),
+        }
+    }
+
+    /// Fetch the status of a background job from its status URL
+    pub(crate) async fn job_status(&self, status_url: &str) -> Result<JobStatus> {
+        let response = self.execute(self.client.get(status_url)).await?;
+        self.handle_response(response).await
+    }
+
+    /// Wait for `duration` on the configured clock
+    pub(crate) async fn sleep(&self, duration: Duration) {
+        self.clock.sleep(duration).await
+    }
+
+    /// Build a job handle from a `202 Accepted` response
+    ///
+    /// The job ID comes from a `job_id` body field or, failing that, the
+    /// last segment of the `Location` header. Status is polled at the
+    /// `Location` if given, else at `jobs/{id}`.
+    async fn accepted_job(&self, response: Response) -> Result<JobHandle> {
+        let location = response
+            .headers()
+            .get(reqwest::header::LOCATION)
+            .and_then(|value| value.to_str().ok())
+            .map(|location| {
+                if location.starts_with('/') {
+                    format!("{}{}", self.config.base_url, location)
+                } else {
+                    location.to_string()
+                }
+            });
+        let accepted: Option<JobAccepted> = self.handle_optional_response(response).await?;
+
+        let id = accepted
+            .and_then(|accepted| accepted.job_id)
+            .or_else(|| {
+                let segment = location.as_deref()?.trim_end_matches('/').rsplit('/').next()?;
+                Some(segment.to_string())
+            })
+            .ok_or_else(|| UcpError::ServerError {
+                status: None,
+                code: None,
+                message: "accepted job has neither a job ID nor a Location".to_string(),
+            })?;
+        let status_url = location.unwrap_or_else(|| self.url(&format!("jobs/{}", id)));
+
+        Ok(JobHandle::new(self.clone(), id, status_url))
+    }
+
+    /// Build the URL of an API endpoint for the configured version
+    fn url(&self, path: &str) -> String {
+        format!(
+            "{}/api/{}/{}",
+            self.config.base_url, self.config.api_version, path
+        )
+    }
+
+    /// Send a request, retrying retryable failures with backoff
+    ///
+    /// Returns the response only if it has a success status; error statuses
+    /// are converted into `UcpError`s. Only idempotent methods are retried,
+    /// unless the request carries an `Idempotency-Key` header or
+    /// `RetryPolicy::retry_non_idempotent` is set.
+    async fn execute(&self, request: RequestBuilder) -> Result<Response> {
+        let replayable = self.config.retry_policy.retry_non_idempotent
+            || request
+                .try_clone()
+                .and_then(|request| request.build().ok())
+                .is_some_and(|request| {
+                    is_idempotent(request.method())
+                        || request.headers().contains_key(IDEMPOTENCY_KEY_HEADER)
+                });
+        self.execute_with(request, replayable).await
+    }
+
+    /// Send a read-only request that goes out as POST, such as a search,
+    /// retrying it like an idempotent method
+    async fn execute_read(&self, request: RequestBuilder) -> Result<Response> {
+        self.execute_with(request, true).await
+    }
+
+    /// Send a request, retrying retryable failures only if `replayable`
+    async fn execute_with(&self, request: RequestBuilder, replayable: bool) -> Result<Response> {
+        let policy = &self.config.retry_policy;
+        let mut attempt = 0;
+        // A rejected token is refreshed at most once per call
+        let mut refreshed = false;
+
+        loop {
+            let result = match request.try_clone() {
+                Some(request) => match self.send(request).await {
+                    Ok(response) if response.status().is_success() => Ok(response),
+                    Ok(response)
+                        if response.status() == reqwest::StatusCode::UNAUTHORIZED && !refreshed =>
+                    {
+                        match self.config.token_provider {
+                            Some(ref provider) => {
+                                refreshed = true;
+                                provider.refresh().await?;
+                                continue;
+                            }
+                            None => Err(self.handle_error_response(response).await),
+                        }
+                    }
+                    Ok(response) => Err(self.handle_error_response(response).await),
+                    Err(e) => Err(e),
+                },
+                // Bodies that cannot be cloned are sent exactly once
+                None => return self.execute_once(request).await,
+            };
+
+            match result {
+                Ok(response) => {
+                    self.retry_budget.deposit();
+                    return Ok(response);
+                }
+                Err(err) => {
+                    // Honor the server's Retry-After, but leave waits longer
+                    // than any backoff to the caller
+                    let delay = match err {
+                        UcpError::ServiceUnavailable {
+                            retry_after: Some(retry_after),
+                            ..
+                        } => retry_after,
+                        _ => backoff_delay(policy, attempt),
+                    };
+
+                    if !replayable
+                        || attempt >= self.config.max_retries
+                        || delay > Duration::from_millis(policy.max_backoff_ms)
+                        || !policy.should_retry(&err, attempt + 1)
+                        || !self.retry_budget.try_withdraw()
+                    {
+                        return Err(err);
+                    }
+
+                    self.clock.sleep(delay).await;
+                    attempt += 1;
+                }
+            }
+        }
+    }
+
+    /// Send a request without retries
+    ///
+    /// A `401` still has the token refreshed and the request re-sent once,
+    /// if its body can be cloned.
+    async fn execute_once(&self, request: RequestBuilder) -> Result<Response> {
+        let resend = request.try_clone();
+        let mut response = self.send(request).await?;
+        if let (reqwest::StatusCode::UNAUTHORIZED, Some(provider), Some(resend)) =
+            (response.status(), &self.config.token_provider, resend)
+        {
+            provider.refresh().await?;
+            response = self.send(resend).await?;
+        }
+
+        if response.status().is_success() {
+            Ok(response)
+        } else {
+            Err(self.handle_error_response(response).await)
+        }
+    }
+
+    /// Send a single request, logging it at debug level when enabled
+    ///
+    /// Latency is tracked under the request's own method, even when
+    /// `method_override` sends it as POST.
+    async fn send(&self, request: RequestBuilder) -> Result<Response> {
+        // Held until the response headers arrive
+        let priority = self.options.priority.unwrap_or_default();
+        let _permit = match self.concurrency {
+            Some(ref limiter) => Some(limiter.acquire(priority).await),
+            None => None,
+        };
+
+        let (client, request) = request.build_split();
+        let mut request = request?;
+        let method = request.method().clone();
+
+        if let Some(ref provider) = self.config.token_provider {
+            let token = provider.token().await?;
+            let (name, value) = auth_header(&self.config.auth_header, &token)?;
+            request.headers_mut().insert(name, value);
+        }
+
+        if let Some(priority) = self.options.priority {
+            request.headers_mut().insert(
+                PRIORITY_HEADER,
+                reqwest::header::HeaderValue::from_static(priority.as_str()),
+            );
+        }
+
+        if self.config.method_override {
+            let overridden = match method {
+                Method::PATCH => Some("PATCH"),
+                Method::DELETE => Some("DELETE"),
+                _ => None,
+            };
+            if let Some(overridden) = overridden {
+                request.headers_mut().insert(
+                    METHOD_OVERRIDE_HEADER,
+                    reqwest::header::HeaderValue::from_static(overridden),
+                );
+                *request.method_mut() = Method::POST;
+            }
+        }
+
+        if let Some(ref settings) = self.config.adaptive_timeout {
+            if let Some(latency) = self.latency.average(&method) {
+                *request.timeout_mut() = Some(adaptive_timeout(settings, latency));
+            }
+        }
+
+        #[cfg(feature = "tracing")]
+        {
+            crate::propagation::inject_trace_context(&mut request);
+            if tracing::enabled!(tracing::Level::DEBUG) {
+                crate::logging::log_request(&request, self.config.redact_auth_in_logs);
+            }
+        }
+
+        let started = self.clock.now();
+        let response = client.execute(request).await?;
+        self.latency
+            .record(&method, self.clock.now().saturating_duration_since(started));
+        Ok(response)
+    }
+
+    /// Apply the configured normalizer, then redactor, to the request
+    /// content
+    fn rewrite_content(&self, request: &mut MemoryRequest) {
+        if let Some(ref normalizer) = self.config.content_normalizer {
+            request.content = normalizer(&request.content);
+        }
+        if let Some(ref redactor) = self.config.redactor {
+            request.content = redactor(&request.content);
+        }
+    }
+
+    /// Encrypt the request content with the configured cipher
+    fn encrypt_content(&self, request: &mut MemoryRequest) -> Result<()> {
+        if let Some(ref cipher) = self.config.content_cipher {
+            request.content = cipher.encrypt(&request.content)?;
+        }
+        Ok(())
+    }
+
+    /// Decrypt a received memory's content with the configured cipher
+    fn decrypted(&self, mut memory: MemoryResponse) -> Result<MemoryResponse> {
+        if let Some(ref cipher) = self.config.content_cipher {
+            memory.content = cipher.decrypt(&memory.content)?;
+        }
+        Ok(memory)
+    }
+
+    /// Decrypt the content of each received memory
+    fn decrypted_all(&self, memories: Vec<MemoryResponse>) -> Result<Vec<MemoryResponse>> {
+        memories
+            .into_iter()
+            .map(|memory| self.decrypted(memory))
+            .collect()
+    }
+
+    /// Fill in a missing embedding with the configured embedder
+    async fn embed_content(&self, request: &mut MemoryRequest) -> Result<()> {
+        if let (Some(embedder), None) = (&self.config.embedder, &request.embedding) {
+            let embedding = embedder.embed(&request.content).await?;
+            check_embedding_dim(embedding.len(), self.cached_embedding_dim())?;
+            request.embedding = Some(embedding);
+        }
+        Ok(())
+    }
+
+    /// Embedding dimension from the last fetched capabilities, if any
+    fn cached_embedding_dim(&self) -> Option<u32> {
+        self.capabilities
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .as_ref()
+            .and_then(|capabilities| capabilities.embedding_dim)
+    }
+
+    /// Check a memory request's invariants in strict mode, and its
+    /// embedding against the server's embedding dimension when known
+    fn validate_memory_request(&self, request: &MemoryRequest) -> Result<()> {
+        if self.config.strict {
+            crate::validate::memory_request(request)?;
+        }
+
+        match request.embedding {
+            Some(ref embedding) => check_embedding_dim(embedding.len(), self.cached_embedding_dim()),
+            None => Ok(()),
+        }
+    }
+
+    /// Merge `default_search_filter` into the query's metadata filter,
+    /// keeping keys the query sets itself
+    fn with_default_filter(&self, mut query: VectorQuery) -> VectorQuery {
+        if let Some(ref defaults) = self.config.default_search_filter {
+            let filter = query.metadata_filter.get_or_insert_with(HashMap::new);
+            for (key, value) in defaults {
+                filter.entry(key.clone()).or_insert_with(|| value.clone());
+            }
+        }
+        query
+    }
+
+    /// Check a search query's invariants in strict mode
+    fn validate_query(&self, query: &VectorQuery) -> Result<()> {
+        if self.config.strict {
+            crate::validate::vector_query(query)?;
+        }
+
+        // Query vectors must share a dimension, and match the server's
+        // when known
+        if let Some(ref embeddings) = query.embeddings {
+            let Some((first, _)) = embeddings.first() else {
+                return Err(UcpError::ConfigError(
+                    "query embeddings are set but empty".to_string(),
+                ));
+            };
+            if let Some((index, (vector, _))) = embeddings
+                .iter()
+                .enumerate()
+                .find(|(_, (vector, _))| vector.len() != first.len())
+            {
+                return Err(UcpError::ConfigError(format!(
+                    "query embedding {} has {} dimensions, but embedding 0 has {}",
+                    index,
+                    vector.len(),
+                    first.len()
+                )));
+            }
+            check_embedding_dim(first.len(), self.cached_embedding_dim())?;
+        }
+        Ok(())
+    }
+
+    /// Pass the request to the sampling hook with probability `sample_rate`
+    fn sample_store(&self, request: &MemoryRequest) {
+        if let Some(ref hook) = self.config.on_store_sampled {
+            let rate = self.config.sample_rate;
+            if rate >= 1.0 || (rate > 0.0 && rand::random::<f32>() < rate) {
+                hook(request);
+            }
+        }
+    }
+
+    /// Attach a body encoded with the configured wire codec, and its
+    /// `Content-Type`
+    fn with_body<T: Serialize>(
+        &self,
+        request: RequestBuilder,
+        value: &T,
+    ) -> Result<RequestBuilder> {
+        let body = self.codec.encode(&serde_json::to_value(value)?)?;
+        Ok(request
+            .header(reqwest::header::CONTENT_TYPE, self.codec.content_type())
+            .body(body))
+    }
+
+    /// Generic response handler
+    async fn handle_response<T>(&self, response: Response) -> Result<T>
+    where
+        T: for<'de> Deserialize<'de>,
+    {
+        let status = response.status();
+        
+        if status.is_success() {
+            let body = read_text_body(response).await?;
+            self.decode_body(&body)
+        } else {
+            Err(self.handle_error_response(response).await)
+        }
+    }
+
+    /// Response handler for endpoints that may succeed with an empty body
+    ///
+    /// Returns `None` for empty (or whitespace-only) success bodies such as
+    /// `204 No Content` or a bare `202 Accepted`.
+    async fn handle_optional_response<T>(&self, response: Response) -> Result<Option<T>>
+    where
+        T: for<'de> Deserialize<'de>,
+    {
+        if !response.status().is_success() {
+            return Err(self.handle_error_response(response).await);
+        }
+
+        let body = read_text_body(response).await?;
+        if body.iter().all(u8::is_ascii_whitespace) {
+            Ok(None)
+        } else {
+            self.decode_body(&body).map(Some)
+        }
+    }
+
+    /// Decode a response body with the configured wire codec
+    fn decode_body<T>(&self, body: &[u8]) -> Result<T>
+    where
+        T: for<'de> Deserialize<'de>,
+    {
+        #[cfg(feature = "tracing")]
+        crate::logging::log_response_body(|| body_snippet(&String::from_utf8_lossy(body)));
+
+        self.codec
+            .decode(body)
+            .and_then(|value| serde_json::from_value::<T>(value).map_err(UcpError::from))
+            .map_err(|e| match e {
+                UcpError::JsonError(source) => UcpError::DeserializationError {
+                    source,
+                    body_snippet: body_snippet(&String::from_utf8_lossy(body)),
+                },
+                other => other,
+            })
+    }
+
+    /// Handle error responses
+    async fn handle_error_response(&self, response: Response) -> UcpError {
+        let status = response.status();
+        let retry_after = retry_after(&response);
+        // 404 bodies are read, as their code can tell which resource is missing
+        if status.as_u16() != 404 {
+            if let Some(error) = status_error(status.as_u16()) {
+                return error;
+            }
+        }
+
+        let error_text = response
+            .text()
+            .await
+            .unwrap_or_else(|_| format!("HTTP {} error", status));
+
+        #[cfg(feature = "tracing")]
+        crate::logging::log_response_body(|| body_snippet(&error_text));
+
+        // Try to parse as structured error
+        let parsed = serde_json::from_str::<ErrorResponse>(&error_text).ok();
+        match error_from_parts(Some(status.as_u16()), parsed, error_text) {
+            UcpError::ServiceUnavailable { message, .. } => UcpError::ServiceUnavailable {
+                retry_after,
+                message,
+            },
+            error => error,
+        }
+    }
+}
+
+/// Whether repeating a request with `method` has the same effect as sending
+/// it once
+fn is_idempotent(method: &Method) -> bool {
+    matches!(
+        *method,
+        Method::GET | Method::HEAD | Method::PUT | Method::DELETE | Method::OPTIONS
+    )
+}
+
+/// Translate a redirect policy into reqwest's
+///
+/// reqwest drops only the `Authorization` header (and cookies) on redirects
+/// to another origin. When `auth` sends the key in any other header,
+/// `Limited` therefore refuses cross-origin redirects like `SameHostOnly`,
+/// so the key never reaches another host.
+fn redirect_policy(policy: RedirectPolicy, auth: &AuthHeaderStyle) -> reqwest::redirect::Policy {
+    let (name, _) = auth.header("");
+    let stripped_by_reqwest = name.eq_ignore_ascii_case(reqwest::header::AUTHORIZATION.as_str());
+
+    match policy {
+        RedirectPolicy::None => reqwest::redirect::Policy::none(),
+        RedirectPolicy::Limited(max) if stripped_by_reqwest => {
+            reqwest::redirect::Policy::limited(max)
+        }
+        RedirectPolicy::Limited(max) => reqwest::redirect::Policy::custom(move |attempt| {
+            if attempt.previous().len() >= max {
+                return attempt.error(format!("more than {} redirects", max));
+            }
+            follow_same_origin(attempt)
+        }),
+        RedirectPolicy::SameHostOnly => reqwest::redirect::Policy::custom(|attempt| {
+            if attempt.previous().len() > MAX_REDIRECTS {
+                return attempt.error(format!("more than {} redirects", MAX_REDIRECTS));
+            }
+            follow_same_origin(attempt)
+        }),
+    }
+}
+
+/// Follow a redirect within the origin of the original request, failing on
+/// any redirect elsewhere
+fn follow_same_origin(attempt: reqwest::redirect::Attempt<'_>) -> reqwest::redirect::Action {
+    let same_origin = attempt
+        .previous()
+        .first()
+        .is_some_and(|original| original.origin() == attempt.url().origin());
+    if same_origin {
+        attempt.follow()
+    } else {
+        let message = format!("redirect to another host blocked: {}", attempt.url());
+        attempt.error(message)
+    }
+}
+
+/// Header carrying `credential` in the configured style, marked sensitive
+/// so debug logs mask it
+fn auth_header(
+    style: &AuthHeaderStyle,
+    credential: &str,
+) -> Result<(reqwest::header::HeaderName, reqwest::header::HeaderValue)> {
+    let (name, value) = style.header(credential);
+    let name = reqwest::header::HeaderName::from_bytes(name.as_bytes())
+        .map_err(|_| UcpError::ConfigError(format!("Invalid auth header name {:?}", name)))?;
+    let mut value = reqwest::header::HeaderValue::from_str(&value)
+        .map_err(|_| UcpError::ConfigError("Invalid API key format".to_string()))?;
+    value.set_sensitive(true);
+    Ok((name, value))
+}
+
+/// Error for statuses that map to a dedicated variant regardless of body
+fn status_error(status: u16) -> Option<UcpError> {
+    match status {
+        401 => Some(UcpError::AuthenticationError),
+        404 => Some(UcpError::NotFound),
+        412 => Some(UcpError::Conflict),
+        429 => Some(UcpError::RateLimitError),
+        _ => None,
+    }
+}
+
+/// Build an error from a status and a (possibly unparsed) error body
+///
+/// Shared by whole-response errors and per-item errors in batch results;
+/// `fallback` is the message used when the body is not a structured error.
+fn error_from_parts(status: Option<u16>, parsed: Option<ErrorResponse>, fallback: String) -> UcpError {
+    let code = parsed.as_ref().and_then(|e| e.code.as_deref()).map(ErrorCode::from);
+
+    // Keep the code of a missing version so the caller can report which
+    if code != Some(ErrorCode::VersionNotFound) {
+        if let Some(error) = status.and_then(status_error) {
+            return error;
+        }
+    }
+
+    let is_quota = status == Some(507) || code == Some(ErrorCode::QuotaExceeded);
+    if is_quota {
+        let details = parsed.as_ref().and_then(|e| e.details.as_ref());
+        return UcpError::QuotaExceeded {
+            project: details
+                .and_then(|d| d.get("project"))
+                .and_then(|v| v.as_str())
+                .map(str::to_string),
+            limit: details.and_then(|d| d.get("limit")).and_then(|v| v.as_u64()),
+        };
+    }
+
+    if status == Some(503) {
+        let message = match parsed {
+            Some(error_response) => error_response.message,
+            None if fallback.trim().is_empty() => "service unavailable".to_string(),
+            None => fallback.trim().to_string(),
+        };
+        return UcpError::ServiceUnavailable {
+            retry_after: None,
+            message,
+        };
+    }
+
+    match parsed {
+        Some(error_response) => UcpError::ServerError {
+            status,
+            code: error_response.code,
+            message: error_response.message,
+        },
+        None => UcpError::ServerError {
+            status,
+            code: None,
+            message: fallback,
+        },
+    }
+}
+
+/// Delay requested by a `Retry-After` header given in seconds
+///
+/// HTTP-date values are not parsed; the retry loop falls back to its own
+/// backoff for them.
+fn retry_after(response: &Response) -> Option<Duration> {
+    response
+        .headers()
+        .get(reqwest::header::RETRY_AFTER)?
+        .to_str()
+        .ok()?
+        .trim()
+        .parse()
+        .ok()
+        .map(Duration::from_secs)
+}
+
+/// Fail when the server reports an embedding dimension other than `dim`
+fn check_embedding_dim(dim: usize, server_dim: Option<u32>) -> Result<()> {
+    match server_dim {
+        Some(expected) if expected as usize != dim => Err(UcpError::ConfigError(format!(
+            "embedding has {} dimensions, but the server expects {}",
+            dim, expected
+        ))),
+        _ => Ok(()),
+    }
+}
+
+/// Placeholder response for a store accepted without a response body
+fn pending_response(request: MemoryRequest, clock: &dyn Clock) -> MemoryResponse {
+    let timestamp = clock
+        .wall_time()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+
+    MemoryResponse {
+        id: String::new(),
+        content_hash: Some(hash_content(&request.content)),
+        content: request.content,
+        score: None,
+        project: Some(request.project),
+        session: Some(request.session),
+        metadata: request.metadata,
+        tags: request.tags,
+        timestamp,
+        version: None,
+        parent_id: request.parent_id,
+        embedding: request.embedding,
+        expires_at: request.ttl_secs.map(|ttl| timestamp + ttl),
+        archived: false,
+        extra: HashMap::new(),
+    }
+}
+
+/// Read a full response body
+async fn read_body(response: Response) -> Result<Bytes> {
+    response.bytes().await.map_err(body_read_error)
+}
+
+/// Read a full response body, converting it to UTF-8 if the
+/// `Content-Type` names another charset
+async fn read_text_body(response: Response) -> Result<Bytes> {
+    let charset = crate::charset::from_headers(response.headers());
+    let body = read_body(response).await?;
+    crate::charset::to_utf8(body, charset.as_deref())
+}
+
+/// Map a failure while reading a response body, telling body timeouts
+/// apart from timeouts before the response arrived
+fn body_read_error(error: reqwest::Error) -> UcpError {
+    if error.is_timeout() {
+        UcpError::BodyTimeout(error)
+    } else {
+        UcpError::HttpError(error)
+    }
+}
+
+/// Attach an `If-Match` header carrying the memory version, if any
+fn if_match(request: RequestBuilder, version: Option<u64>) -> RequestBuilder {
+    match version {
+        Some(version) => request.header(reqwest::header::IF_MATCH, format!("\"{}\"", version)),
+        None => request,
+    }
+}
+
+/// Truncate a response body to a displayable snippet on a char boundary
+fn body_snippet(body: &str) -> String {
+    if body.len() <= BODY_SNIPPET_LIMIT {
+        return body.to_string();
+    }
+
+    let mut end = BODY_SNIPPET_LIMIT;
+    while !body.is_char_boundary(end) {
+        end -= 1;
+    }
+    format!("{}...", &body[..end])
+}
+
+/// Parse a newline-delimited JSON response body into a stream of values
+fn ndjson_stream<T: DeserializeOwned>(response: Response) -> impl Stream<Item = Result<T>> {
+    async_stream::stream! {
+        let mut buffer = Vec::new();
+        let mut bytes_stream = body_stream(response);
+
+        while let Some(chunk_result) = bytes_stream.next().await {
+            let chunk = match chunk_result {
+                Ok(chunk) => chunk,
+                Err(ChunkError::Transport(e)) => {
+                    yield Err(body_read_error(e));
+                    return;
+                }
+                Err(ChunkError::Decode(message)) => {
+                    yield Err(UcpError::CodecError(message));
+                    return;
+                }
+            };
+            buffer.extend_from_slice(&chunk);
+
+            while let Some(newline_pos) = buffer.iter().position(|&b| b == b'\n') {
+                let line = buffer.drain(..=newline_pos).collect::<Vec<_>>();
+                if line.iter().all(u8::is_ascii_whitespace) {
+                    continue;
+                }
+                yield serde_json::from_slice(&line).map_err(UcpError::JsonError);
+            }
+        }
+
+        // The last line may lack a trailing newline
+        if !buffer.iter().all(u8::is_ascii_whitespace) {
+            yield serde_json::from_slice(&buffer)
+                .map_err(|e| unterminated_record_error(e, buffer.len()));
+        }
+    }
+}
+
+/// Error for an unparseable last line that the stream ended without
+/// terminating: input ending partway through a value means the connection
+/// closed mid-record, anything else is malformed
+fn unterminated_record_error(error: serde_json::Error, len: usize) -> UcpError {
+    if error.is_eof() {
+        UcpError::TruncatedStream(len)
+    } else {
+        UcpError::JsonError(error)
+    }
+}
+
+/// Request body for batch stores
+#[derive(Debug, Serialize)]
+struct BatchStoreRequest<'a> {
+    memories: &'a [MemoryRequest],
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    fail_fast: bool,
+}
+
+/// Response for batch stores
+#[derive(Debug, Deserialize)]
+struct BatchStoreResponse {
+    results: Vec<BatchItem>,
+}
+
+/// Outcome of one memory in a batch store, in submission order
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum BatchItem {
+    Failed { error: ErrorResponse },
+    Stored(Box<MemoryResponse>),
+}
+
+/// Request body for batch deletes
+#[derive(Debug, Serialize)]
+struct BatchDeleteRequest<'a> {
+    project: &'a str,
+    ids: &'a [String],
+}
+
+/// Response for batch deletes and project clears
+#[derive(Debug, Serialize, Deserialize)]
+struct BatchDeleteResponse {
+    deleted: u64,
+}
+
+/// Request body for `retag`
+#[derive(Debug, Serialize)]
+struct RetagRequest<'a> {
+    filter: &'a MemoryFilter,
+    add: &'a [String],
+    remove: &'a [String],
+}
+
+/// Response for `retag`
+#[derive(Debug, Serialize, Deserialize)]
+struct RetagResponse {
+    modified: u64,
+}
+
+/// Request body for `aggregate`
+#[derive(Debug, Serialize)]
+struct AggregateRequest<'a> {
+    group_by: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    filter: Option<&'a MemoryFilter>,
+}
+
+/// Response for `aggregate`
+#[derive(Debug, Serialize, Deserialize)]
+struct AggregateResponse {
+    buckets: Vec<AggBucket>,
+}
+
+/// Response listing a memory's versions
+#[derive(Debug, Serialize, Deserialize)]
+struct MemoryVersionsResponse {
+    versions: Vec<MemoryVersion>,
+}
+
+/// Response listing a project's activity
+#[derive(Debug, Serialize, Deserialize)]
+struct ActivityResponse {
+    events: Vec<ActivityEvent>,
+}
+
+/// Response listing the children of a memory
+#[derive(Debug, Serialize, Deserialize)]
+struct MemoriesResponse {
+    memories: Vec<MemoryResponse>,
+}
+
+/// Response for search operations
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SearchResponse {
+    pub results: Vec<MemoryResponse>,
+    /// Number of matching memories
+    pub total: u64,
+    /// Server time spent on the search, in milliseconds
+    pub took: u64,
+    /// Server time per phase in milliseconds, keyed by phase name such as
+    /// `"embed"`, `"search"`, or `"rerank"`, when the server reports it
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timings: Option<HashMap<String, u64>>,
+}
+
+/// Response for searches projected to IDs and scores
+#[derive(Debug, Deserialize)]
+struct SearchIdsResponse {
+    results: Vec<SearchHit>,
+}
+
+/// A search result carrying only its ID and score
+#[derive(Debug, Deserialize)]
+struct SearchHit {
+    id: String,
+    #[serde(default)]
+    score: Option<f64>,
+}
+
+/// One page of the projects list
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ProjectsPage {
+    pub projects: Vec<String>,
+    /// Cursor for the next page, absent on the last page
+    #[serde(default)]
+    pub next_cursor: Option<String>,
+}
+
+/// One page of a session's memories
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SessionPage {
+    pub memories: Vec<MemoryResponse>,
+    /// Cursor for the next page, absent on the last page
+    #[serde(default)]
+    pub next_cursor: Option<String>,
+}
+
+/// One page of memories matching a `MemoryFilter`
+#[derive(Debug, Serialize, Deserialize)]
+pub struct MemoriesPage {
+    pub memories: Vec<MemoryResponse>,
+    /// Cursor for the next page, absent on the last page
+    #[serde(default)]
+    pub next_cursor: Option<String>,
+}
+
+/// Project statistics
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ProjectStats {
+    pub project: String,
+    pub total_memories: u64,
+    pub total_sessions: u64,
+    pub total_size_bytes: u64,
+    pub created_at: u64,
+    pub last_updated: u64,
+}
+
+/// Server limits and supported features
+///
+/// Limits the server does not report are `None`.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ServerCapabilities {
+    /// Largest accepted memory content in bytes
+    pub max_content_bytes: Option<u64>,
+    /// Dimension of the server's embedding vectors
+    pub embedding_dim: Option<u32>,
+    /// Similarity metrics available for search, e.g. `"cosine"`
+    pub supported_metrics: Vec<String>,
+    /// Largest number of memories accepted in one batch request
+    pub max_batch_size: Option<u32>,
+}
+
+/// Health status response
+#[derive(Debug, Serialize, Deserialize)]
+pub struct HealthStatus {
+    pub status: String,
+    pub version: String,
+    pub uptime: u64,
+    pub memory_usage: HashMap<String, u64>,
+}
+
+/// Request body for `list_memories_page`
+#[derive(Debug, Serialize)]
+struct ListMemoriesRequest<'a> {
+    #[serde(flatten)]
+    filter: &'a MemoryFilter,
+    limit: u32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    cursor: Option<&'a str>,
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    include_embeddings: bool,
+}
+
+/// Request body for `rollback_memory`
+#[derive(Debug, Serialize, Deserialize)]
+struct RollbackRequest {
+    version: u64,
+}
+
+/// Request body for `set_ttl`
+#[derive(Debug, Serialize, Deserialize)]
+struct TtlUpdate {
+    ttl_secs: u64,
+}
+
+/// Response for operations started as background jobs
+#[derive(Debug, Serialize, Deserialize)]
+struct JobAccepted {
+    #[serde(default)]
+    job_id: Option<String>,
+}
+
+/// Versions offered by the server
+#[derive(Debug, Serialize, Deserialize)]
+struct VersionsResponse {
+    versions: Vec<String>,
+}
+
+/// Error response from UCP server
+#[derive(Debug, Serialize, Deserialize)]
+struct ErrorResponse {
+    /// HTTP status of a per-item error in a batch result
+    #[serde(default)]
+    status: Option<u16>,
+    message: String,
+    code: Option<String>,
+    details: Option<HashMap<String, serde_json::Value>>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{ActivityKind, AdaptiveTimeout, Order, Priority, RetryPolicy, SearchCacheConfig, TagMatch};
+    use mockito::Server;
+    use serde_json::json;
+
+    #[tokio::test]
+    async fn test_store_memory() {
+        let mut server = Server::new_async().await;
+        
+        let mock_response = json!({
+            "id": "mem_123",
+            "content": "Test memory content",
+            "score": null,
+            "metadata": {},
+            "tags": ["test"],
+            "timestamp": 1234567890
+        });
+
+        let _m = server.mock("POST", "/api/v1/memory")
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(mock_response.to_string())
+            .create_async()
+            .await;
+
+        let config = UcpConfig {
+            base_url: server.url(),
+            ..Default::default()
+        };
+
+        let client = UcpClient::new(config).unwrap();
+        
+        let request = MemoryRequest {
+            project: "test_project".to_string(),
+            session: "test_session".to_string(),
+            content: "Test memory content".to_string(),
+            metadata: HashMap::new(),
+            tags: vec!["test".to_string()],
+            collection: None,
+            parent_id: None,
+            embedding: None,
+            ttl_secs: None,
+        };
+
+        let response = client.store_memory(request).await.unwrap();
+        assert_eq!(response.id, "mem_123");
+        assert_eq!(response.content, "Test memory content");
+    }
+
+    #[tokio::test]
+    async fn test_store_memory_sampling() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+        use std::sync::Arc;
+
+        let mut server = Server::new_async().await;
+
+        let mock_response = json!({
+            "id": "mem_123",
+            "content": "Test memory content",
+            "score": null,
+            "metadata": {},
+            "tags": [],
+            "timestamp": 1234567890
+        });
+
+        let _m = server.mock("POST", "/api/v1/memory")
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(mock_response.to_string())
+            .expect(4)
+            .create_async()
+            .await;
+
+        let sampled = Arc::new(AtomicUsize::new(0));
+        let counter = sampled.clone();
+
+        let mut config = UcpConfig {
+            base_url: server.url(),
+            sample_rate: 1.0,
+            on_store_sampled: Some(Arc::new(move |_request: &MemoryRequest| {
+                counter.fetch_add(1, Ordering::SeqCst);
+            })),
+            ..Default::default()
+        };
+
+        let request = MemoryRequest {
+            project: "test_project".to_string(),
+            session: "test_session".to_string(),
+            content: "Test memory content".to_string(),
+            metadata: HashMap::new(),
+            tags: vec![],
+            collection: None,
+            parent_id: None,
+            embedding: None,
+            ttl_secs: None,
+        };
+
+        let client = UcpClient::new(config.clone()).unwrap();
+        client.store_memory(request.clone()).await.unwrap();
+        client.store_memory(request.clone()).await.unwrap();
+        assert_eq!(sampled.load(Ordering::SeqCst), 2);
+
+        config.sample_rate = 0.0;
+        let client = UcpClient::new(config).unwrap();
+        client.store_memory(request.clone()).await.unwrap();
+        client.store_memory(request).await.unwrap();
+        assert_eq!(sampled.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn test_store_memory_accepted_without_body() {
+        /// Clock whose wall time is fixed
+        #[derive(Debug)]
+        struct FixedClock;
+
+        impl Clock for FixedClock {
+            fn now(&self) -> std::time::Instant {
+                std::time::Instant::now()
+            }
+
+            fn sleep(&self, duration: Duration) -> crate::clock::Sleep<'_> {
+                Box::pin(tokio::time::sleep(duration))
+            }
+
+            fn wall_time(&self) -> std::time::SystemTime {
+                std::time::UNIX_EPOCH + Duration::from_secs(1_700_000_000)
+            }
+        }
+
+        let mut server = Server::new_async().await;
+
+        let _m = server.mock("POST", "/api/v1/memory")
+            .with_status(202)
+            .create_async()
+            .await;
+
+        let config = UcpConfig {
+            base_url: server.url(),
+            clock: Some(Arc::new(FixedClock)),
+            ..Default::default()
+        };
+
+        let client = UcpClient::new(config).unwrap();
+
+        let request = MemoryRequest {
+            project: "test_project".to_string(),
+            session: "test_session".to_string(),
+            content: "Queued content".to_string(),
+            metadata: HashMap::new(),
+            tags: vec!["queued".to_string()],
+            collection: None,
+            parent_id: None,
+            embedding: None,
+            ttl_secs: Some(60),
+        };
+
+        let response = client.store_memory(request).await.unwrap();
+        assert!(response.is_pending());
+        assert_eq!(response.content, "Queued content");
+        assert_eq!(response.tags, vec!["queued".to_string()]);
+        assert_eq!(response.timestamp, 1_700_000_000);
+        assert_eq!(response.expires_at, Some(1_700_000_060));
+    }
+
+    #[tokio::test]
+    async fn test_delete_memory_no_content() {
+        let mut server = Server::new_async().await;
+
+        let m = server.mock("DELETE", "/api/v1/memory/test_project/mem_123")
+            .with_status(204)
+            .create_async()
+            .await;
+
+        let config = UcpConfig {
+            base_url: server.url(),
+            ..Default::default()
+        };
+
+        let client = UcpClient::new(config).unwrap();
+        client.delete_memory("test_project", "mem_123", None).await.unwrap();
+        m.assert_async().await;
+    }
+
+    #[tokio::test]
+    async fn test_method_override_tunnels_patch_and_delete() {
+        let mut server = Server::new_async().await;
+
+        let delete = server.mock("POST", "/api/v1/memory/test_project/mem_123")
+            .match_header("x-http-method-override", "DELETE")
+            .with_status(204)
+            .create_async()
+            .await;
+        let update = server.mock("POST", "/api/v1/memory/test_project/mem_456")
+            .match_header("x-http-method-override", "PATCH")
+            .match_body(mockito::Matcher::Json(json!({ "content": "Updated content" })))
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(json!({
+                "id": "mem_456",
+                "content": "Updated content",
+                "metadata": {},
+                "tags": [],
+                "timestamp": 1234567890
+            }).to_string())
+            .create_async()
+            .await;
+
+        let config = UcpConfig {
+            base_url: server.url(),
+            method_override: true,
+            ..Default::default()
+        };
+
+        let client = UcpClient::new(config).unwrap();
+        client.delete_memory("test_project", "mem_123", None).await.unwrap();
+        let update_body = MemoryUpdate {
+            content: Some("Updated content".to_string()),
+            ..Default::default()
+        };
+        client
+            .update_memory("test_project", "mem_456", update_body, None)
+            .await
+            .unwrap();
+
+        delete.assert_async().await;
+        update.assert_async().await;
+    }
+
+    #[tokio::test]
+    async fn test_retag_filtered_memories() {
+        let mut server = Server::new_async().await;
+
+        let m = server.mock("POST", "/api/v1/memory/test_project/retag")
+            .match_body(mockito::Matcher::Json(json!({
+                "filter": { "tags": ["draft"] },
+                "add": ["reviewed"],
+                "remove": ["draft"]
+            })))
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(json!({ "modified": 12 }).to_string())
+            .expect(1)
+            .create_async()
+            .await;
+
+        let config = UcpConfig {
+            base_url: server.url(),
+            ..Default::default()
+        };
+
+        let client = UcpClient::new(config).unwrap();
+        let filter = MemoryFilter::new().with_tag("draft");
+        let modified = client
+            .retag("test_project", filter.clone(), vec!["reviewed".into()], vec!["draft".into()])
+            .await
+            .unwrap();
+        assert_eq!(modified, 12);
+
+        // No tag changes: nothing to send
+        let modified = client.retag("test_project", filter, vec![], vec![]).await.unwrap();
+        assert_eq!(modified, 0);
+        m.assert_async().await;
+    }
+
+    #[tokio::test]
+    async fn test_delete_memory_returning() {
+        let mut server = Server::new_async().await;
+
+        let mock_response = json!({
+            "id": "mem_123",
+            "content": "Deleted content",
+            "score": null,
+            "metadata": {},
+            "tags": [],
+            "timestamp": 1234567890
+        });
+
+        let _found = server.mock("DELETE", "/api/v1/memory/test_project/mem_123")
+            .match_query(mockito::Matcher::UrlEncoded("return".into(), "true".into()))
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(mock_response.to_string())
+            .create_async()
+            .await;
+
+        let _missing = server.mock("DELETE", "/api/v1/memory/test_project/mem_404")
+            .match_query(mockito::Matcher::Any)
+            .with_status(404)
+            .create_async()
+            .await;
+
+        let config = UcpConfig {
+            base_url: server.url(),
+            ..Default::default()
+        };
+
+        let client = UcpClient::new(config).unwrap();
+
+        let deleted = client
+            .delete_memory_returning("test_project", "mem_123")
+            .await
+            .unwrap();
+        assert_eq!(deleted.content, "Deleted content");
+
+        let err = client
+            .delete_memory_returning("test_project", "mem_404")
+            .await
+            .unwrap_err();
+        assert!(matches!(err, UcpError::NotFound));
+    }
+
+    #[tokio::test]
+    async fn test_trim_session_deletes_oldest() {
+        let mut server = Server::new_async().await;
+
+        let memory = |id: &str, timestamp: u64| json!({
+            "id": id,
+            "content": id,
+            "score": null,
+            "metadata": {},
+            "tags": [],
+            "timestamp": timestamp
+        });
+
+        let _first_page = server.mock("GET", "/api/v1/sessions/test_project/test_session")
+            .match_query(mockito::Matcher::Exact("order=asc".into()))
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(json!({
+                "memories": [memory("old", 1), memory("newest", 3)],
+                "next_cursor": "c1"
+            }).to_string())
+            .create_async()
+            .await;
+
+        let _second_page = server.mock("GET", "/api/v1/sessions/test_project/test_session")
+            .match_query(mockito::Matcher::Exact("order=asc&cursor=c1".into()))
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(json!({ "memories": [memory("middle", 2)] }).to_string())
+            .create_async()
+            .await;
+
+        let delete = server.mock("POST", "/api/v1/memory/batch/delete")
+            .match_body(mockito::Matcher::Json(json!({
+                "project": "test_project",
+                "ids": ["middle", "old"]
+            })))
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(json!({ "deleted": 2 }).to_string())
+            .create_async()
+            .await;
+
+        let config = UcpConfig {
+            base_url: server.url(),
+            ..Default::default()
+        };
+
+        let client = UcpClient::new(config).unwrap();
+
+        let removed = client.trim_session("test_project", "test_session", 1).await.unwrap();
+        assert_eq!(removed, 2);
+        delete.assert_async().await;
+
+        let removed = client.trim_session("test_project", "test_session", 5).await.unwrap();
+        assert_eq!(removed, 0);
+    }
+
+    #[tokio::test]
+    async fn test_get_session_page_descending() {
+        let mut server = Server::new_async().await;
+
+        let _m = server.mock("GET", "/api/v1/sessions/test_project/test_session")
+            .match_query(mockito::Matcher::Exact("order=desc&limit=1".into()))
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(json!({
+                "memories": [{
+                    "id": "newest",
+                    "content": "Latest turn",
+                    "metadata": {},
+                    "tags": [],
+                    "timestamp": 3
+                }],
+                "next_cursor": "c1"
+            }).to_string())
+            .create_async()
+            .await;
+
+        let config = UcpConfig {
+            base_url: server.url(),
+            ..Default::default()
+        };
+
+        let client = UcpClient::new(config).unwrap();
+        let query = SessionQuery {
+            order: Order::Desc,
+            limit: Some(1),
+            cursor: None,
+        };
+
+        let page = client.get_session("test_project", "test_session", &query).await.unwrap();
+        assert_eq!(page.memories[0].id, "newest");
+        assert_eq!(page.next_cursor.as_deref(), Some("c1"));
+    }
+
+    #[tokio::test]
+    async fn test_binary_memory_round_trip() {
+        let mut server = Server::new_async().await;
+        let payload: Vec<u8> = vec![0x89, b'P', b'N', b'G', 0x00, 0xff];
+
+        let store = server.mock("POST", "/api/v1/memory/test_project/raw")
+            .match_query(mockito::Matcher::UrlEncoded("session".into(), "test_session".into()))
+            .match_header("content-type", "image/png")
+            .match_body(payload.clone())
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(json!({
+                "id": "mem_bin",
+                "content": "",
+                "score": null,
+                "metadata": {},
+                "tags": [],
+                "timestamp": 1234567890
+            }).to_string())
+            .create_async()
+            .await;
+
+        let _fetch = server.mock("GET", "/api/v1/memory/test_project/mem_bin/raw")
+            .with_status(200)
+            .with_header("content-type", "image/png")
+            .with_body(payload.clone())
+            .create_async()
+            .await;
+
+        let config = UcpConfig {
+            base_url: server.url(),
+            ..Default::default()
+        };
+
+        let client = UcpClient::new(config).unwrap();
+
+        let stored = client
+            .store_memory_bytes("test_project", "test_session", payload.clone(), "image/png")
+            .await
+            .unwrap();
+        assert_eq!(stored.id, "mem_bin");
+        store.assert_async().await;
+
+        let (body, content_type) = client.get_memory_raw("test_project", "mem_bin").await.unwrap();
+        assert_eq!(body.as_ref(), payload.as_slice());
+        assert_eq!(content_type.as_deref(), Some("image/png"));
+    }
+
+    #[tokio::test]
+    async fn test_update_memory_sends_if_match() {
+        let mut server = Server::new_async().await;
+
+        let mock_response = json!({
+            "id": "mem_123",
+            "content": "Updated content",
+            "score": null,
+            "metadata": {},
+            "tags": [],
+            "timestamp": 1234567890,
+            "version": 4
+        });
+
+        let _m = server.mock("PATCH", "/api/v1/memory/test_project/mem_123")
+            .match_header("if-match", "\"3\"")
+            .match_body(mockito::Matcher::Json(json!({ "content": "Updated content" })))
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(mock_response.to_string())
+            .create_async()
+            .await;
+
+        let config = UcpConfig {
+            base_url: server.url(),
+            ..Default::default()
+        };
+
+        let client = UcpClient::new(config).unwrap();
+
+        let update = MemoryUpdate {
+            content: Some("Updated content".to_string()),
+            ..Default::default()
+        };
+
+        let response = client
+            .update_memory("test_project", "mem_123", update, Some(3))
+            .await
+            .unwrap();
+        assert_eq!(response.version, Some(4));
+    }
+
+    #[tokio::test]
+    async fn test_delete_memory_version_conflict() {
+        let mut server = Server::new_async().await;
+
+        let _m = server.mock("DELETE", "/api/v1/memory/test_project/mem_123")
+            .match_header("if-match", "\"3\"")
+            .with_status(412)
+            .create_async()
+            .await;
+
+        let config = UcpConfig {
+            base_url: server.url(),
+            ..Default::default()
+        };
+
+        let client = UcpClient::new(config).unwrap();
+        let err = client
+            .delete_memory("test_project", "mem_123", Some(3))
+            .await
+            .unwrap_err();
+        assert!(matches!(err, UcpError::Conflict));
+    }
+
+    #[tokio::test]
+    async fn test_store_memories_batch_applies_redactor() {
+        let mut server = Server::new_async().await;
+
+        let mock_response = json!({
+            "results": [
+                {
+                    "id": "mem_1",
+                    "content": "Contact [email]",
+                    "score": null,
+                    "metadata": {},
+                    "tags": [],
+                    "timestamp": 1234567890
+                }
+            ]
+        });
+
+        let _m = server.mock("POST", "/api/v1/memory/batch")
+            .match_body(mockito::Matcher::PartialJson(json!({
+                "memories": [{ "content": "Contact [email]" }]
+            })))
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(mock_response.to_string())
+            .create_async()
+            .await;
+
+        let config = UcpConfig {
+            base_url: server.url(),
+            redactor: Some(Arc::new(|content: &str| {
+                content.replace("jane@example.com", "[email]")
+            })),
+            ..Default::default()
+        };
+
+        let client = UcpClient::new(config).unwrap();
+
+        let request = MemoryRequest {
+            project: "test_project".to_string(),
+            session: "test_session".to_string(),
+            content: "Contact jane@example.com".to_string(),
+            metadata: HashMap::new(),
+            tags: vec![],
+            collection: None,
+            parent_id: None,
+            embedding: None,
+            ttl_secs: None,
+        };
+
+        let results = client.store_memories_batch(vec![request]).await.unwrap();
+        assert!(results.is_complete());
+        assert_eq!(results.succeeded.len(), 1);
+        assert_eq!(results.succeeded[0].1.content, "Contact [email]");
+    }
+
+    #[tokio::test]
+    async fn test_content_normalizer_runs_before_redactor() {
+        let mut server = Server::new_async().await;
+
+        let mock = server
+            .mock("POST", "/api/v1/memory")
+            .match_body(mockito::Matcher::PartialJson(json!({ "content": "key [secret] here" })))
+            .with_status(202)
+            .create_async()
+            .await;
+
+        let config = UcpConfig {
+            base_url: server.url(),
+            content_normalizer: Some(Arc::new(|content: &str| {
+                content.split_whitespace().collect::<Vec<_>>().join(" ")
+            })),
+            // Only matches once whitespace has been collapsed
+            redactor: Some(Arc::new(|content: &str| content.replace("key abc123", "key [secret]"))),
+            ..Default::default()
+        };
+        let client = UcpClient::new(config).unwrap();
+
+        let request = MemoryRequest::new("test_project", "test_session", "  key\tabc123 \n here\n");
+        let memory = client.store_memory(request).await.unwrap();
+        assert_eq!(memory.content, "key [secret] here");
+        mock.assert_async().await;
+    }
+
+    #[tokio::test]
+    async fn test_embedder_fills_missing_embedding() {
+        #[derive(Debug)]
+        struct LengthEmbedder;
+
+        impl crate::embed::Embedder for LengthEmbedder {
+            fn embed<'a>(&'a self, text: &'a str) -> crate::embed::Embedding<'a> {
+                Box::pin(async move { Ok(vec![text.len() as f32, 1.0]) })
+            }
+        }
+
+        let mut server = Server::new_async().await;
+
+        let embedded = server
+            .mock("POST", "/api/v1/memory")
+            .match_body(mockito::Matcher::PartialJson(json!({ "content": "four", "embedding": [4.0, 1.0] })))
+            .with_status(202)
+            .create_async()
+            .await;
+        let given = server
+            .mock("POST", "/api/v1/memory")
+            .match_body(mockito::Matcher::PartialJson(json!({ "content": "kept", "embedding": [0.5] })))
+            .with_status(202)
+            .create_async()
+            .await;
+
+        let config = UcpConfig {
+            base_url: server.url(),
+            embedder: Some(Arc::new(LengthEmbedder)),
+            ..Default::default()
+        };
+        let client = UcpClient::new(config).unwrap();
+
+        client
+            .store_memory(MemoryRequest::new("test_project", "test_session", "four"))
+            .await
+            .unwrap();
+        let mut request = MemoryRequest::new("test_project", "test_session", "kept");
+        request.embedding = Some(vec![0.5]);
+        client.store_memory(request).await.unwrap();
+
+        embedded.assert_async().await;
+        given.assert_async().await;
+    }
+
+    #[tokio::test]
+    async fn test_prefetch_warms_search_cache() {
+        let mut server = Server::new_async().await;
+
+        let search = server
+            .mock("POST", "/api/v1/search")
+            .match_header("x-priority", "low")
+            .match_body(mockito::Matcher::PartialJson(json!({ "query": "next step" })))
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(json!({ "results": [{ "id": "mem_1", "content": "c", "timestamp": 1 }], "total": 1, "took": 2 }).to_string())
+            .expect(1)
+            .create_async()
+            .await;
+
+        let config = UcpConfig {
+            base_url: server.url(),
+            search_cache: Some(SearchCacheConfig::default()),
+            ..Default::default()
+        };
+        let client = UcpClient::new(config).unwrap();
+
+        client.prefetch(vec![VectorQuery::builder("test_project", "next step").build()]);
+        tokio::time::sleep(Duration::from_millis(200)).await;
+
+        let results = client
+            .search_memories(VectorQuery::builder("test_project", "next step").build())
+            .await
+            .unwrap();
+        assert_eq!(results[0].id, "mem_1");
+        search.assert_async().await;
+    }
+
+    #[tokio::test]
+    async fn test_content_cipher_encrypts_sent_and_decrypts_received() {
+        #[derive(Debug)]
+        struct ReverseCipher;
+
+        impl crate::cipher::ContentCipher for ReverseCipher {
+            fn encrypt(&self, plaintext: &str) -> Result<String> {
+                Ok(plaintext.chars().rev().collect())
+            }
+
+            fn decrypt(&self, ciphertext: &str) -> Result<String> {
+                Ok(ciphertext.chars().rev().collect())
+            }
+        }
+
+        let mut server = Server::new_async().await;
+
+        let store = server
+            .mock("POST", "/api/v1/memory")
+            .match_body(mockito::Matcher::PartialJson(json!({ "content": "terces" })))
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(json!({ "id": "mem_1", "content": "terces", "metadata": {}, "tags": [], "timestamp": 1 }).to_string())
+            .create_async()
+            .await;
+        let get = server
+            .mock("GET", "/api/v1/memory/test_project/mem_1")
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(json!({ "id": "mem_1", "content": "terces", "metadata": {}, "tags": [], "timestamp": 1 }).to_string())
+            .create_async()
+            .await;
+
+        let config = UcpConfig {
+            base_url: server.url(),
+            content_cipher: Some(Arc::new(ReverseCipher)),
+            ..Default::default()
+        };
+        let client = UcpClient::new(config).unwrap();
+
+        let stored = client
+            .store_memory(MemoryRequest::new("test_project", "test_session", "secret"))
+            .await
+            .unwrap();
+        assert_eq!(stored.content, "secret");
+        let fetched = client.get_memory("test_project", "mem_1").await.unwrap();
+        assert_eq!(fetched.content, "secret");
+
+        let update = server
+            .mock("PATCH", "/api/v1/memory/test_project/mem_1")
+            .match_body(mockito::Matcher::PartialJson(json!({ "content": "dlrow" })))
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(json!({ "id": "mem_1", "content": "dlrow", "metadata": {}, "tags": [], "timestamp": 2 }).to_string())
+            .create_async()
+            .await;
+        let changes = MemoryUpdate {
+            content: Some("world".to_string()),
+            ..Default::default()
+        };
+        let updated = client
+            .update_memory("test_project", "mem_1", changes, None)
+            .await
+            .unwrap();
+        assert_eq!(updated.content, "world");
+
+        // Other read paths decrypt too
+        let encrypted = |id: &str| json!({ "id": id, "content": "terces", "metadata": {}, "tags": [], "timestamp": 1 });
+        let _children = server
+            .mock("GET", "/api/v1/memory/test_project/mem_1/children")
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(json!({ "memories": [encrypted("mem_2")] }).to_string())
+            .create_async()
+            .await;
+        let _list = server
+            .mock("POST", "/api/v1/memory/test_project/list")
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(json!({ "memories": [encrypted("mem_1")] }).to_string())
+            .create_async()
+            .await;
+        let _versions = server
+            .mock("GET", "/api/v1/memory/test_project/mem_1/versions")
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(json!({ "versions": [{ "version": 1, "content": "terces", "timestamp": 1 }] }).to_string())
+            .create_async()
+            .await;
+        let _archive = server
+            .mock("POST", "/api/v1/memory/test_project/mem_1/archive")
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(encrypted("mem_1").to_string())
+            .create_async()
+            .await;
+
+        let children = client.get_children("test_project", "mem_1").await.unwrap();
+        assert_eq!(children[0].content, "secret");
+        let page = client
+            .list_memories_page("test_project", &MemoryFilter::new(), None)
+            .await
+            .unwrap();
+        assert_eq!(page.memories[0].content, "secret");
+        let versions = client.get_memory_versions("test_project", "mem_1").await.unwrap();
+        assert_eq!(versions[0].content, "secret");
+        let archived = client.archive_memory("test_project", "mem_1").await.unwrap();
+        assert_eq!(archived.content, "secret");
+
+        store.assert_async().await;
+        get.assert_async().await;
+        update.assert_async().await;
+    }
+
+    #[tokio::test]
+    async fn test_store_memories_batch_partial_success() {
+        let mut server = Server::new_async().await;
+
+        let stored = |id: &str| {
+            json!({ "id": id, "content": "c", "metadata": {}, "tags": [], "timestamp": 1234567890 })
+        };
+        let _m = server
+            .mock("POST", "/api/v1/memory/batch")
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(
+                json!({
+                    "results": [
+                        stored("mem_1"),
+                        { "error": { "status": 400, "code": "embedding_failed", "message": "model timeout" } },
+                        stored("mem_3"),
+                        { "error": { "code": "quota_exceeded", "message": "full", "details": { "limit": 1024 } } },
+                        { "error": { "status": 429, "message": "slow down" } }
+                    ]
+                })
+                .to_string(),
+            )
+            .create_async()
+            .await;
+
+        let config = UcpConfig {
+            base_url: server.url(),
+            ..Default::default()
+        };
+        let client = UcpClient::new(config).unwrap();
+
+        let batch = (0..5)
+            .map(|i| MemoryRequest::new("test_project", "test_session", format!("memory {}", i)))
+            .collect();
+        let result = client.store_memories_batch(batch).await.unwrap();
+
+        assert!(!result.is_complete());
+        let stored: Vec<(usize, &str)> = result
+            .succeeded
+            .iter()
+            .map(|(index, memory)| (*index, memory.id.as_str()))
+            .collect();
+        assert_eq!(stored, [(0, "mem_1"), (2, "mem_3")]);
+
+        assert_eq!(result.failed.len(), 3);
+        assert_eq!(result.failed[0].0, 1);
+        assert_eq!(result.failed[0].1.error_code(), Some(ErrorCode::EmbeddingFailed));
+        assert!(matches!(
+            result.failed[0].1,
+            UcpError::ServerError { status: Some(400), .. }
+        ));
+        assert!(matches!(
+            result.failed[1],
+            (3, UcpError::QuotaExceeded { limit: Some(1024), .. })
+        ));
+        assert!(matches!(result.failed[2], (4, UcpError::RateLimitError)));
+    }
+
+    #[tokio::test]
+    async fn test_store_memories_batch_reports_undecryptable_items() {
+        /// Fails on content that was not encrypted by it
+        #[derive(Debug)]
+        struct PrefixCipher;
+
+        impl crate::cipher::ContentCipher for PrefixCipher {
+            fn encrypt(&self, plaintext: &str) -> Result<String> {
+                Ok(format!("enc:{}", plaintext))
+            }
+
+            fn decrypt(&self, ciphertext: &str) -> Result<String> {
+                ciphertext
+                    .strip_prefix("enc:")
+                    .map(str::to_string)
+                    .ok_or_else(|| UcpError::CipherError("not encrypted".to_string()))
+            }
+        }
+
+        let mut server = Server::new_async().await;
+
+        let stored = |id: &str, content: &str| {
+            json!({ "id": id, "content": content, "metadata": {}, "tags": [], "timestamp": 1 })
+        };
+        let _m = server
+            .mock("POST", "/api/v1/memory/batch")
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(
+                json!({ "results": [stored("mem_1", "enc:a"), stored("mem_2", "garbled"), stored("mem_3", "enc:c")] })
+                    .to_string(),
+            )
+            .create_async()
+            .await;
+
+        let config = UcpConfig {
+            base_url: server.url(),
+            content_cipher: Some(Arc::new(PrefixCipher)),
+            ..Default::default()
+        };
+        let client = UcpClient::new(config).unwrap();
+
+        let batch = ["a", "b", "c"]
+            .iter()
+            .map(|content| MemoryRequest::new("test_project", "test_session", *content))
+            .collect();
+        let result = client.store_memories_batch(batch).await.unwrap();
+
+        let stored: Vec<(usize, &str)> = result
+            .succeeded
+            .iter()
+            .map(|(index, memory)| (*index, memory.content.as_str()))
+            .collect();
+        assert_eq!(stored, [(0, "a"), (2, "c")]);
+        assert!(matches!(result.failed[..], [(1, UcpError::CipherError(_))]));
+    }
+
+    #[tokio::test]
+    async fn test_store_memories_batch_short_response() {
+        let mut server = Server::new_async().await;
+
+        let _m = server
+            .mock("POST", "/api/v1/memory/batch")
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(
+                json!({ "results": [
+                    { "id": "mem_1", "content": "c", "metadata": {}, "tags": [], "timestamp": 1 }
+                ] })
+                .to_string(),
+            )
+            .create_async()
+            .await;
+
+        let config = UcpConfig {
+            base_url: server.url(),
+            ..Default::default()
+        };
+        let client = UcpClient::new(config).unwrap();
+
+        let batch = (0..3)
+            .map(|i| MemoryRequest::new("test_project", "test_session", format!("memory {}", i)))
+            .collect();
+        let result = client.store_memories_batch(batch).await.unwrap();
+
+        assert!(!result.is_complete());
+        assert_eq!(result.succeeded.len(), 1);
+        let missing: Vec<usize> = result.failed.iter().map(|(index, _)| *index).collect();
+        assert_eq!(missing, [1, 2]);
+        match &result.failed[0].1 {
+            UcpError::ServerError { message, .. } => {
+                assert_eq!(message, "no result returned for batch index 1");
+            }
+            other => panic!("expected ServerError, got {:?}", other),
+        }
+    }
+
+
+    #[tokio::test]
+    async fn test_store_memories_batch_fail_fast() {
+        let mut server = Server::new_async().await;
+
+        let mock = server
+            .mock("POST", "/api/v1/memory/batch")
+            .match_body(mockito::Matcher::PartialJson(json!({ "fail_fast": true })))
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(
+                json!({
+                    "results": [
+                        { "id": "mem_1", "content": "c", "timestamp": 1 },
+                        { "error": { "status": 400, "code": "embedding_failed", "message": "model timeout" } }
+                    ]
+                })
+                .to_string(),
+            )
+            .create_async()
+            .await;
+
+        let config = UcpConfig {
+            base_url: server.url(),
+            ..Default::default()
+        };
+        let client = UcpClient::new(config).unwrap();
+
+        let batch = (0..3)
+            .map(|i| MemoryRequest::new("test_project", "test_session", format!("memory {}", i)))
+            .collect();
+        let err = client
+            .store_memories_batch_with(batch, BatchOptions { fail_fast: true })
+            .await
+            .unwrap_err();
+
+        assert_eq!(err.error_code(), Some(ErrorCode::EmbeddingFailed));
+        mock.assert_async().await;
+    }
+    #[tokio::test]
+    async fn test_search_memories() {
+        let mut server = Server::new_async().await;
+        
+        let mock_response = json!({
+            "results": [
+                {
+                    "id": "mem_123",
+                    "content": "Test memory content",
+                    "score": 0.95,
+                    "metadata": {},
+                    "tags": ["test"],
+                    "timestamp": 1234567890
+                }
+            ],
+            "total": 1,
+            "took": 15
+        });
+
+        let _m = server.mock("POST", "/api/v1/search")
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(mock_response.to_string())
+            .create_async()
+            .await;
+
+        let config = UcpConfig {
+            base_url: server.url(),
+            ..Default::default()
+        };
+
+        let client = UcpClient::new(config).unwrap();
+        
+        let query = VectorQuery {
+            project: "test_project".to_string(),
+            session: Some("test_session".to_string()),
+            query: "test query".to_string(),
+            limit: 10,
+            threshold: 0.7,
+            tags: None,
+            tag_match: TagMatch::Any,
+            tag_prefix: None,
+            metadata_filter: None,
+            filter: None,
+            projects: None,
+            collection: None,
+            embeddings: None,
+            group_by_session: false,
+            include_archived: false,
+        };
+
+        let results = client.search_memories(query).await.unwrap();
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].id, "mem_123");
+        assert_eq!(results[0].score, Some(0.95));
+    }
+
+    #[tokio::test]
+    async fn test_import_memories_stream() {
+        let mut server = Server::new_async().await;
+
+        let _m = server
+            .mock("POST", "/api/v1/memory/import")
+            .match_header("content-type", "application/x-ndjson")
+            .match_body(mockito::Matcher::Regex(r#"(?s)"content":"first".*\n.*"content":""#.into()))
+            .with_status(200)
+            .with_header("content-type", "application/x-ndjson")
+            .with_body(concat!(
+                r#"{"line":1,"id":"mem_1"}"#,
+                "\n",
+                r#"{"line":2,"error":"content is empty"}"#,
+                "\n",
+            ))
+            .create_async()
+            .await;
+
+        let config = UcpConfig {
+            base_url: server.url(),
+            ..Default::default()
+        };
+        let client = UcpClient::new(config).unwrap();
+
+        let rows = tokio_stream::iter(vec![
+            MemoryRequest::new("test_project", "test_session", "first"),
+            MemoryRequest::new("test_project", "test_session", ""),
+        ]);
+        let acks: Vec<ImportAck> = client
+            .import_memories_stream(rows)
+            .await
+            .unwrap()
+            .collect::<Result<_>>()
+            .await
+            .unwrap();
+
+        assert_eq!(acks.len(), 2);
+        assert!(acks[0].is_ok());
+        assert_eq!(acks[0].id.as_deref(), Some("mem_1"));
+        assert!(!acks[1].is_ok());
+        assert_eq!(acks[1].line, 2);
+    }
+
+    #[tokio::test]
+    async fn test_search_stream_gzip_body() {
+        let mut server = Server::new_async().await;
+
+        let ndjson = concat!(
+            r#"{"id":"mem_1","content":"first","score":0.9,"metadata":{},"tags":[],"timestamp":1}"#,
+            "\n",
+            r#"{"id":"mem_2","content":"second","score":0.8,"metadata":{},"tags":[],"timestamp":2}"#,
+            "\n",
+        );
+
+        #[cfg(feature = "gzip")]
+        let body = {
+            use tokio::io::AsyncReadExt;
+            let mut encoder = async_compression::tokio::bufread::GzipEncoder::new(ndjson.as_bytes());
+            let mut compressed = Vec::new();
+            encoder.read_to_end(&mut compressed).await.unwrap();
+            compressed
+        };
+        #[cfg(not(feature = "gzip"))]
+        let body = ndjson.as_bytes().to_vec();
+
+        let mock = server.mock("POST", "/api/v1/search/stream");
+        #[cfg(feature = "gzip")]
+        let mock = mock.match_header("accept-encoding", "gzip");
+        let _m = mock
+            .with_status(200)
+            .with_header("content-type", "application/x-ndjson")
+            .with_header("content-encoding", "gzip")
+            .with_body(body)
+            .create_async()
+            .await;
+
+        let config = UcpConfig {
+            base_url: server.url(),
+            ..Default::default()
+        };
+        let client = UcpClient::new(config).unwrap();
+
+        let query = VectorQuery::builder("test_project", "test query").build();
+        let results: Vec<Result<MemoryResponse>> = client
+            .search_memories_stream(query)
+            .await
+            .unwrap()
+            .collect()
+            .await;
+
+        #[cfg(feature = "gzip")]
+        {
+            let ids: Vec<String> = results.into_iter().map(|r| r.unwrap().id).collect();
+            assert_eq!(ids, ["mem_1", "mem_2"]);
+        }
+
+        #[cfg(not(feature = "gzip"))]
+        match results.as_slice() {
+            [Err(UcpError::CodecError(message))] => assert!(message.contains("gzip")),
+            other => panic!("expected a single CodecError, got {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_recent_activity() {
+        let mut server = Server::new_async().await;
+
+        let mock = server
+            .mock("GET", "/api/v1/projects/test_project/activity")
+            .match_query(mockito::Matcher::Exact("limit=2&since=1700000000".into()))
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(
+                json!({
+                    "events": [
+                        { "kind": "memory_created", "memory_id": "mem_1", "timestamp": 1700000100 },
+                        { "kind": "memory_deleted", "memory_id": "mem_2", "session": "s", "timestamp": 1700000200 },
+                        { "kind": "memory_archived", "memory_id": "mem_3", "timestamp": 1700000150 }
+                    ]
+                })
+                .to_string(),
+            )
+            .create_async()
+            .await;
+
+        let config = UcpConfig {
+            base_url: server.url(),
+            ..Default::default()
+        };
+        let client = UcpClient::new(config).unwrap();
+
+        let events = client
+            .recent_activity("test_project", 2, Some(1700000000))
+            .await
+            .unwrap();
+        let kinds: Vec<ActivityKind> = events.iter().map(|event| event.kind).collect();
+        assert_eq!(
+            kinds,
+            [ActivityKind::MemoryDeleted, ActivityKind::Other, ActivityKind::MemoryCreated]
+        );
+        assert_eq!(events[0].session.as_deref(), Some("s"));
+        mock.assert_async().await;
+    }
+
+    #[tokio::test]
+    async fn test_aggregate_by_metadata_field() {
+        let mut server = Server::new_async().await;
+
+        let mock = server
+            .mock("POST", "/api/v1/projects/test_project/aggregate")
+            .match_body(mockito::Matcher::Json(json!({
+                "group_by": "source",
+                "filter": { "tags": ["chat"] }
+            })))
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(
+                json!({
+                    "buckets": [
+                        { "key": "email", "count": 3 },
+                        { "key": null, "count": 1 },
+                        { "key": "chat", "count": 12 }
+                    ]
+                })
+                .to_string(),
+            )
+            .create_async()
+            .await;
+
+        let config = UcpConfig {
+            base_url: server.url(),
+            ..Default::default()
+        };
+        let client = UcpClient::new(config).unwrap();
+
+        let buckets = client
+            .aggregate("test_project", "source", Some(MemoryFilter::new().with_tag("chat")))
+            .await
+            .unwrap();
+        let counts: Vec<(serde_json::Value, u64)> =
+            buckets.into_iter().map(|bucket| (bucket.key, bucket.count)).collect();
+        assert_eq!(
+            counts,
+            [(json!("chat"), 12), (json!("email"), 3), (serde_json::Value::Null, 1)]
+        );
+        mock.assert_async().await;
+    }
+
+    #[tokio::test]
+    async fn test_search_stream_idle_timeout() {
+        let mut server = Server::new_async().await;
+
+        let line = |id: &str| {
+            format!(
+                "{}\n",
+                json!({ "id": id, "content": "c", "score": 0.9, "metadata": {}, "tags": [], "timestamp": 1 })
+            )
+        };
+        let (first, second, third) = (line("mem_1"), line("mem_2"), line("mem_3"));
+        let _m = server
+            .mock("POST", "/api/v1/search/stream")
+            .with_status(200)
+            .with_header("content-type", "application/x-ndjson")
+            .with_chunked_body(move |w| {
+                // A steady trickle inside the idle window, then a stall
+                w.write_all(first.as_bytes())?;
+                w.flush()?;
+                std::thread::sleep(Duration::from_millis(150));
+                w.write_all(second.as_bytes())?;
+                w.flush()?;
+                std::thread::sleep(Duration::from_millis(1000));
+                w.write_all(third.as_bytes())
+            })
+            .create_async()
+            .await;
+
+        let config = UcpConfig {
+            base_url: server.url(),
+            ..Default::default()
+        };
+        let client = UcpClient::new(config).unwrap();
+
+        let options = StreamOptions {
+            stream_idle_timeout: Some(Duration::from_millis(400)),
+            ..Default::default()
+        };
+        let query = VectorQuery::builder("test_project", "test query").build();
+        let results: Vec<Result<MemoryResponse>> = client
+            .search_memories_stream_with(query, options)
+            .await
+            .unwrap()
+            .collect()
+            .await;
+
+        assert_eq!(results.len(), 3);
+        assert_eq!(results[1].as_ref().unwrap().id, "mem_2");
+        assert!(matches!(results[2], Err(UcpError::Timeout(_))));
+    }
+
+    #[tokio::test]
+    async fn test_probe_retrieval() {
+        let mut server = Server::new_async().await;
+
+        let memory = |id: &str, content: &str, score: f64| {
+            json!({ "id": id, "content": content, "score": score, "metadata": {}, "tags": [], "timestamp": 1 })
+        };
+        let store = server
+            .mock("POST", "/api/v1/memory")
+            .match_body(mockito::Matcher::PartialJson(json!({ "content": "canary: blue heron" })))
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(memory("mem_canary", "canary: blue heron", 0.0).to_string())
+            .create_async()
+            .await;
+        let _search = server
+            .mock("POST", "/api/v1/search")
+            .match_body(mockito::Matcher::PartialJson(
+                json!({ "query": "canary: blue heron", "threshold": 0.0 }),
+            ))
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(
+                json!({
+                    "results": [
+                        memory("mem_other", "grey heron", 0.91),
+                        memory("mem_canary", "canary: blue heron", 0.88)
+                    ],
+                    "total": 2,
+                    "took": 3
+                })
+                .to_string(),
+            )
+            .expect(2)
+            .create_async()
+            .await;
+
+        let config = UcpConfig {
+            base_url: server.url(),
+            ..Default::default()
+        };
+        let client = UcpClient::new(config).unwrap();
+
+        let canary = ProbeSpec::new("probes", "canary: blue heron");
+        let result = client.probe_retrieval("test_project", &canary).await.unwrap();
+        assert_eq!(result.memory_id, "mem_canary");
+        assert_eq!(result.rank, Some(2));
+        assert_eq!(result.score, Some(0.88));
+        assert!(!result.is_top());
+        store.assert_async().await;
+
+        // An already stored canary is not stored again
+        let canary = canary.with_memory_id("mem_missing");
+        let result = client.probe_retrieval("test_project", &canary).await.unwrap();
+        assert_eq!(result.rank, None);
+        store.assert_async().await;
+    }
+
+    #[tokio::test]
+    async fn test_search_stream_take_stops_early() {
+        let mut server = Server::new_async().await;
+
+        let line = |id: &str| {
+            format!(
+                "{}\n",
+                json!({ "id": id, "content": "c", "score": 0.9, "metadata": {}, "tags": [], "timestamp": 1 })
+            )
+        };
+        let (first, second, third) = (line("mem_1"), line("mem_2"), line("mem_3"));
+        let _m = server
+            .mock("POST", "/api/v1/search/stream")
+            .with_status(200)
+            .with_header("content-type", "application/x-ndjson")
+            .with_chunked_body(move |w| {
+                w.write_all(first.as_bytes())?;
+                w.write_all(second.as_bytes())?;
+                w.flush()?;
+                std::thread::sleep(Duration::from_millis(3000));
+                w.write_all(third.as_bytes())
+            })
+            .create_async()
+            .await;
+
+        let config = UcpConfig {
+            base_url: server.url(),
+            ..Default::default()
+        };
+        let client = UcpClient::new(config).unwrap();
+
+        let options = StreamOptions {
+            take: Some(2),
+            ..Default::default()
+        };
+        let query = VectorQuery::builder("test_project", "test query").build();
+        let started = std::time::Instant::now();
+        let results: Vec<MemoryResponse> = client
+            .search_memories_stream_with(query, options)
+            .await
+            .unwrap()
+            .collect::<Result<_>>()
+            .await
+            .unwrap();
+
+        let ids: Vec<&str> = results.iter().map(|memory| memory.id.as_str()).collect();
+        assert_eq!(ids, ["mem_1", "mem_2"]);
+        // The stream ends without waiting for the rest of the body
+        assert!(started.elapsed() < Duration::from_millis(2000));
+    }
+
+    #[tokio::test]
+    async fn test_search_stream_unterminated_last_record() {
+        let mut server = Server::new_async().await;
+
+        let record = |id: &str| {
+            json!({ "id": id, "content": "c", "score": 0.9, "metadata": {}, "tags": [], "timestamp": 1 })
+                .to_string()
+        };
+        let complete = format!("{}\n{}", record("mem_1"), record("mem_2"));
+        let truncated = format!("{}\n{{\"id\":\"mem_2\",\"con", record("mem_1"));
+        let _complete = server
+            .mock("POST", "/api/v1/search/stream")
+            .match_body(mockito::Matcher::PartialJson(json!({ "query": "complete" })))
+            .with_status(200)
+            .with_header("content-type", "application/x-ndjson")
+            .with_body(complete)
+            .create_async()
+            .await;
+        let _truncated = server
+            .mock("POST", "/api/v1/search/stream")
+            .match_body(mockito::Matcher::PartialJson(json!({ "query": "truncated" })))
+            .with_status(200)
+            .with_header("content-type", "application/x-ndjson")
+            .with_body(truncated)
+            .create_async()
+            .await;
+
+        let config = UcpConfig {
+            base_url: server.url(),
+            ..Default::default()
+        };
+        let client = UcpClient::new(config).unwrap();
+
+        // A complete last record without a newline is still yielded
+        let query = VectorQuery::builder("test_project", "complete").build();
+        let results: Vec<MemoryResponse> = client
+            .search_memories_stream(query)
+            .await
+            .unwrap()
+            .collect::<Result<_>>()
+            .await
+            .unwrap();
+        assert_eq!(results.len(), 2);
+
+        let query = VectorQuery::builder("test_project", "truncated").build();
+        let results: Vec<Result<MemoryResponse>> =
+            client.search_memories_stream(query).await.unwrap().collect().await;
+        assert_eq!(results.len(), 2);
+        assert_eq!(results[0].as_ref().unwrap().id, "mem_1");
+        assert!(
+            matches!(results[1], Err(UcpError::TruncatedStream(len)) if len == 18),
+            "got {:?}",
+            results[1]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_get_memory_versions() {
+        let mut server = Server::new_async().await;
+
+        let _m = server
+            .mock("GET", "/api/v1/memory/test_project/mem_123/versions")
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(
+                json!({
+                    "versions": [
+                        { "version": 2, "content": "fixed", "tags": ["a"], "timestamp": 20 },
+                        { "version": 1, "content": "typo", "metadata": {}, "tags": [], "timestamp": 10 }
+                    ]
+                })
+                .to_string(),
+            )
+            .create_async()
+            .await;
+
+        let config = UcpConfig {
+            base_url: server.url(),
+            ..Default::default()
+        };
+        let client = UcpClient::new(config).unwrap();
+
+        let versions = client.get_memory_versions("test_project", "mem_123").await.unwrap();
+        assert_eq!(versions.len(), 2);
+        assert_eq!(versions[0].version, 1);
+
+        let diff = crate::diff::diff_versions(&versions[0], &versions[1]);
+        assert!(diff.content_changed());
+        assert_eq!(diff.tags_added, ["a"]);
+    }
+
+    #[tokio::test]
+    async fn test_rollback_memory() {
+        let mut server = Server::new_async().await;
+
+        let restored = server
+            .mock("POST", "/api/v1/memory/test_project/mem_123/rollback")
+            .match_body(mockito::Matcher::Json(json!({ "version": 1 })))
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(
+                json!({
+                    "id": "mem_123",
+                    "content": "original",
+                    "metadata": {},
+                    "tags": [],
+                    "timestamp": 30,
+                    "version": 3
+                })
+                .to_string(),
+            )
+            .create_async()
+            .await;
+        let _missing_version = server
+            .mock("POST", "/api/v1/memory/test_project/mem_123/rollback")
+            .match_body(mockito::Matcher::Json(json!({ "version": 9 })))
+            .with_status(404)
+            .with_header("content-type", "application/json")
+            .with_body(json!({ "code": "VERSION_NOT_FOUND", "message": "no version 9" }).to_string())
+            .create_async()
+            .await;
+        let _missing_memory = server
+            .mock("POST", "/api/v1/memory/test_project/mem_gone/rollback")
+            .with_status(404)
+            .with_body(json!({ "message": "memory not found" }).to_string())
+            .create_async()
+            .await;
+
+        let config = UcpConfig {
+            base_url: server.url(),
+            ..Default::default()
+        };
+        let client = UcpClient::new(config).unwrap();
+
+        let memory = client.rollback_memory("test_project", "mem_123", 1).await.unwrap();
+        assert_eq!(memory.content, "original");
+        assert_eq!(memory.version, Some(3));
+        restored.assert_async().await;
+
+        match client.rollback_memory("test_project", "mem_123", 9).await {
+            Err(UcpError::VersionNotFound { memory_id, version }) => {
+                assert_eq!(memory_id, "mem_123");
+                assert_eq!(version, 9);
+            }
+            other => panic!("expected VersionNotFound, got {:?}", other),
+        }
+        assert!(matches!(
+            client.rollback_memory("test_project", "mem_gone", 1).await,
+            Err(UcpError::NotFound)
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_threshold_sweep_uses_one_search() {
+        let mut server = Server::new_async().await;
+
+        let mock = server
+            .mock("POST", "/api/v1/search")
+            .match_query(mockito::Matcher::UrlEncoded("fields".into(), "id,score".into()))
+            .match_body(mockito::Matcher::PartialJson(json!({ "threshold": 0.0 })))
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(
+                json!({
+                    "results": [
+                        { "id": "mem_1", "score": 0.92 },
+                        { "id": "mem_2", "score": 0.75 },
+                        { "id": "mem_3", "score": 0.41 },
+                        { "id": "mem_4", "score": null }
+                    ]
+                })
+                .to_string(),
+            )
+            .expect(1)
+            .create_async()
+            .await;
+
+        let config = UcpConfig {
+            base_url: server.url(),
+            ..Default::default()
+        };
+        let client = UcpClient::new(config).unwrap();
+
+        let query = VectorQuery::builder("test_project", "test query").threshold(0.8).build();
+        let counts = client
+            .threshold_sweep(query, &[0.0, 0.5, 0.75, 0.95])
+            .await
+            .unwrap();
+        assert_eq!(counts, [(0.0, 4), (0.5, 2), (0.75, 2), (0.95, 0)]);
+        mock.assert_async().await;
+    }
+
+    #[tokio::test]
+    async fn test_clear_project_requires_confirm() {
+        let mut server = Server::new_async().await;
+
+        let mock = server
+            .mock("DELETE", "/api/v1/memory/test_project")
+            .match_query(mockito::Matcher::UrlEncoded("all".into(), "true".into()))
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(json!({ "deleted": 42 }).to_string())
+            .expect(1)
+            .create_async()
+            .await;
+
+        let config = UcpConfig {
+            base_url: server.url(),
+            ..Default::default()
+        };
+        let client = UcpClient::new(config).unwrap();
+
+        let err = client.clear_project("test_project", false).await.unwrap_err();
+        assert!(matches!(err, UcpError::ConfigError(_)));
+
+        assert_eq!(client.clear_project("test_project", true).await.unwrap(), 42);
+        mock.assert_async().await;
+    }
+
+    #[tokio::test]
+    async fn test_body_timeout_is_distinguished() {
+        let mut server = Server::new_async().await;
+
+        let _m = server
+            .mock("GET", "/api/v1/memory/test_project/mem_123")
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_chunked_body(|w| {
+                w.write_all(b"{\"id\":")?;
+                w.flush()?;
+                std::thread::sleep(Duration::from_millis(1500));
+                w.write_all(b"\"mem_123\"}")
+            })
+            .create_async()
+            .await;
+
+        let config = UcpConfig {
+            base_url: server.url(),
+            timeout_secs: 1,
+            ..Default::default()
+        };
+        let client = UcpClient::new(config).unwrap();
+
+        let err = client.get_memory("test_project", "mem_123").await.unwrap_err();
+        assert!(matches!(err, UcpError::BodyTimeout(_)), "got {:?}", err);
+    }
+
+    #[tokio::test]
+    async fn test_get_capabilities() {
+        let mut server = Server::new_async().await;
+
+        let _m = server
+            .mock("GET", "/api/v1/capabilities")
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(
+                json!({
+                    "max_content_bytes": 65536,
+                    "embedding_dim": 1536,
+                    "supported_metrics": ["cosine", "dot"]
+                })
+                .to_string(),
+            )
+            .create_async()
+            .await;
+
+        let config = UcpConfig {
+            base_url: server.url(),
+            ..Default::default()
+        };
+        let client = UcpClient::new(config).unwrap();
+
+        let capabilities = client.get_capabilities().await.unwrap();
+        assert_eq!(capabilities.max_content_bytes, Some(65536));
+        assert_eq!(capabilities.embedding_dim, Some(1536));
+        assert_eq!(capabilities.supported_metrics, ["cosine", "dot"]);
+        assert_eq!(capabilities.max_batch_size, None);
+    }
+
+    #[tokio::test]
+    async fn test_reject_empty_content() {
+        let mut server = Server::new_async().await;
+
+        let single = server
+            .mock("POST", "/api/v1/memory")
+            .expect(0)
+            .create_async()
+            .await;
+        let batch_mock = server
+            .mock("POST", "/api/v1/memory/batch")
+            .match_body(mockito::Matcher::PartialJson(json!({
+                "memories": [{ "content": "kept" }]
+            })))
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(json!({ "results": [
+                { "id": "m1", "content": "kept", "metadata": {}, "tags": [], "timestamp": 1 }
+            ] }).to_string())
+            .expect(1)
+            .create_async()
+            .await;
+
+        let config = UcpConfig {
+            base_url: server.url(),
+            reject_empty_content: true,
+            ..Default::default()
+        };
+        let client = UcpClient::new(config).unwrap();
+
+        let err = client
+            .store_memory(MemoryRequest::new("test_project", "test_session", "  \n"))
+            .await
+            .unwrap_err();
+        assert!(matches!(err, UcpError::ConfigError(_)));
+
+        // Empty items are reported per index while the rest are stored
+        let batch = || vec![
+            MemoryRequest::new("test_project", "test_session", ""),
+            MemoryRequest::new("test_project", "test_session", "kept"),
+            MemoryRequest::new("test_project", "test_session", " "),
+        ];
+        let result = client.store_memories_batch(batch()).await.unwrap();
+        assert_eq!(result.succeeded.len(), 1);
+        assert_eq!(result.succeeded[0].0, 1);
+        assert_eq!(result.succeeded[0].1.id, "m1");
+        let failed: Vec<usize> = result.failed.iter().map(|(index, _)| *index).collect();
+        assert_eq!(failed, [0, 2]);
+        assert!(result.failed.iter().all(|(_, e)| matches!(e, UcpError::ConfigError(_))));
+
+        // With fail_fast, nothing is sent
+        let options = BatchOptions { fail_fast: true };
+        match client.store_memories_batch_with(batch(), options).await {
+            Err(UcpError::ConfigError(message)) => assert!(message.contains("[0, 2]"), "{}", message),
+            other => panic!("expected ConfigError, got {:?}", other),
+        }
+
+        single.assert_async().await;
+        batch_mock.assert_async().await;
+    }
+
+    #[tokio::test]
+    async fn test_strict_mode_rejects_before_sending() {
+        let mut server = Server::new_async().await;
+
+        let mock = server
+            .mock("POST", mockito::Matcher::Any)
+            .expect(0)
+            .create_async()
+            .await;
+
+        let config = UcpConfig {
+            base_url: server.url(),
+            strict: true,
+            ..Default::default()
+        };
+        let client = UcpClient::new(config).unwrap();
+
+        let err = client
+            .search_memories(VectorQuery::builder("test_project", "test query").limit(0).build())
+            .await
+            .unwrap_err();
+        assert!(matches!(err, UcpError::ConfigError(_)));
+
+        let batch = vec![
+            MemoryRequest::new("test_project", "test_session", "fine"),
+            MemoryRequest::new("test_project", "", "no session"),
+        ];
+        match client.store_memories_batch(batch).await {
+            Err(UcpError::ConfigError(message)) => {
+                assert!(message.starts_with("batch index 1:"), "{}", message);
+            }
+            other => panic!("expected ConfigError, got {:?}", other),
+        }
+
+        mock.assert_async().await;
+    }
+
+    #[tokio::test]
+    async fn test_adaptive_timeout_uses_observed_latency() {
+        let mut server = Server::new_async().await;
+
+        let _health = server
+            .mock("GET", "/api/v1/health")
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(
+                json!({ "status": "ok", "version": "1.0.0", "uptime": 1, "memory_usage": {} })
+                    .to_string(),
+            )
+            .create_async()
+            .await;
+        let _slow = server
+            .mock("GET", "/api/v1/memory/test_project/mem_123")
+            .with_body_from_request(|_| {
+                std::thread::sleep(Duration::from_millis(600));
+                b"{}".to_vec()
+            })
+            .create_async()
+            .await;
+
+        let config = UcpConfig {
+            base_url: server.url(),
+            max_retries: 0,
+            adaptive_timeout: Some(AdaptiveTimeout {
+                min_timeout_ms: 200,
+                max_timeout_ms: 200,
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+        let client = UcpClient::new(config).unwrap();
+
+        assert!(client.observed_latency(&Method::GET).is_none());
+        client.health_check().await.unwrap();
+        assert!(client.observed_latency(&Method::GET).is_some());
+
+        match client.get_memory("test_project", "mem_123").await {
+            Err(UcpError::HttpError(e)) => assert!(e.is_timeout()),
+            other => panic!("expected timeout, got {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_parent_links_and_get_children() {
+        let mut server = Server::new_async().await;
+
+        let _store = server
+            .mock("POST", "/api/v1/memory")
+            .match_body(mockito::Matcher::PartialJson(json!({ "parent_id": "mem_root" })))
+            .with_status(202)
+            .create_async()
+            .await;
+        let _children = server
+            .mock("GET", "/api/v1/memory/test_project/mem_root/children")
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(
+                json!({
+                    "memories": [{
+                        "id": "mem_child",
+                        "content": "Follow-up thought",
+                        "metadata": {},
+                        "tags": [],
+                        "timestamp": 1234567890,
+                        "parent_id": "mem_root"
+                    }]
+                })
+                .to_string(),
+            )
+            .create_async()
+            .await;
+
+        let config = UcpConfig {
+            base_url: server.url(),
+            ..Default::default()
+        };
+        let client = UcpClient::new(config).unwrap();
+
+        let pending = client
+            .store_memory(
+                MemoryRequest::new("test_project", "test_session", "Follow-up thought")
+                    .with_parent("mem_root"),
+            )
+            .await
+            .unwrap();
+        assert_eq!(pending.parent_id.as_deref(), Some("mem_root"));
+
+        let children = client.get_children("test_project", "mem_root").await.unwrap();
+        assert_eq!(children.len(), 1);
+        assert_eq!(children[0].id, "mem_child");
+        assert_eq!(children[0].parent_id.as_deref(), Some("mem_root"));
+    }
+
+    #[tokio::test]
+    async fn test_warmup_is_best_effort() {
+        let mut server = Server::new_async().await;
+
+        let head = server
+            .mock("HEAD", "/api/v1/health")
+            .with_status(200)
+            .expect(1)
+            .create_async()
+            .await;
+
+        let config = UcpConfig {
+            base_url: server.url(),
+            ..Default::default()
+        };
+        UcpClient::new(config).unwrap().warmup().await;
+        head.assert_async().await;
+
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let unreachable = format!("http://{}", listener.local_addr().unwrap());
+        drop(listener);
+
+        let config = UcpConfig {
+            base_url: unreachable,
+            ..Default::default()
+        };
+        UcpClient::new(config).unwrap().warmup().await;
+    }
+
+    #[tokio::test]
+    async fn test_auth_header_styles() {
+        let mut server = Server::new_async().await;
+
+        let api_key_header = server
+            .mock("GET", "/api/v1/auth/verify")
+            .match_header("x-api-key", "secret")
+            .match_header("authorization", mockito::Matcher::Missing)
+            .with_status(200)
+            .create_async()
+            .await;
+        let custom = server
+            .mock("GET", "/api/v1/auth/verify")
+            .match_header("authorization", "ApiKey secret")
+            .with_status(200)
+            .create_async()
+            .await;
+
+        let client = |auth_header: AuthHeaderStyle| {
+            UcpClient::new(UcpConfig {
+                base_url: server.url(),
+                api_key: Some("secret".to_string()),
+                auth_header,
+                ..Default::default()
+            })
+        };
+
+        client(AuthHeaderStyle::ApiKeyHeader("X-Api-Key".into())).unwrap().verify().await.unwrap();
+        client(AuthHeaderStyle::Custom {
+            name: "Authorization".into(),
+            format: "ApiKey {key}".into(),
+        })
+        .unwrap()
+        .verify()
+        .await
+        .unwrap();
+        api_key_header.assert_async().await;
+        custom.assert_async().await;
+
+        let err = client(AuthHeaderStyle::ApiKeyHeader("bad header".into())).unwrap_err();
+        assert!(matches!(err, UcpError::ConfigError(_)));
+    }
+
+    #[tokio::test]
+    async fn test_verify_distinguishes_auth_and_connection_failures() {
+        let mut server = Server::new_async().await;
+
+        let _ok = server
+            .mock("GET", "/api/v1/auth/verify")
+            .match_header("authorization", "Bearer good-key")
+            .with_status(200)
+            .create_async()
+            .await;
+        let _rejected = server
+            .mock("GET", "/api/v1/auth/verify")
+            .match_header("authorization", "Bearer bad-key")
+            .with_status(401)
+            .create_async()
+            .await;
+
+        let client = |base_url: String, api_key: &str| {
+            UcpClient::new(UcpConfig {
+                base_url,
+                api_key: Some(api_key.to_string()),
+                ..Default::default()
+            })
+            .unwrap()
+        };
+
+        client(server.url(), "good-key").verify().await.unwrap();
+
+        let err = client(server.url(), "bad-key").verify().await.unwrap_err();
+        assert!(matches!(err, UcpError::AuthenticationError));
+
+        // Nothing listens on a port freed right after binding it
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let unreachable = format!("http://{}", listener.local_addr().unwrap());
+        drop(listener);
+
+        let err = client(unreachable, "good-key").verify().await.unwrap_err();
+        assert!(matches!(err, UcpError::ConnectionError(_)), "got {:?}", err);
+    }
+
+
+    #[tokio::test]
+    async fn test_token_refreshed_once_on_unauthorized() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        /// Issues `token-N`, where N counts refreshes
+        #[derive(Debug, Default)]
+        struct CountingProvider {
+            refreshes: AtomicUsize,
+        }
+
+        impl crate::auth::TokenProvider for CountingProvider {
+            fn token(&self) -> crate::auth::Token<'_> {
+                let token = format!("token-{}", self.refreshes.load(Ordering::SeqCst));
+                Box::pin(async move { Ok(token) })
+            }
+
+            fn refresh(&self) -> crate::auth::Token<'_> {
+                let refreshes = self.refreshes.fetch_add(1, Ordering::SeqCst) + 1;
+                Box::pin(async move { Ok(format!("token-{}", refreshes)) })
+            }
+        }
+
+        let mut server = Server::new_async().await;
+
+        let expired = server
+            .mock("GET", "/api/v1/memory/test_project/mem_1")
+            .match_header("authorization", "Bearer token-0")
+            .with_status(401)
+            .expect(1)
+            .create_async()
+            .await;
+        let refreshed = server
+            .mock("GET", "/api/v1/memory/test_project/mem_1")
+            .match_header("authorization", "Bearer token-1")
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(json!({ "id": "mem_1", "content": "c", "timestamp": 1 }).to_string())
+            .create_async()
+            .await;
+        let _rejected = server
+            .mock("GET", "/api/v1/memory/test_project/mem_2")
+            .with_status(401)
+            .create_async()
+            .await;
+
+        let provider = Arc::new(CountingProvider::default());
+        let config = UcpConfig {
+            base_url: server.url(),
+            token_provider: Some(provider.clone()),
+            ..Default::default()
+        };
+        let client = UcpClient::new(config).unwrap();
+
+        let memory = client.get_memory("test_project", "mem_1").await.unwrap();
+        assert_eq!(memory.id, "mem_1");
+        expired.assert_async().await;
+        refreshed.assert_async().await;
+
+        // A token rejected again after refreshing is not refreshed twice
+        let err = client.get_memory("test_project", "mem_2").await.unwrap_err();
+        assert!(matches!(err, UcpError::AuthenticationError));
+        assert_eq!(provider.refreshes.load(Ordering::SeqCst), 2);
+
+        // Calls sent without retries still recover from an expired token
+        let _verify_expired = server
+            .mock("GET", "/api/v1/auth/verify")
+            .match_header("authorization", "Bearer token-2")
+            .with_status(401)
+            .create_async()
+            .await;
+        let _verify = server
+            .mock("GET", "/api/v1/auth/verify")
+            .match_header("authorization", "Bearer token-3")
+            .with_status(200)
+            .create_async()
+            .await;
+        client.verify().await.unwrap();
+        assert_eq!(provider.refreshes.load(Ordering::SeqCst), 3);
+
+        // A streamed upload cannot be re-sent, so it goes out with a fresh
+        // token
+        let import = server
+            .mock("POST", "/api/v1/memory/import")
+            .match_header("authorization", "Bearer token-4")
+            .with_status(200)
+            .with_header("content-type", "application/x-ndjson")
+            .with_body(concat!(r#"{"line":1,"id":"mem_3"}"#, "\n"))
+            .expect(1)
+            .create_async()
+            .await;
+        let rows = tokio_stream::iter(vec![MemoryRequest::new("test_project", "test_session", "row")]);
+        let acks: Vec<ImportAck> = client
+            .import_memories_stream(rows)
+            .await
+            .unwrap()
+            .collect::<Result<_>>()
+            .await
+            .unwrap();
+        assert_eq!(acks[0].id.as_deref(), Some("mem_3"));
+        import.assert_async().await;
+    }
+    #[tokio::test]
+    async fn test_embedding_checked_against_server_dimension() {
+        let mut server = Server::new_async().await;
+
+        let _capabilities = server
+            .mock("GET", "/api/v1/capabilities")
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(json!({ "embedding_dim": 3 }).to_string())
+            .create_async()
+            .await;
+        let store = server
+            .mock("POST", "/api/v1/memory")
+            .match_body(mockito::Matcher::PartialJson(json!({ "embedding": [0.5, 0.25, 1.0] })))
+            .with_status(202)
+            .expect(2)
+            .create_async()
+            .await;
+
+        let config = UcpConfig {
+            base_url: server.url(),
+            ..Default::default()
+        };
+        let client = UcpClient::new(config).unwrap();
+        let request = |embedding: Vec<f32>| {
+            MemoryRequest::new("test_project", "test_session", "Embedded").with_embedding(embedding)
+        };
+
+        // Unknown dimension: sent as-is
+        client.store_memory(request(vec![0.5, 0.25, 1.0])).await.unwrap();
+
+        client.get_capabilities().await.unwrap();
+        let err = client.store_memory(request(vec![0.5, 0.25])).await.unwrap_err();
+        assert!(matches!(err, UcpError::ConfigError(_)));
+        client.store_memory(request(vec![0.5, 0.25, 1.0])).await.unwrap();
+
+        store.assert_async().await;
+    }
+
+    #[tokio::test]
+    async fn test_get_stats_many() {
+        let mut server = Server::new_async().await;
+
+        let stats = |project: &str| {
+            json!({
+                "project": project,
+                "total_memories": 10,
+                "total_sessions": 2,
+                "total_size_bytes": 2048,
+                "created_at": 1,
+                "last_updated": 2
+            })
+            .to_string()
+        };
+        for project in ["alpha", "gamma"] {
+            server.mock("GET", format!("/api/v1/stats/{}", project).as_str())
+                .with_status(200)
+                .with_header("content-type", "application/json")
+                .with_body(stats(project))
+                .create_async()
+                .await;
+        }
+        let _missing = server.mock("GET", "/api/v1/stats/beta")
+            .with_status(404)
+            .create_async()
+            .await;
+
+        let config = UcpConfig {
+            base_url: server.url(),
+            ..Default::default()
+        };
+
+        let client = UcpClient::new(config).unwrap();
+        let projects: Vec<String> = ["alpha", "beta", "gamma"].map(String::from).to_vec();
+        let results = client.get_stats_many(&projects, 2).await;
+
+        let names: Vec<&str> = results.iter().map(|(project, _)| project.as_str()).collect();
+        assert_eq!(names, ["alpha", "beta", "gamma"]);
+        assert_eq!(results[0].1.as_ref().unwrap().total_memories, 10);
+        assert!(matches!(results[1].1, Err(UcpError::NotFound)));
+        assert_eq!(results[2].1.as_ref().unwrap().project, "gamma");
+    }
+
+    #[tokio::test]
+    async fn test_assert_embedding_dim() {
+        let mut server = Server::new_async().await;
+
+        let _capabilities = server
+            .mock("GET", "/api/v1/capabilities")
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(json!({ "embedding_dim": 1536 }).to_string())
+            .create_async()
+            .await;
+
+        let config = UcpConfig {
+            base_url: server.url(),
+            ..Default::default()
+        };
+        let client = UcpClient::new(config).unwrap();
+
+        client.assert_embedding_dim(1536).await.unwrap();
+        let err = client.assert_embedding_dim(768).await.unwrap_err();
+        assert!(
+            matches!(err, UcpError::ConfigError(ref message) if message.contains("768") && message.contains("1536")),
+            "got {:?}",
+            err
+        );
+    }
+
+    #[tokio::test]
+    async fn test_default_search_filter_merged_into_queries() {
+        let mut server = Server::new_async().await;
+
+        let _m = server
+            .mock("POST", "/api/v1/search")
+            .match_body(mockito::Matcher::PartialJson(json!({
+                "metadata_filter": { "agent": "planner", "lang": "en" }
+            })))
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(json!({ "results": [], "total": 0, "took": 1 }).to_string())
+            .expect(1)
+            .create_async()
+            .await;
+
+        let config = UcpConfig {
+            base_url: server.url(),
+            default_search_filter: Some(HashMap::from([
+                ("agent".to_string(), json!("default-agent")),
+                ("lang".to_string(), json!("en")),
+            ])),
+            ..Default::default()
+        };
+        let client = UcpClient::new(config).unwrap();
+
+        let query = VectorQuery::builder("test_project", "test query")
+            .metadata_filter("agent", "planner")
+            .build();
+        client.search_memories(query).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_reembed_memory_and_project() {
+        let mut server = Server::new_async().await;
+
+        let _memory = server
+            .mock("POST", "/api/v1/memory/test_project/mem_123/reembed")
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(
+                json!({
+                    "id": "mem_123",
+                    "content": "Test memory content",
+                    "metadata": {},
+                    "tags": [],
+                    "timestamp": 1234567890
+                })
+                .to_string(),
+            )
+            .create_async()
+            .await;
+        let _project = server
+            .mock("POST", "/api/v1/memory/test_project/reembed")
+            .with_status(202)
+            .with_header("content-type", "application/json")
+            .with_body(json!({ "job_id": "job_1" }).to_string())
+            .create_async()
+            .await;
+
+        let config = UcpConfig {
+            base_url: server.url(),
+            ..Default::default()
+        };
+        let client = UcpClient::new(config).unwrap();
+
+        let memory = client.reembed_memory("test_project", "mem_123").await.unwrap();
+        assert_eq!(memory.id, "mem_123");
+
+        let job = client.reembed_project("test_project").await.unwrap();
+        assert_eq!(job.id(), "job_1");
+    }
+
+    #[tokio::test]
+    async fn test_job_handle_polls_until_completion() {
+        let mut server = Server::new_async().await;
+
+        let _start = server
+            .mock("POST", "/api/v1/memory/test_project/reembed")
+            .with_status(202)
+            .with_header("location", "/api/v1/jobs/job_7")
+            .create_async()
+            .await;
+
+        let polls = Arc::new(std::sync::atomic::AtomicUsize::new(0));
+        let _status = server
+            .mock("GET", "/api/v1/jobs/job_7")
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body_from_request({
+                let polls = polls.clone();
+                move |_| {
+                    let body = match polls.fetch_add(1, std::sync::atomic::Ordering::SeqCst) {
+                        0 => json!({ "state": "pending" }),
+                        1 => json!({ "state": "running", "progress": 0.5 }),
+                        _ => json!({ "state": "completed", "result": { "processed": 42 } }),
+                    };
+                    body.to_string().into_bytes()
+                }
+            })
+            .create_async()
+            .await;
+
+        let config = UcpConfig {
+            base_url: server.url(),
+            ..Default::default()
+        };
+        let client = UcpClient::new(config).unwrap();
+
+        let job = client.reembed_project("test_project").await.unwrap();
+        assert_eq!(job.id(), "job_7");
+        assert_eq!(job.status().await.unwrap(), JobStatus::Pending);
+
+        let result = job.await_completion(Duration::from_millis(5)).await.unwrap();
+        assert_eq!(result.processed, Some(42));
+        assert_eq!(polls.load(std::sync::atomic::Ordering::SeqCst), 3);
+    }
+
+    #[tokio::test]
+    async fn test_job_failure_is_reported() {
+        let mut server = Server::new_async().await;
+
+        let _status = server
+            .mock("GET", "/api/v1/jobs/job_8")
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(json!({ "state": "failed", "error": "model unavailable" }).to_string())
+            .create_async()
+            .await;
+
+        let config = UcpConfig {
+            base_url: server.url(),
+            ..Default::default()
+        };
+        let client = UcpClient::new(config).unwrap();
+        let job = JobHandle::new(client.clone(), "job_8".to_string(), client.url("jobs/job_8"));
+
+        match job.await_completion(Duration::from_millis(5)).await {
+            Err(UcpError::JobFailed { job_id, message }) => {
+                assert_eq!(job_id, "job_8");
+                assert_eq!(message, "model unavailable");
+            }
+            other => panic!("expected JobFailed, got {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_dedup_window_skips_repeated_stores() {
+        let mut server = Server::new_async().await;
+
+        let mock = server
+            .mock("POST", "/api/v1/memory")
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(
+                json!({
+                    "id": "mem_1",
+                    "content": "same observation",
+                    "metadata": {},
+                    "tags": [],
+                    "timestamp": 1234567890
+                })
+                .to_string(),
+            )
+            .expect(2)
+            .create_async()
+            .await;
+
+        let config = UcpConfig {
+            base_url: server.url(),
+            dedup_window: Some(8),
+            ..Default::default()
+        };
+        let client = UcpClient::new(config).unwrap();
+
+        let first = client
+            .store_memory(MemoryRequest::new("test_project", "test_session", "same observation"))
+            .await
+            .unwrap();
+        let repeat = client
+            .clone()
+            .store_memory(MemoryRequest::new("test_project", "test_session", "same observation"))
+            .await
+            .unwrap();
+        assert_eq!(repeat.id, first.id);
+
+        // Another session is not deduplicated against this one
+        client
+            .store_memory(MemoryRequest::new("test_project", "other_session", "same observation"))
+            .await
+            .unwrap();
+
+        mock.assert_async().await;
+    }
+
+    #[cfg(not(feature = "tls"))]
+    #[test]
+    fn test_https_rejected_without_tls() {
+        let config = UcpConfig {
+            base_url: "HTTPS://ucp.internal".to_string(),
+            ..Default::default()
+        };
+        match UcpClient::new(config) {
+            Err(UcpError::ConfigError(message)) => assert!(message.contains("`tls` feature")),
+            other => panic!("expected ConfigError, got {:?}", other.map(|_| ())),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_redirect_to_another_host() {
+        let mut origin = Server::new_async().await;
+        let mut elsewhere = Server::new_async().await;
+
+        let target = format!("{}/api/v1/health", elsewhere.url());
+        let _redirect = origin
+            .mock("GET", "/api/v1/health")
+            .with_status(302)
+            .with_header("location", &target)
+            .create_async()
+            .await;
+        let health = json!({ "status": "ok", "version": "1.0.0", "uptime": 1, "memory_usage": {} });
+        let followed = elsewhere
+            .mock("GET", "/api/v1/health")
+            .match_header("authorization", mockito::Matcher::Missing)
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(health.to_string())
+            .expect(1)
+            .create_async()
+            .await;
+
+        let client = |redirect_policy| {
+            UcpClient::new(UcpConfig {
+                base_url: origin.url(),
+                api_key: Some("secret".to_string()),
+                max_retries: 0,
+                redirect_policy,
+                ..Default::default()
+            })
+            .unwrap()
+        };
+
+        // The default policy refuses to leave the original host
+        match client(RedirectPolicy::default()).health_check().await {
+            Err(UcpError::HttpError(e)) => assert!(e.is_redirect()),
+            other => panic!("expected a redirect error, got {:?}", other),
+        }
+
+        assert!(matches!(
+            client(RedirectPolicy::None).health_check().await,
+            Err(UcpError::ServerError { status: Some(302), .. })
+        ));
+
+        // Followed when allowed, but without the credentials
+        client(RedirectPolicy::Limited(3)).health_check().await.unwrap();
+        followed.assert_async().await;
+    }
+
+    #[tokio::test]
+    async fn test_redirect_keeps_api_key_header_on_host() {
+        let mut origin = Server::new_async().await;
+        let mut elsewhere = Server::new_async().await;
+
+        let target = format!("{}/api/v1/health", elsewhere.url());
+        let _redirect = origin
+            .mock("GET", "/api/v1/health")
+            .with_status(302)
+            .with_header("location", &target)
+            .create_async()
+            .await;
+        let health = json!({ "status": "ok", "version": "1.0.0", "uptime": 1, "memory_usage": {} });
+        let leaked = elsewhere
+            .mock("GET", "/api/v1/health")
+            .match_header("x-api-key", mockito::Matcher::Any)
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(health.to_string())
+            .expect(0)
+            .create_async()
+            .await;
+
+        let client = UcpClient::new(UcpConfig {
+            base_url: origin.url(),
+            api_key: Some("secret".to_string()),
+            auth_header: AuthHeaderStyle::ApiKeyHeader("X-Api-Key".to_string()),
+            max_retries: 0,
+            redirect_policy: RedirectPolicy::Limited(3),
+            ..Default::default()
+        })
+        .unwrap();
+
+        match client.health_check().await {
+            Err(UcpError::HttpError(e)) => assert!(e.is_redirect()),
+            other => panic!("expected a redirect error, got {:?}", other),
+        }
+        leaked.assert_async().await;
+    }
+
+    #[test]
+    fn test_debug_output_hides_api_key() {
+        let client = UcpClient::new(UcpConfig {
+            api_key: Some("super-secret-key".to_string()),
+            auth_header: AuthHeaderStyle::ApiKeyHeader("X-Api-Key".to_string()),
+            ..Default::default()
+        })
+        .unwrap();
+
+        let debug = format!("{:?}", client);
+        assert!(!debug.contains("super-secret-key"), "{}", debug);
+        assert!(debug.contains("<redacted>"));
+    }
+
+    #[tokio::test]
+    async fn test_priority_header() {
+        let mut server = Server::new_async().await;
+
+        let health = json!({ "status": "ok", "version": "1.0.0", "uptime": 1, "memory_usage": {} });
+        let prioritized = server
+            .mock("GET", "/api/v1/health")
+            .match_header("x-priority", "high")
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(health.to_string())
+            .create_async()
+            .await;
+        let plain = server
+            .mock("GET", "/api/v1/health")
+            .match_header("x-priority", mockito::Matcher::Missing)
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(health.to_string())
+            .create_async()
+            .await;
+
+        let config = UcpConfig {
+            base_url: server.url(),
+            max_concurrent_requests: Some(2),
+            ..Default::default()
+        };
+        let client = UcpClient::new(config).unwrap();
+
+        client
+            .with_options(RequestOptions {
+                priority: Some(Priority::High),
+            })
+            .health_check()
+            .await
+            .unwrap();
+        client.health_check().await.unwrap();
+
+        prioritized.assert_async().await;
+        plain.assert_async().await;
+    }
+
+    #[test]
+    fn test_zero_dedup_window_is_rejected() {
+        let config = UcpConfig {
+            dedup_window: Some(0),
+            ..Default::default()
+        };
+        assert!(matches!(UcpClient::new(config), Err(UcpError::ConfigError(_))));
+    }
+
+    #[tokio::test]
+    async fn test_set_ttl() {
+        let mut server = Server::new_async().await;
+
+        let mock = server
+            .mock("PUT", "/api/v1/memory/test_project/mem_123/ttl")
+            .match_body(mockito::Matcher::Json(json!({ "ttl_secs": 3600 })))
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(
+                json!({
+                    "id": "mem_123",
+                    "content": "scratch",
+                    "metadata": {},
+                    "tags": [],
+                    "timestamp": 1234567890,
+                    "expires_at": 1234571490
+                })
+                .to_string(),
+            )
+            .create_async()
+            .await;
+
+        let config = UcpConfig {
+            base_url: server.url(),
+            ..Default::default()
+        };
+        let client = UcpClient::new(config).unwrap();
+
+        let memory = client.set_ttl("test_project", "mem_123", 3600).await.unwrap();
+        assert_eq!(memory.expires_at, Some(1234571490));
+        mock.assert_async().await;
+    }
+
+    #[tokio::test]
+    async fn test_get_document_orders_chunks() {
+        let mut server = Server::new_async().await;
+
+        let chunk = |id: &str, content: &str, index: u64| {
+            json!({
+                "id": id,
+                "content": content,
+                "metadata": { "doc_id": "doc_1", "chunk_index": index },
+                "tags": [],
+                "timestamp": 1234567890
+            })
+        };
+        let _mock = server
+            .mock("GET", "/api/v1/memory/test_project/documents/doc_1")
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(
+                json!({ "memories": [chunk("mem_2", "world", 1), chunk("mem_1", "Hello, ", 0)] })
+                    .to_string(),
+            )
+            .create_async()
+            .await;
+
+        let config = UcpConfig {
+            base_url: server.url(),
+            ..Default::default()
+        };
+        let client = UcpClient::new(config).unwrap();
+
+        let chunks = client.get_document("test_project", "doc_1").await.unwrap();
+        assert_eq!(chunks[0].id, "mem_1");
+        assert_eq!(crate::utils::reassemble_content(&chunks), "Hello, world");
+    }
+
+    #[tokio::test]
+    async fn test_search_ids() {
+        let mut server = Server::new_async().await;
+
+        let _m = server
+            .mock("POST", "/api/v1/search")
+            .match_query(mockito::Matcher::UrlEncoded("fields".into(), "id,score".into()))
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(
+                json!({
+                    "results": [{ "id": "mem_1", "score": 0.92 }, { "id": "mem_2" }],
+                    "total": 2,
+                    "took": 4
+                })
+                .to_string(),
+            )
+            .create_async()
+            .await;
+
+        let config = UcpConfig {
+            base_url: server.url(),
+            ..Default::default()
+        };
+        let client = UcpClient::new(config).unwrap();
+
+        let ids = client
+            .search_ids(VectorQuery::builder("test_project", "test query").build())
+            .await
+            .unwrap();
+
+        assert_eq!(
+            ids,
+            [("mem_1".to_string(), Some(0.92)), ("mem_2".to_string(), None)]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_search_memories_across_projects() {
+        let mut server = Server::new_async().await;
+
+        let mock_response = json!({
+            "results": [
+                {
+                    "id": "mem_1",
+                    "content": "From alpha",
+                    "score": 0.91,
+                    "project": "alpha",
+                    "metadata": {},
+                    "tags": [],
+                    "timestamp": 1234567890
+                },
+                {
+                    "id": "mem_2",
+                    "content": "From beta",
+                    "score": 0.85,
+                    "project": "beta",
+                    "metadata": {},
+                    "tags": [],
+                    "timestamp": 1234567891
+                }
+            ],
+            "total": 2,
+            "took": 12
+        });
+
+        let _m = server.mock("POST", "/api/v1/search")
+            .match_body(mockito::Matcher::PartialJson(json!({
+                "project": "alpha",
+                "projects": ["alpha", "beta"]
+            })))
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(mock_response.to_string())
+            .create_async()
+            .await;
+
+        let config = UcpConfig {
+            base_url: server.url(),
+            ..Default::default()
+        };
+
+        let client = UcpClient::new(config).unwrap();
+
+        let query = VectorQuery {
+            project: "alpha".to_string(),
+            session: None,
+            query: "shared context".to_string(),
+            limit: 10,
+            threshold: 0.7,
+            tags: None,
+            tag_match: TagMatch::Any,
+            tag_prefix: None,
+            metadata_filter: None,
+            filter: None,
+            projects: Some(vec!["alpha".to_string(), "beta".to_string()]),
+            collection: None,
+            embeddings: None,
+            group_by_session: false,
+            include_archived: false,
+        };
+
+        let results = client.search_memories(query).await.unwrap();
+        assert_eq!(results.len(), 2);
+        assert_eq!(results[0].project.as_deref(), Some("alpha"));
+        assert_eq!(results[1].project.as_deref(), Some("beta"));
+    }
+
+    #[tokio::test]
+    async fn test_search_weighted_embeddings() {
+        let mut server = Server::new_async().await;
+
+        let m = server.mock("POST", "/api/v1/search")
+            .match_body(mockito::Matcher::PartialJson(json!({
+                "embeddings": [[[1.0, 0.0], 0.75], [[0.0, 1.0], 0.25]]
+            })))
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(json!({ "results": [], "total": 0, "took": 1 }).to_string())
+            .expect(1)
+            .create_async()
+            .await;
+
+        let config = UcpConfig {
+            base_url: server.url(),
+            ..Default::default()
+        };
+
+        let client = UcpClient::new(config).unwrap();
+        let query = VectorQuery::builder("test_project", "hyde query")
+            .embedding(vec![1.0, 0.0], 0.75)
+            .embedding(vec![0.0, 1.0], 0.25)
+            .build();
+        client.search_memories(query).await.unwrap();
+
+        let mismatched = VectorQuery::builder("test_project", "hyde query")
+            .embedding(vec![1.0, 0.0], 0.5)
+            .embedding(vec![1.0, 0.0, 0.0], 0.5)
+            .build();
+        let err = client.search_memories(mismatched).await.unwrap_err();
+        assert!(matches!(err, UcpError::ConfigError(_)), "got {:?}", err);
+        m.assert_async().await;
+    }
+
+    #[tokio::test]
+    async fn test_store_bundle() {
+        let mut server = Server::new_async().await;
+
+        let m = server.mock("POST", "/api/v1/memory")
+            .match_body(mockito::Matcher::PartialJson(json!({
+                "project": "target",
+                "session": "imported",
+                "content": "Portable note",
+                "tags": ["shared"],
+                "embedding": [0.5, 0.25]
+            })))
+            .with_status(202)
+            .expect(1)
+            .create_async()
+            .await;
+
+        let config = UcpConfig {
+            base_url: server.url(),
+            ..Default::default()
+        };
+
+        let client = UcpClient::new(config).unwrap();
+        let bundle = MemoryBundle {
+            format_version: BUNDLE_FORMAT_VERSION,
+            content: "Portable note".to_string(),
+            metadata: HashMap::new(),
+            tags: vec!["shared".to_string()],
+            embedding: Some(vec![0.5, 0.25]),
+        };
+        client.store_bundle("target", "imported", bundle.clone()).await.unwrap();
+
+        let future = MemoryBundle {
+            format_version: BUNDLE_FORMAT_VERSION + 1,
+            ..bundle
+        };
+        let err = client.store_bundle("target", "imported", future).await.unwrap_err();
+        assert!(matches!(err, UcpError::ConfigError(_)));
+        m.assert_async().await;
+    }
+
+    #[tokio::test]
+    async fn test_archive_and_restore_memory() {
+        let mut server = Server::new_async().await;
+
+        let memory = |archived: bool| {
+            json!({ "id": "mem_1", "content": "c", "metadata": {}, "tags": [], "timestamp": 1, "archived": archived })
+                .to_string()
+        };
+        let _archive = server.mock("POST", "/api/v1/memory/test_project/mem_1/archive")
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(memory(true))
+            .create_async()
+            .await;
+        let _restore = server.mock("POST", "/api/v1/memory/test_project/mem_1/restore")
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(memory(false))
+            .create_async()
+            .await;
+        let search = server.mock("POST", "/api/v1/search")
+            .match_body(mockito::Matcher::PartialJson(json!({ "include_archived": true })))
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(json!({ "results": [], "total": 0, "took": 1 }).to_string())
+            .create_async()
+            .await;
+
+        let config = UcpConfig {
+            base_url: server.url(),
+            ..Default::default()
+        };
+
+        let client = UcpClient::new(config).unwrap();
+        assert!(client.archive_memory("test_project", "mem_1").await.unwrap().archived);
+        assert!(!client.restore_memory("test_project", "mem_1").await.unwrap().archived);
+
+        let query = VectorQuery::builder("test_project", "old notes")
+            .include_archived(true)
+            .build();
+        client.search_memories(query).await.unwrap();
+        search.assert_async().await;
+    }
+
+    #[tokio::test]
+    async fn test_search_grouped_by_session() {
+        let mut server = Server::new_async().await;
+
+        let hit = |id: &str, session: &str, score: f64| {
+            json!({ "id": id, "content": "c", "session": session, "score": score, "metadata": {}, "tags": [], "timestamp": 1 })
+        };
+        let _m = server.mock("POST", "/api/v1/search")
+            .match_body(mockito::Matcher::PartialJson(json!({ "group_by_session": true })))
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(json!({
+                "results": [hit("m1", "s1", 0.9), hit("m2", "s2", 0.8), hit("m3", "s1", 0.7)],
+                "total": 3,
+                "took": 5
+            }).to_string())
+            .create_async()
+            .await;
+
+        let config = UcpConfig {
+            base_url: server.url(),
+            ..Default::default()
+        };
+
+        let client = UcpClient::new(config).unwrap();
+        let query = VectorQuery::builder("test_project", "across sessions").build();
+        let groups = client.search_grouped(query).await.unwrap();
+
+        assert_eq!(groups.len(), 2);
+        let s1: Vec<&str> = groups["s1"].iter().map(|m| m.id.as_str()).collect();
+        assert_eq!(s1, ["m1", "m3"]);
+        assert_eq!(groups["s2"][0].id, "m2");
+    }
+
+    #[tokio::test]
+    async fn test_search_memories_cancellable() {
+        let mut server = Server::new_async().await;
+
+        let _slow = server.mock("POST", "/api/v1/search")
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_chunked_body(|w| {
+                std::thread::sleep(Duration::from_millis(3000));
+                w.write_all(json!({ "results": [], "total": 0, "took": 3000 }).to_string().as_bytes())
+            })
+            .create_async()
+            .await;
+
+        let config = UcpConfig {
+            base_url: server.url(),
+            ..Default::default()
+        };
+
+        let client = UcpClient::new(config).unwrap();
+        let cancel = CancellationToken::new();
+        let canceller = cancel.clone();
+        tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_millis(50)).await;
+            canceller.cancel();
+        });
+
+        let query = VectorQuery::builder("test_project", "slow query").build();
+        let started = std::time::Instant::now();
+        let err = client.search_memories_cancellable(query, &cancel).await.unwrap_err();
+        assert!(matches!(err, UcpError::Cancelled), "got {:?}", err);
+        assert!(started.elapsed() < Duration::from_millis(2000));
+    }
+
+    #[tokio::test]
+    async fn test_search_memories_detailed_timings() {
+        let mut server = Server::new_async().await;
+
+        let _m = server.mock("POST", "/api/v1/search")
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(json!({
+                "results": [],
+                "total": 0,
+                "took": 42,
+                "timings": { "embed": 30, "search": 10, "rerank": 2 }
+            }).to_string())
+            .create_async()
+            .await;
+
+        let config = UcpConfig {
+            base_url: server.url(),
+            ..Default::default()
+        };
+
+        let client = UcpClient::new(config).unwrap();
+        let query = VectorQuery::builder("test_project", "slow query").build();
+
+        let detailed = client.search_memories_detailed(query).await.unwrap();
+        assert_eq!(detailed.took, 42);
+        let timings = detailed.timings.unwrap();
+        assert_eq!(timings["embed"], 30);
+        assert_eq!(timings["rerank"], 2);
+    }
+
+    #[tokio::test]
+    async fn test_retries_transient_server_errors() {
+        let mut server = Server::new_async().await;
+
+        let mock_response = json!({
+            "status": "healthy",
+            "version": "1.0.0",
+            "uptime": 3600,
+            "memory_usage": {}
+        });
+
+        let unavailable = server.mock("GET", "/api/v1/health")
+            .with_status(503)
+            .with_body("try again")
+            .expect(2)
+            .create_async()
+            .await;
+
+        let healthy = server.mock("GET", "/api/v1/health")
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(mock_response.to_string())
+            .expect(1)
+            .create_async()
+            .await;
+
+        let config = UcpConfig {
+            base_url: server.url(),
+            retry_policy: RetryPolicy {
+                initial_backoff_ms: 1,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+
+        let client = UcpClient::new(config).unwrap();
+        let health = client.health_check().await.unwrap();
+
+        assert_eq!(health.status, "healthy");
+        unavailable.assert_async().await;
+        healthy.assert_async().await;
+    }
+
+    #[tokio::test]
+    async fn test_timed_out_store_is_not_resent() {
+        let mut server = Server::new_async().await;
+
+        let slow = server.mock("POST", "/api/v1/memory")
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body_from_request(|_| {
+                std::thread::sleep(Duration::from_millis(1500));
+                b"{}".to_vec()
+            })
+            .expect(1)
+            .create_async()
+            .await;
+
+        let config = UcpConfig {
+            base_url: server.url(),
+            timeout_secs: 1,
+            max_retries: 3,
+            retry_policy: RetryPolicy {
+                initial_backoff_ms: 1,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+
+        let client = UcpClient::new(config).unwrap();
+        let request = MemoryRequest::new("test_project", "test_session", "Note");
+        let err = client.store_memory(request).await.unwrap_err();
+
+        assert!(err.is_retryable(), "got {:?}", err);
+        slow.assert_async().await;
+    }
+
+    #[tokio::test]
+    async fn test_retry_non_idempotent_opt_in() {
+        let mut server = Server::new_async().await;
+
+        let unavailable = server.mock("POST", "/api/v1/memory")
+            .with_status(503)
+            .with_body("try again")
+            .expect(3)
+            .create_async()
+            .await;
+
+        let config = UcpConfig {
+            base_url: server.url(),
+            max_retries: 2,
+            retry_policy: RetryPolicy {
+                initial_backoff_ms: 1,
+                retry_non_idempotent: true,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+
+        let client = UcpClient::new(config).unwrap();
+        let request = MemoryRequest::new("test_project", "test_session", "Note");
+        let err = client.store_memory(request).await.unwrap_err();
+
+        assert!(matches!(err, UcpError::ServiceUnavailable { .. }), "got {:?}", err);
+        unavailable.assert_async().await;
+    }
+
+    /// Clock that records requested sleeps and returns immediately
+    #[derive(Debug, Default)]
+    struct RecordingClock {
+        sleeps: std::sync::Mutex<Vec<Duration>>,
+    }
+
+    impl Clock for RecordingClock {
+        fn now(&self) -> std::time::Instant {
+            std::time::Instant::now()
+        }
+
+        fn sleep(&self, duration: Duration) -> crate::clock::Sleep<'_> {
+            self.sleeps.lock().unwrap().push(duration);
+            Box::pin(std::future::ready(()))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_backoff_sleeps_on_configured_clock() {
+        let mut server = Server::new_async().await;
+
+        let _m = server.mock("GET", "/api/v1/health")
+            .with_status(503)
+            .expect(4)
+            .create_async()
+            .await;
+
+        let clock = Arc::new(RecordingClock::default());
+        let config = UcpConfig {
+            base_url: server.url(),
+            max_retries: 3,
+            retry_policy: RetryPolicy {
+                initial_backoff_ms: 100,
+                jitter: false,
+                ..Default::default()
+            },
+            clock: Some(clock.clone()),
+            ..Default::default()
+        };
+
+        let client = UcpClient::new(config).unwrap();
+        assert!(client.health_check().await.is_err());
+
+        let sleeps = clock.sleeps.lock().unwrap().clone();
+        assert_eq!(sleeps, [100, 200, 400].map(Duration::from_millis));
+    }
+
+    #[tokio::test]
+    async fn test_maintenance_503_honors_retry_after() {
+        let mut server = Server::new_async().await;
+
+        let _short = server.mock("GET", "/api/v1/health")
+            .with_status(503)
+            .with_header("retry-after", "2")
+            .with_body(json!({ "message": "Scheduled maintenance until 02:00 UTC" }).to_string())
+            .expect(4)
+            .create_async()
+            .await;
+        let _long = server.mock("GET", "/api/v1/stats/test_project")
+            .with_status(503)
+            .with_header("retry-after", "3600")
+            .with_body("Down for maintenance")
+            .expect(1)
+            .create_async()
+            .await;
+
+        let clock = Arc::new(RecordingClock::default());
+        let config = UcpConfig {
+            base_url: server.url(),
+            max_retries: 3,
+            retry_policy: RetryPolicy {
+                jitter: false,
+                ..Default::default()
+            },
+            clock: Some(clock.clone()),
+            ..Default::default()
+        };
+
+        let client = UcpClient::new(config).unwrap();
+        match client.health_check().await.unwrap_err() {
+            UcpError::ServiceUnavailable { retry_after, message } => {
+                assert_eq!(retry_after, Some(Duration::from_secs(2)));
+                assert_eq!(message, "Scheduled maintenance until 02:00 UTC");
+            }
+            other => panic!("expected ServiceUnavailable, got {:?}", other),
+        }
+        assert_eq!(*clock.sleeps.lock().unwrap(), [Duration::from_secs(2); 3]);
+
+        // Waits beyond the longest backoff are left to the caller
+        clock.sleeps.lock().unwrap().clear();
+        match client.get_stats("test_project").await.unwrap_err() {
+            UcpError::ServiceUnavailable { retry_after, message } => {
+                assert_eq!(retry_after, Some(Duration::from_secs(3600)));
+                assert_eq!(message, "Down for maintenance");
+            }
+            other => panic!("expected ServiceUnavailable, got {:?}", other),
+        }
+        assert!(clock.sleeps.lock().unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_custom_retry_predicate() {
+        let mut server = Server::new_async().await;
+
+        let conflict = server.mock("GET", "/api/v1/health")
+            .with_status(409)
+            .with_body(json!({ "message": "index busy", "code": "INDEX_BUSY" }).to_string())
+            .expect(2)
+            .create_async()
+            .await;
+
+        let config = UcpConfig {
+            base_url: server.url(),
+            retry_policy: RetryPolicy {
+                initial_backoff_ms: 1,
+                retry_if: Some(Arc::new(|err: &UcpError, attempts: u32| {
+                    matches!(err, UcpError::ServerError { status: Some(409), .. }) && attempts < 2
+                })),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+
+        let client = UcpClient::new(config).unwrap();
+        let err = client.health_check().await.unwrap_err();
+
+        assert!(matches!(err, UcpError::ServerError { status: Some(409), .. }));
+        conflict.assert_async().await;
+    }
+
+    #[tokio::test]
+    async fn test_quota_exceeded_errors() {
+        let mut server = Server::new_async().await;
+
+        let _coded = server.mock("POST", "/api/v1/memory")
+            .with_status(403)
+            .with_body(json!({
+                "message": "Project storage quota exceeded",
+                "code": "QUOTA_EXCEEDED",
+                "details": { "project": "test_project", "limit": 1048576 }
+            }).to_string())
+            .create_async()
+            .await;
+
+        let _insufficient = server.mock("GET", "/api/v1/health")
+            .with_status(507)
+            .with_body("Insufficient Storage")
+            .create_async()
+            .await;
+
+        let config = UcpConfig {
+            base_url: server.url(),
+            ..Default::default()
+        };
+
+        let client = UcpClient::new(config).unwrap();
+
+        let request = MemoryRequest {
+            project: "test_project".to_string(),
+            session: "test_session".to_string(),
+            content: "Test memory content".to_string(),
+            metadata: HashMap::new(),
+            tags: vec![],
+            collection: None,
+            parent_id: None,
+            embedding: None,
+            ttl_secs: None,
+        };
+
+        match client.store_memory(request).await.unwrap_err() {
+            UcpError::QuotaExceeded { project, limit } => {
+                assert_eq!(project.as_deref(), Some("test_project"));
+                assert_eq!(limit, Some(1048576));
+            }
+            other => panic!("expected QuotaExceeded, got {:?}", other),
+        }
+
+        let err = client.health_check().await.unwrap_err();
+        assert!(matches!(err, UcpError::QuotaExceeded { project: None, limit: None }));
+    }
+
+    #[tokio::test]
+    async fn test_exhausted_retry_budget_stops_retrying() {
+        let mut server = Server::new_async().await;
+
+        let unavailable = server.mock("GET", "/api/v1/health")
+            .with_status(503)
+            .with_body("try again")
+            .expect(4)
+            .create_async()
+            .await;
+
+        let config = UcpConfig {
+            base_url: server.url(),
+            max_retries: 5,
+            retry_policy: RetryPolicy {
+                initial_backoff_ms: 1,
+                budget_max_tokens: 2,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+
+        // Clones share the budget, so the second call is not retried
+        let client = UcpClient::new(config).unwrap();
+        let clone = client.clone();
+
+        let err = client.health_check().await.unwrap_err();
+        assert!(matches!(err, UcpError::ServiceUnavailable { .. }));
+        let err = clone.health_check().await.unwrap_err();
+        assert!(matches!(err, UcpError::ServiceUnavailable { .. }));
+
+        // 1 + 2 budgeted retries for the first call, 1 attempt for the second
+        unavailable.assert_async().await;
+    }
+
+    #[tokio::test]
+    async fn test_list_projects_follows_cursors() {
+        let mut server = Server::new_async().await;
+
+        let _first = server.mock("GET", "/api/v1/projects")
+            .match_query(mockito::Matcher::Exact("limit=100".into()))
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(json!({ "projects": ["alpha", "beta"], "next_cursor": "c1" }).to_string())
+            .create_async()
+            .await;
+
+        let _second = server.mock("GET", "/api/v1/projects")
+            .match_query(mockito::Matcher::Exact("limit=100&cursor=c1".into()))
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(json!({ "projects": ["gamma"] }).to_string())
+            .create_async()
+            .await;
+
+        let config = UcpConfig {
+            base_url: server.url(),
+            ..Default::default()
+        };
+
+        let client = UcpClient::new(config).unwrap();
+        let projects = client.list_projects().await.unwrap();
+        assert_eq!(projects, vec!["alpha", "beta", "gamma"]);
     }
 
-    /// Delete memory by ID
-    pub async fn delete_memory(&self, project: &str, memory_id: &str) -> Result<()> {
-        let url = format!(
-            "{}/api/v1/memory/{}/{}",
-            self.config.base_url, project, memory_id
-        );
-
-        let response = self.client.delete(&url).send().await?;
-
-        if response.status().is_success() {
-            Ok(())
-        } else {
-            Err(self.handle_error_response(response).await)
-        }
-    }
+    #[tokio::test]
+    async fn test_iter_memories_pages_lazily() {
+        let mut server = Server::new_async().await;
+        let memory = |id: &str| json!({
+            "id": id,
+            "content": "Note",
+            "metadata": {},
+            "tags": ["rust"],
+            "timestamp": 1
+        });
 
-    /// List all projects available
-    pub async fn list_projects(&self) -> Result<Vec<String>> {
-        let url = format!("{}/api/v1/projects", self.config.base_url);
+        let first = server.mock("POST", "/api/v1/memory/test_project/list")
+            .match_body(mockito::Matcher::Json(json!({ "tags": ["rust"], "limit": 100 })))
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(json!({ "memories": [memory("m1"), memory("m2")], "next_cursor": "c1" }).to_string())
+            .create_async()
+            .await;
 
-        let response = self.client.get(&url).send().await?;
-        let projects_response: ProjectsResponse = self.handle_response(response).await?;
-        Ok(projects_response.projects)
-    }
+        let second = server.mock("POST", "/api/v1/memory/test_project/list")
+            .match_body(mockito::Matcher::Json(json!({ "tags": ["rust"], "limit": 100, "cursor": "c1" })))
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(json!({ "memories": [memory("m3")] }).to_string())
+            .expect(1)
+            .create_async()
+            .await;
 
-    /// Get statistics for a project
-    pub async fn get_stats(&self, project: &str) -> Result<ProjectStats> {
-        let url = format!("{}/api/v1/stats/{}", self.config.base_url, project);
+        let config = UcpConfig {
+            base_url: server.url(),
+            ..Default::default()
+        };
 
-        let response = self.client.get(&url).send().await?;
-        self.handle_response(response).await
-    }
+        let client = UcpClient::new(config).unwrap();
+        let filter = MemoryFilter::new().with_tag("rust");
 
-    /// Health check endpoint
-    pub async fn health_check(&self) -> Result<HealthStatus> {
-        let url = format!("{}/api/v1/health", self.config.base_url);
+        // Stopping within the first page never fetches the second
+        let head: Vec<_> = client.iter_memories("test_project", filter.clone()).take(2).collect().await;
+        assert_eq!(head.len(), 2);
+        first.assert_async().await;
 
-        let response = self.client.get(&url).send().await?;
-        self.handle_response(response).await
+        let ids: Vec<String> = client
+            .iter_memories("test_project", filter)
+            .map(|memory| memory.unwrap().id)
+            .collect()
+            .await;
+        assert_eq!(ids, ["m1", "m2", "m3"]);
+        second.assert_async().await;
     }
 
-    /// Generic response handler
-    async fn handle_response<T>(&self, response: Response) -> Result<T>
-    where
-        T: for<'de> Deserialize<'de>,
-    {
-        let status = response.status();
-        
-        if status.is_success() {
-            let data = response.json::<T>().await?;
-            Ok(data)
-        } else {
-            Err(self.handle_error_response(response).await)
-        }
-    }
 
-    /// Handle error responses
-    async fn handle_error_response(&self, response: Response) -> UcpError {
-        let status = response.status();
-        
-        match status.as_u16() {
-            401 => UcpError::AuthenticationError,
-            429 => UcpError::RateLimitError,
-            _ => {
-                let error_text = response
-                    .text()
-                    .await
-                    .unwrap_or_else(|_| format!("HTTP {} error", status));
-                
-                // Try to parse as structured error
-                if let Ok(error_response) = serde_json::from_str::<ErrorResponse>(&error_text) {
-                    UcpError::ServerError {
-                        message: error_response.message,
-                    }
-                } else {
-                    UcpError::ServerError {
-                        message: error_text,
-                    }
-                }
-            }
-        }
-    }
-}
+    #[tokio::test]
+    async fn test_find_duplicates() {
+        let mut server = Server::new_async().await;
+        let memory = |id: &str, embedding: [f32; 2]| json!({
+            "id": id,
+            "content": "Note",
+            "timestamp": 1,
+            "embedding": embedding
+        });
 
-/// Response for search operations
-#[derive(Debug, Serialize, Deserialize)]
-struct SearchResponse {
-    results: Vec<MemoryResponse>,
-    total: u64,
-    took: u64, // Time taken in milliseconds
-}
+        let _m = server.mock("POST", "/api/v1/memory/test_project/list")
+            .match_body(mockito::Matcher::PartialJson(json!({ "include_embeddings": true })))
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(json!({ "memories": [
+                memory("m1", [1.0, 0.0]),
+                memory("m2", [0.0, 1.0]),
+                memory("m3", [1.0, 0.0])
+            ] }).to_string())
+            .create_async()
+            .await;
 
-/// Response for projects list
-#[derive(Debug, Serialize, Deserialize)]
-struct ProjectsResponse {
-    projects: Vec<String>,
-}
+        let config = UcpConfig {
+            base_url: server.url(),
+            ..Default::default()
+        };
+        let client = UcpClient::new(config).unwrap();
 
-/// Project statistics
-#[derive(Debug, Serialize, Deserialize)]
-pub struct ProjectStats {
-    pub project: String,
-    pub total_memories: u64,
-    pub total_sessions: u64,
-    pub total_size_bytes: u64,
-    pub created_at: u64,
-    pub last_updated: u64,
-}
+        let groups = client.find_duplicates("test_project", 0.95).await.unwrap();
+        assert_eq!(groups.len(), 1);
+        assert_eq!(groups[0].memory_ids, ["m1", "m3"]);
+    }
 
-/// Health status response
-#[derive(Debug, Serialize, Deserialize)]
-pub struct HealthStatus {
-    pub status: String,
-    pub version: String,
-    pub uptime: u64,
-    pub memory_usage: HashMap<String, u64>,
-}
+    #[tokio::test]
+    async fn test_find_duplicates_without_embeddings_fails() {
+        let mut server = Server::new_async().await;
 
-/// Error response from UCP server
-#[derive(Debug, Serialize, Deserialize)]
-struct ErrorResponse {
-    message: String,
-    code: Option<String>,
-    details: Option<HashMap<String, serde_json::Value>>,
-}
+        let _m = server.mock("POST", "/api/v1/memory/test_project/list")
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(json!({ "memories": [
+                { "id": "m1", "content": "Note", "timestamp": 1 },
+                { "id": "m2", "content": "Note", "timestamp": 1 }
+            ] }).to_string())
+            .create_async()
+            .await;
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use mockito::{Mock, Server};
-    use serde_json::json;
+        let config = UcpConfig {
+            base_url: server.url(),
+            ..Default::default()
+        };
+        let client = UcpClient::new(config).unwrap();
 
+        match client.find_duplicates("test_project", 0.95).await {
+            Err(UcpError::ServerError { message, .. }) => assert!(message.contains("m1"), "{}", message),
+            other => panic!("expected a missing embedding error, got {:?}", other),
+        }
+    }
     #[tokio::test]
-    async fn test_store_memory() {
+    async fn test_content_type_only_on_requests_with_body() {
         let mut server = Server::new_async().await;
-        
-        let mock_response = json!({
-            "id": "mem_123",
-            "content": "Test memory content",
-            "score": null,
-            "metadata": {},
-            "tags": ["test"],
-            "timestamp": 1234567890
-        });
 
-        let _m = server.mock("POST", "/api/v1/memory")
+        let health = server.mock("GET", "/api/v1/health")
+            .match_header("content-type", mockito::Matcher::Missing)
             .with_status(200)
             .with_header("content-type", "application/json")
-            .with_body(mock_response.to_string())
+            .with_body(json!({
+                "status": "healthy",
+                "version": "1.0.0",
+                "uptime": 1,
+                "memory_usage": {}
+            }).to_string())
+            .create_async()
+            .await;
+
+        let store = server.mock("POST", "/api/v1/memory")
+            .match_header("content-type", "application/json")
+            .with_status(202)
             .create_async()
             .await;
 
@@ -308,24 +5945,114 @@ mod tests {
         };
 
         let client = UcpClient::new(config).unwrap();
-        
+        client.health_check().await.unwrap();
+
         let request = MemoryRequest {
             project: "test_project".to_string(),
             session: "test_session".to_string(),
             content: "Test memory content".to_string(),
             metadata: HashMap::new(),
-            tags: vec!["test".to_string()],
+            tags: vec![],
+            collection: None,
+            parent_id: None,
+            embedding: None,
+            ttl_secs: None,
         };
+        client.store_memory(request).await.unwrap();
 
-        let response = client.store_memory(request).await.unwrap();
-        assert_eq!(response.id, "mem_123");
-        assert_eq!(response.content, "Test memory content");
+        health.assert_async().await;
+        store.assert_async().await;
+    }
+
+    /// Serve two connections: the first drops after one NDJSON line, the
+    /// second returns the remaining lines. Returns the second request's head.
+    async fn serve_dropped_stream(listener: tokio::net::TcpListener) -> String {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        async fn read_request(socket: &mut tokio::net::TcpStream) -> String {
+            let mut buf = vec![0u8; 8192];
+            let mut len = 0;
+            loop {
+                len += socket.read(&mut buf[len..]).await.unwrap();
+                let text = String::from_utf8_lossy(&buf[..len]).to_string();
+                if let Some(head_end) = text.find("\r\n\r\n") {
+                    let content_length = text[..head_end]
+                        .to_ascii_lowercase()
+                        .lines()
+                        .find_map(|l| l.strip_prefix("content-length:").map(|v| v.trim().to_string()))
+                        .map_or(0, |v| v.parse::<usize>().unwrap());
+                    if len >= head_end + 4 + content_length {
+                        return text[..head_end].to_string();
+                    }
+                }
+            }
+        }
+
+        let line = |id: &str| {
+            let memory = json!({
+                "id": id,
+                "content": id,
+                "score": 0.9,
+                "metadata": {},
+                "tags": [],
+                "timestamp": 1
+            });
+            format!("{}\n", memory)
+        };
+
+        let (mut socket, _) = listener.accept().await.unwrap();
+        read_request(&mut socket).await;
+        let first = line("mem_1");
+        socket.write_all(format!(
+            "HTTP/1.1 200 OK\r\ncontent-type: application/x-ndjson\r\ncontent-length: {}\r\n\r\n{}",
+            first.len() + 100,
+            first
+        ).as_bytes()).await.unwrap();
+        drop(socket);
+
+        let (mut socket, _) = listener.accept().await.unwrap();
+        let head = read_request(&mut socket).await;
+        let rest = format!("{}{}", line("mem_1"), line("mem_2"));
+        socket.write_all(format!(
+            "HTTP/1.1 200 OK\r\ncontent-type: application/x-ndjson\r\ncontent-length: {}\r\nconnection: close\r\n\r\n{}",
+            rest.len(),
+            rest
+        ).as_bytes()).await.unwrap();
+        head
     }
 
     #[tokio::test]
-    async fn test_search_memories() {
+    async fn test_search_stream_reconnects_after_disconnect() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let server = tokio::spawn(serve_dropped_stream(listener));
+
+        let config = UcpConfig {
+            base_url: format!("http://{}", addr),
+            ..Default::default()
+        };
+
+        let client = UcpClient::new(config).unwrap();
+        let query = VectorQuery::builder("test_project", "test query").build();
+        let options = StreamOptions {
+            reconnect: true,
+            ..Default::default()
+        };
+
+        let stream = client.search_memories_stream_with(query, options).await.unwrap();
+        let results: Vec<_> = stream.collect().await;
+        let ids: Vec<_> = results.into_iter().map(|r| r.unwrap().id).collect();
+        assert_eq!(ids, vec!["mem_1", "mem_2"]);
+
+        let head = server.await.unwrap().to_ascii_lowercase();
+        assert!(head.contains("last-event-id: mem_1"));
+    }
+
+    #[cfg(feature = "coalesce")]
+    #[tokio::test]
+    async fn test_identical_searches_are_coalesced() {
         let mut server = Server::new_async().await;
-        
+
         let mock_response = json!({
             "results": [
                 {
@@ -333,7 +6060,7 @@ mod tests {
                     "content": "Test memory content",
                     "score": 0.95,
                     "metadata": {},
-                    "tags": ["test"],
+                    "tags": [],
                     "timestamp": 1234567890
                 }
             ],
@@ -341,10 +6068,14 @@ mod tests {
             "took": 15
         });
 
-        let _m = server.mock("POST", "/api/v1/search")
+        let search = server.mock("POST", "/api/v1/search")
             .with_status(200)
             .with_header("content-type", "application/json")
-            .with_body(mock_response.to_string())
+            .with_chunked_body(move |w| {
+                std::thread::sleep(Duration::from_millis(200));
+                w.write_all(mock_response.to_string().as_bytes())
+            })
+            .expect(1)
             .create_async()
             .await;
 
@@ -354,20 +6085,102 @@ mod tests {
         };
 
         let client = UcpClient::new(config).unwrap();
-        
-        let query = VectorQuery {
-            project: "test_project".to_string(),
-            session: Some("test_session".to_string()),
-            query: "test query".to_string(),
-            limit: 10,
-            threshold: 0.7,
-            tags: None,
+        let query = VectorQuery::builder("test_project", "test query").build();
+
+        let searches: Vec<_> = (0..5)
+            .map(|_| {
+                let client = client.clone();
+                let query = query.clone();
+                tokio::spawn(async move { client.search_memories(query).await })
+            })
+            .collect();
+
+        for handle in searches {
+            assert_eq!(handle.await.unwrap().unwrap()[0].id, "mem_123");
+        }
+
+        search.assert_async().await;
+    }
+
+    #[tokio::test]
+    async fn test_negotiate_version() {
+        let mut server = Server::new_async().await;
+
+        let _versions = server.mock("GET", "/api/versions")
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(json!({ "versions": ["v1", "v2", "v9"] }).to_string())
+            .create_async()
+            .await;
+
+        let health = server.mock("GET", "/api/v2/health")
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(json!({
+                "status": "healthy",
+                "version": "2.0.0",
+                "uptime": 1,
+                "memory_usage": {}
+            }).to_string())
+            .create_async()
+            .await;
+
+        let config = UcpConfig {
+            base_url: server.url(),
+            ..Default::default()
         };
 
-        let results = client.search_memories(query).await.unwrap();
-        assert_eq!(results.len(), 1);
-        assert_eq!(results[0].id, "mem_123");
-        assert_eq!(results[0].score, Some(0.95));
+        let mut client = UcpClient::new(config).unwrap();
+        assert_eq!(client.negotiate_version().await.unwrap(), ApiVersion::V2);
+
+        client.health_check().await.unwrap();
+        health.assert_async().await;
+    }
+
+    #[tokio::test]
+    async fn test_max_concurrent_requests() {
+        let mut server = Server::new_async().await;
+
+        let body = json!({
+            "status": "healthy",
+            "version": "1.0.0",
+            "uptime": 1,
+            "memory_usage": {}
+        })
+        .to_string();
+
+        let _m = server.mock("GET", "/api/v1/health")
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body_from_request(move |_| {
+                std::thread::sleep(Duration::from_millis(150));
+                body.clone().into_bytes()
+            })
+            .expect(2)
+            .create_async()
+            .await;
+
+        let config = UcpConfig {
+            base_url: server.url(),
+            max_concurrent_requests: Some(1),
+            ..Default::default()
+        };
+
+        let client = UcpClient::new(config).unwrap();
+        let started = std::time::Instant::now();
+
+        let first = tokio::spawn({
+            let client = client.clone();
+            async move { client.health_check().await }
+        });
+        let second = tokio::spawn({
+            let client = client.clone();
+            async move { client.health_check().await }
+        });
+
+        first.await.unwrap().unwrap();
+        second.await.unwrap().unwrap();
+        assert!(started.elapsed() >= Duration::from_millis(300));
     }
 
     #[tokio::test]
@@ -403,4 +6216,39 @@ mod tests {
         assert_eq!(health.version, "1.0.0");
         assert_eq!(health.uptime, 3600);
     }
-}
\ No newline at end of file
+
+    #[tokio::test]
+    async fn test_unexpected_body_reports_snippet() {
+        let mut server = Server::new_async().await;
+
+        let _m = server.mock("GET", "/api/v1/health")
+            .with_status(200)
+            .with_header("content-type", "text/html")
+            .with_body("<html><body>Gateway maintenance</body></html>")
+            .create_async()
+            .await;
+
+        let config = UcpConfig {
+            base_url: server.url(),
+            ..Default::default()
+        };
+
+        let client = UcpClient::new(config).unwrap();
+        let err = client.health_check().await.unwrap_err();
+
+        match err {
+            UcpError::DeserializationError { body_snippet, .. } => {
+                assert!(body_snippet.starts_with("<html>"));
+            }
+            other => panic!("expected DeserializationError, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_body_snippet_truncates_on_char_boundary() {
+        let body = "é".repeat(BODY_SNIPPET_LIMIT);
+        let snippet = body_snippet(&body);
+        assert!(snippet.ends_with("..."));
+        assert!(snippet.len() <= BODY_SNIPPET_LIMIT + 3);
+    }
+}
@@ -0,0 +1,42 @@
+//! UCP Clock
+//!
+//! Time source used for retry backoff, latency measurement, and the
+//! timestamps the client fills in itself. The default
+//! `SystemClock` uses real time; tests can substitute a clock that records
+//! or skips sleeps to check timing without waiting.
+
+use std::fmt;
+use std::future::Future;
+use std::pin::Pin;
+use std::time::{Duration, Instant, SystemTime};
+
+/// Future returned by `Clock::sleep`
+pub type Sleep<'a> = Pin<Box<dyn Future<Output = ()> + Send + 'a>>;
+
+/// Source of the current time and of delays
+pub trait Clock: fmt::Debug + Send + Sync {
+    /// Current instant
+    fn now(&self) -> Instant;
+
+    /// Wait for `duration`
+    fn sleep(&self, duration: Duration) -> Sleep<'_>;
+
+    /// Current wall-clock time, for timestamps the client fills in itself
+    fn wall_time(&self) -> SystemTime {
+        SystemTime::now()
+    }
+}
+
+/// Real time, backed by `Instant::now` and `tokio::time::sleep` (default)
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> Instant {
+        Instant::now()
+    }
+
+    fn sleep(&self, duration: Duration) -> Sleep<'_> {
+        Box::pin(tokio::time::sleep(duration))
+    }
+}
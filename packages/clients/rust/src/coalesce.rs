@@ -0,0 +1,182 @@
+//! UCP Request Coalescing
+//!
+//! Single-flight deduplication of identical in-flight searches, available
+//! with the `coalesce` feature. While a search is in flight, callers issuing
+//! the same query wait for its result instead of sending their own request.
+//!
+//! The tradeoff is staleness: a caller that joins an in-flight search gets
+//! results computed from when that search started, so memories stored in
+//! between may be missing.
+
+use super::types::{MemoryResponse, Result, UcpError};
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::{Arc, Mutex, MutexGuard};
+use tokio::sync::broadcast;
+
+/// Result shared between coalesced callers
+type SharedResult = std::result::Result<Vec<MemoryResponse>, Arc<UcpError>>;
+
+/// In-flight searches keyed by their serialized query
+type Inflight = HashMap<String, broadcast::Sender<SharedResult>>;
+
+/// Tracks in-flight searches
+#[derive(Debug, Default)]
+pub(crate) struct Coalescer {
+    inflight: Mutex<Inflight>,
+}
+
+/// Removes the in-flight entry when the leading request finishes or is
+/// dropped, so waiters never block on an abandoned request
+struct InflightGuard<'a> {
+    coalescer: &'a Coalescer,
+    key: &'a str,
+}
+
+impl Drop for InflightGuard<'_> {
+    fn drop(&mut self) {
+        self.coalescer.lock().remove(self.key);
+    }
+}
+
+impl Coalescer {
+    fn lock(&self) -> MutexGuard<'_, Inflight> {
+        self.inflight.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Run `search` unless an identical search is already in flight, in
+    /// which case wait for and share its result
+    pub(crate) async fn run<F, Fut>(&self, key: String, search: F) -> Result<Vec<MemoryResponse>>
+    where
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = Result<Vec<MemoryResponse>>>,
+    {
+        let waiting = {
+            let mut inflight = self.lock();
+            match inflight.get(&key) {
+                Some(sender) => Some(sender.subscribe()),
+                None => {
+                    let (sender, _) = broadcast::channel(1);
+                    inflight.insert(key.clone(), sender);
+                    None
+                }
+            }
+        };
+
+        if let Some(mut receiver) = waiting {
+            return match receiver.recv().await {
+                Ok(Ok(results)) => Ok(results),
+                Ok(Err(err)) => Err(UcpError::Coalesced(err)),
+                // The leading request was dropped; search on our own
+                Err(_) => search().await,
+            };
+        }
+
+        let guard = InflightGuard {
+            coalescer: self,
+            key: &key,
+        };
+        let result = search().await;
+
+        let sender = self.lock().remove(&key);
+        drop(guard);
+
+        let Some(sender) = sender.filter(|sender| sender.receiver_count() > 0) else {
+            return result;
+        };
+
+        // The leader keeps its own error as is; only followers see it
+        // wrapped in `Coalesced`
+        let shared = match result {
+            Ok(ref results) => Ok(results.clone()),
+            Err(ref err) => Err(Arc::new(follower_copy(err))),
+        };
+        let _ = sender.send(shared);
+        result
+    }
+}
+
+/// Copy of `err` for followers
+///
+/// Transport and parse errors wrap payloads that cannot be cloned; these
+/// reach followers as `ServerError`s carrying the original message and
+/// status.
+fn follower_copy(err: &UcpError) -> UcpError {
+    let transport = |e: &reqwest::Error| UcpError::ServerError {
+        status: e.status().map(|status| status.as_u16()),
+        code: None,
+        message: e.to_string(),
+    };
+    let json = |e: &serde_json::Error| <serde_json::Error as serde::de::Error>::custom(e);
+
+    match err {
+        UcpError::HttpError(e) | UcpError::ConnectionError(e) | UcpError::BodyTimeout(e) => transport(e),
+        UcpError::Timeout(idle) => UcpError::Timeout(*idle),
+        UcpError::TruncatedStream(len) => UcpError::TruncatedStream(*len),
+        UcpError::Cancelled => UcpError::Cancelled,
+        UcpError::JsonError(e) => UcpError::JsonError(json(e)),
+        UcpError::DeserializationError { source, body_snippet } => UcpError::DeserializationError {
+            source: json(source),
+            body_snippet: body_snippet.clone(),
+        },
+        UcpError::ServerError { status, code, message } => UcpError::ServerError {
+            status: *status,
+            code: code.clone(),
+            message: message.clone(),
+        },
+        UcpError::AuthenticationError => UcpError::AuthenticationError,
+        UcpError::ServiceUnavailable { retry_after, message } => UcpError::ServiceUnavailable {
+            retry_after: *retry_after,
+            message: message.clone(),
+        },
+        UcpError::RateLimitError => UcpError::RateLimitError,
+        UcpError::NotFound => UcpError::NotFound,
+        UcpError::VersionNotFound { memory_id, version } => UcpError::VersionNotFound {
+            memory_id: memory_id.clone(),
+            version: *version,
+        },
+        UcpError::Conflict => UcpError::Conflict,
+        UcpError::QuotaExceeded { project, limit } => UcpError::QuotaExceeded {
+            project: project.clone(),
+            limit: *limit,
+        },
+        UcpError::JobFailed { job_id, message } => UcpError::JobFailed {
+            job_id: job_id.clone(),
+            message: message.clone(),
+        },
+        UcpError::Coalesced(inner) => UcpError::Coalesced(inner.clone()),
+        UcpError::ConfigError(message) => UcpError::ConfigError(message.clone()),
+        UcpError::CodecError(message) => UcpError::CodecError(message.clone()),
+        UcpError::CipherError(message) => UcpError::CipherError(message.clone()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[tokio::test]
+    async fn test_leader_error_is_not_wrapped() {
+        let coalescer = Coalescer::default();
+        let key = "query".to_string();
+
+        let leader = coalescer.run(key.clone(), || async {
+            tokio::time::sleep(Duration::from_millis(100)).await;
+            Err(UcpError::RateLimitError)
+        });
+        let follower = async {
+            tokio::time::sleep(Duration::from_millis(20)).await;
+            coalescer
+                .run(key.clone(), || async { panic!("follower must not search") })
+                .await
+        };
+
+        let (leader, follower) = tokio::join!(leader, follower);
+        assert!(matches!(leader, Err(UcpError::RateLimitError)), "got {:?}", leader);
+        match follower {
+            Err(UcpError::Coalesced(inner)) => assert!(matches!(*inner, UcpError::RateLimitError)),
+            other => panic!("expected a coalesced error, got {:?}", other),
+        }
+    }
+}
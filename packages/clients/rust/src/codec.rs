@@ -0,0 +1,121 @@
+//! UCP Wire Codecs
+//!
+//! Pluggable serialization of request and response bodies. JSON is the
+//! default; MessagePack and CBOR are available behind the `msgpack` and
+//! `cbor` features.
+
+use super::types::Result;
+#[cfg(any(feature = "msgpack", feature = "cbor"))]
+use super::types::UcpError;
+use std::fmt;
+
+/// Encodes and decodes HTTP bodies for a given wire format
+///
+/// Codecs operate on `serde_json::Value` so they can be shared as trait
+/// objects; the client converts to and from typed values around them.
+pub trait WireCodec: fmt::Debug + Send + Sync {
+    /// MIME type used for the `Content-Type` and `Accept` headers
+    fn content_type(&self) -> &'static str;
+
+    /// Encode a value into a request body
+    fn encode(&self, value: &serde_json::Value) -> Result<Vec<u8>>;
+
+    /// Decode a response body into a value
+    fn decode(&self, body: &[u8]) -> Result<serde_json::Value>;
+}
+
+/// JSON codec (default)
+#[derive(Debug, Clone, Copy, Default)]
+pub struct JsonCodec;
+
+impl WireCodec for JsonCodec {
+    fn content_type(&self) -> &'static str {
+        "application/json"
+    }
+
+    fn encode(&self, value: &serde_json::Value) -> Result<Vec<u8>> {
+        Ok(serde_json::to_vec(value)?)
+    }
+
+    fn decode(&self, body: &[u8]) -> Result<serde_json::Value> {
+        Ok(serde_json::from_slice(body)?)
+    }
+}
+
+/// MessagePack codec
+#[cfg(feature = "msgpack")]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct MsgPackCodec;
+
+#[cfg(feature = "msgpack")]
+impl WireCodec for MsgPackCodec {
+    fn content_type(&self) -> &'static str {
+        "application/msgpack"
+    }
+
+    fn encode(&self, value: &serde_json::Value) -> Result<Vec<u8>> {
+        rmp_serde::to_vec_named(value).map_err(|e| UcpError::CodecError(e.to_string()))
+    }
+
+    fn decode(&self, body: &[u8]) -> Result<serde_json::Value> {
+        rmp_serde::from_slice(body).map_err(|e| UcpError::CodecError(e.to_string()))
+    }
+}
+
+/// CBOR codec
+#[cfg(feature = "cbor")]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct CborCodec;
+
+#[cfg(feature = "cbor")]
+impl WireCodec for CborCodec {
+    fn content_type(&self) -> &'static str {
+        "application/cbor"
+    }
+
+    fn encode(&self, value: &serde_json::Value) -> Result<Vec<u8>> {
+        let mut body = Vec::new();
+        ciborium::ser::into_writer(value, &mut body)
+            .map_err(|e| UcpError::CodecError(e.to_string()))?;
+        Ok(body)
+    }
+
+    fn decode(&self, body: &[u8]) -> Result<serde_json::Value> {
+        ciborium::de::from_reader(body).map_err(|e| UcpError::CodecError(e.to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn round_trip(codec: &dyn WireCodec) {
+        let value = json!({
+            "id": "mem_123",
+            "score": 0.5,
+            "tags": ["a", "b"],
+            "metadata": { "nested": { "n": 1 } }
+        });
+
+        let body = codec.encode(&value).unwrap();
+        assert_eq!(codec.decode(&body).unwrap(), value);
+    }
+
+    #[test]
+    fn test_json_round_trip() {
+        round_trip(&JsonCodec);
+    }
+
+    #[cfg(feature = "msgpack")]
+    #[test]
+    fn test_msgpack_round_trip() {
+        round_trip(&MsgPackCodec);
+    }
+
+    #[cfg(feature = "cbor")]
+    #[test]
+    fn test_cbor_round_trip() {
+        round_trip(&CborCodec);
+    }
+}
@@ -0,0 +1,85 @@
+//! UCP Stream Compression
+//!
+//! Decoding of compressed streaming response bodies. Servers may gzip
+//! large NDJSON streams (`Content-Encoding: gzip`); with the `gzip` feature
+//! the client asks for gzip on streaming requests and decompresses the body
+//! incrementally before lines are split. Without it, a gzipped body is
+//! rejected with an error naming the feature.
+
+use bytes::Bytes;
+use reqwest::header::CONTENT_ENCODING;
+use reqwest::{RequestBuilder, Response};
+use std::pin::Pin;
+use tokio_stream::{Stream, StreamExt};
+
+/// Failure while reading a streaming body
+#[derive(Debug)]
+pub(crate) enum ChunkError {
+    /// The connection failed or timed out
+    Transport(reqwest::Error),
+    /// The body could not be decoded
+    Decode(String),
+}
+
+/// Decoded chunks of a streaming body
+pub(crate) type BodyStream = Pin<Box<dyn Stream<Item = Result<Bytes, ChunkError>> + Send>>;
+
+/// Ask for a gzipped body where the `gzip` feature can decode it
+pub(crate) fn accept_compressed(request: RequestBuilder) -> RequestBuilder {
+    #[cfg(feature = "gzip")]
+    let request = request.header(reqwest::header::ACCEPT_ENCODING, "gzip");
+
+    request
+}
+
+/// Stream a response body, decoding its `Content-Encoding`
+pub(crate) fn body_stream(response: Response) -> BodyStream {
+    let encoding = response
+        .headers()
+        .get(CONTENT_ENCODING)
+        .and_then(|value| value.to_str().ok())
+        .map(|value| value.trim().to_ascii_lowercase());
+
+    match encoding.as_deref() {
+        None | Some("") | Some("identity") => {
+            Box::pin(response.bytes_stream().map(|chunk| chunk.map_err(ChunkError::Transport)))
+        }
+        Some("gzip") | Some("x-gzip") => gunzip(response),
+        Some(other) => failed(format!(
+            "response body uses unsupported content encoding {:?}",
+            other
+        )),
+    }
+}
+
+fn failed(message: String) -> BodyStream {
+    Box::pin(tokio_stream::once(Err(ChunkError::Decode(message))))
+}
+
+#[cfg(feature = "gzip")]
+fn gunzip(response: Response) -> BodyStream {
+    use async_compression::tokio::bufread::GzipDecoder;
+    use std::io;
+    use tokio_util::io::{ReaderStream, StreamReader};
+
+    let compressed = response
+        .bytes_stream()
+        .map(|chunk| chunk.map_err(io::Error::other));
+    let decoder = GzipDecoder::new(StreamReader::new(compressed));
+
+    Box::pin(ReaderStream::new(decoder).map(|chunk| {
+        chunk.map_err(|e| {
+            // Transport errors pass through the reader wrapped in io::Error
+            let message = e.to_string();
+            match e.into_inner().map(|inner| inner.downcast::<reqwest::Error>()) {
+                Some(Ok(error)) => ChunkError::Transport(*error),
+                _ => ChunkError::Decode(format!("invalid gzip response body: {}", message)),
+            }
+        })
+    }))
+}
+
+#[cfg(not(feature = "gzip"))]
+fn gunzip(_response: Response) -> BodyStream {
+    failed("response body is gzip-encoded; enable the `gzip` feature to decode it".to_string())
+}
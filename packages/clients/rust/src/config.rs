@@ -0,0 +1,144 @@
+//! UCP Configuration Files
+//!
+//! Loading `UcpConfig` from TOML or YAML files, available with the
+//! `config-file` feature. Environment variables are layered on top:
+//!
+//! - `UCP_BASE_URL`
+//! - `UCP_API_KEY`
+//! - `UCP_TIMEOUT_SECS`
+//! - `UCP_MAX_RETRIES`
+
+use super::types::{Result, UcpConfig, UcpError};
+use std::path::Path;
+
+impl UcpConfig {
+    /// Load configuration from a `.toml`, `.yaml`, or `.yml` file, then
+    /// apply `UCP_*` environment variable overrides
+    ///
+    /// Fields missing from the file take their default values.
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let config_error =
+            |problem: String| UcpError::ConfigError(format!("{}: {}", path.display(), problem));
+
+        let contents = std::fs::read_to_string(path).map_err(|e| config_error(e.to_string()))?;
+
+        let extension = path
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .map(str::to_ascii_lowercase);
+
+        let mut config: UcpConfig = match extension.as_deref() {
+            Some("toml") => toml::from_str(&contents).map_err(|e| config_error(e.to_string()))?,
+            Some("yaml") | Some("yml") => {
+                serde_yaml::from_str(&contents).map_err(|e| config_error(e.to_string()))?
+            }
+            _ => {
+                return Err(config_error(
+                    "unsupported config file extension (expected .toml, .yaml, or .yml)"
+                        .to_string(),
+                ))
+            }
+        };
+
+        config.apply_env_overrides(|name| std::env::var(name).ok())?;
+        Ok(config)
+    }
+
+    /// Override fields from `UCP_*` variables returned by `lookup`
+    fn apply_env_overrides(&mut self, lookup: impl Fn(&str) -> Option<String>) -> Result<()> {
+        let parse_error = |name: &str, value: &str| {
+            UcpError::ConfigError(format!("{}: invalid value {:?}", name, value))
+        };
+
+        if let Some(base_url) = lookup("UCP_BASE_URL") {
+            self.base_url = base_url;
+        }
+        if let Some(api_key) = lookup("UCP_API_KEY") {
+            self.api_key = Some(api_key);
+        }
+        if let Some(value) = lookup("UCP_TIMEOUT_SECS") {
+            self.timeout_secs = value
+                .parse()
+                .map_err(|_| parse_error("UCP_TIMEOUT_SECS", &value))?;
+        }
+        if let Some(value) = lookup("UCP_MAX_RETRIES") {
+            self.max_retries = value
+                .parse()
+                .map_err(|_| parse_error("UCP_MAX_RETRIES", &value))?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    fn write_temp(name: &str, contents: &str) -> PathBuf {
+        let file_name = format!("ucp-client-{}-{}", std::process::id(), name);
+        let path = std::env::temp_dir().join(file_name);
+        std::fs::write(&path, contents).unwrap();
+        path
+    }
+
+    #[test]
+    fn test_from_file_toml_and_yaml() {
+        let toml_path = write_temp(
+            "config.toml",
+            "base_url = \"http://ucp.internal:3001\"\ntimeout_secs = 5\n",
+        );
+        let config = UcpConfig::from_file(&toml_path).unwrap();
+        assert_eq!(config.base_url, "http://ucp.internal:3001");
+        assert_eq!(config.timeout_secs, 5);
+        assert_eq!(config.max_retries, UcpConfig::default().max_retries);
+
+        let yaml_path = write_temp(
+            "config.yaml",
+            "base_url: http://ucp.internal:3001\nmax_retries: 1\n",
+        );
+        let config = UcpConfig::from_file(&yaml_path).unwrap();
+        assert_eq!(config.base_url, "http://ucp.internal:3001");
+        assert_eq!(config.max_retries, 1);
+
+        std::fs::remove_file(toml_path).unwrap();
+        std::fs::remove_file(yaml_path).unwrap();
+    }
+
+    #[test]
+    fn test_from_file_reports_path_and_problem() {
+        let path = write_temp("broken.toml", "timeout_secs = \"soon\"\n");
+
+        match UcpConfig::from_file(&path) {
+            Err(UcpError::ConfigError(message)) => {
+                assert!(message.contains("broken.toml"));
+                assert!(message.contains("timeout_secs"));
+            }
+            other => panic!("expected ConfigError, got {:?}", other),
+        }
+
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_env_overrides_take_precedence() {
+        let mut config = UcpConfig::default();
+        config
+            .apply_env_overrides(|name| match name {
+                "UCP_BASE_URL" => Some("http://override:9000".to_string()),
+                "UCP_MAX_RETRIES" => Some("0".to_string()),
+                _ => None,
+            })
+            .unwrap();
+
+        assert_eq!(config.base_url, "http://override:9000");
+        assert_eq!(config.max_retries, 0);
+
+        let err = config
+            .apply_env_overrides(|name| (name == "UCP_TIMEOUT_SECS").then(|| "soon".to_string()))
+            .unwrap_err();
+        assert!(matches!(err, UcpError::ConfigError(_)));
+    }
+}
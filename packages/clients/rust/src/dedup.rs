@@ -0,0 +1,117 @@
+//! UCP Store Deduplication
+//!
+//! Client-side skipping of repeated stores, enabled by
+//! `UcpConfig::dedup_window`. The client remembers the content hashes of
+//! its most recent stores per project and session; storing content that
+//! matches one of them returns the earlier response without a request.
+//!
+//! This is best-effort: the window is local to one client instance (and
+//! its clones), concurrent identical stores may both be sent, and a memory
+//! deleted on the server since is not noticed.
+
+use super::types::{MemoryRequest, MemoryResponse};
+use super::utils::hash_content;
+use std::collections::VecDeque;
+use std::sync::{Mutex, MutexGuard};
+
+/// Identity of a stored memory's content within its project and session
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct DedupKey {
+    project: String,
+    session: String,
+    /// SHA-256 of the content, as computed by `hash_content`
+    content_hash: String,
+}
+
+impl DedupKey {
+    pub(crate) fn new(request: &MemoryRequest) -> Self {
+        Self {
+            project: request.project.clone(),
+            session: request.session.clone(),
+            content_hash: hash_content(&request.content),
+        }
+    }
+}
+
+/// Most recently stored memories, newest first
+#[derive(Debug)]
+pub(crate) struct DedupWindow {
+    capacity: usize,
+    entries: Mutex<VecDeque<(DedupKey, MemoryResponse)>>,
+}
+
+impl DedupWindow {
+    pub(crate) fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            entries: Mutex::new(VecDeque::with_capacity(capacity)),
+        }
+    }
+
+    fn lock(&self) -> MutexGuard<'_, VecDeque<(DedupKey, MemoryResponse)>> {
+        self.entries.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Response of a recent store with the same key, marking it as
+    /// recently used
+    pub(crate) fn get(&self, key: &DedupKey) -> Option<MemoryResponse> {
+        let mut entries = self.lock();
+        let index = entries.iter().position(|(k, _)| k == key)?;
+        let entry = entries.remove(index)?;
+        let response = entry.1.clone();
+        entries.push_front(entry);
+        Some(response)
+    }
+
+    /// Remember a store, evicting the least recently used entry if full
+    pub(crate) fn insert(&self, key: DedupKey, response: MemoryResponse) {
+        let mut entries = self.lock();
+        entries.retain(|(k, _)| *k != key);
+        if entries.len() == self.capacity {
+            entries.pop_back();
+        }
+        entries.push_front((key, response));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn response(id: &str) -> MemoryResponse {
+        serde_json::from_value(serde_json::json!({
+            "id": id,
+            "project": "p",
+            "content": "c",
+            "metadata": {},
+            "tags": [],
+            "timestamp": 0
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_evicts_least_recently_used() {
+        let window = DedupWindow::new(2);
+        let a = DedupKey::new(&MemoryRequest::new("p", "s", "a"));
+        let b = DedupKey::new(&MemoryRequest::new("p", "s", "b"));
+        let c = DedupKey::new(&MemoryRequest::new("p", "s", "c"));
+
+        window.insert(a.clone(), response("a"));
+        window.insert(b.clone(), response("b"));
+        assert_eq!(window.get(&a).unwrap().id, "a");
+
+        window.insert(c.clone(), response("c"));
+        assert!(window.get(&b).is_none());
+        assert!(window.get(&a).is_some());
+        assert!(window.get(&c).is_some());
+    }
+
+    #[test]
+    fn test_keys_are_scoped_to_project_and_session() {
+        let key = DedupKey::new(&MemoryRequest::new("p", "s", "same"));
+        assert_eq!(key, DedupKey::new(&MemoryRequest::new("p", "s", "same")));
+        assert_ne!(key, DedupKey::new(&MemoryRequest::new("p", "other", "same")));
+        assert_ne!(key, DedupKey::new(&MemoryRequest::new("other", "s", "same")));
+    }
+}
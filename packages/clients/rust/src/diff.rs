@@ -0,0 +1,192 @@
+//! UCP Memory Diffs
+//!
+//! Comparison of two stored versions of a memory, for audit trails and
+//! rollback UIs. Content is compared line by line; metadata and tags are
+//! compared by key and by value.
+
+use super::types::MemoryVersion;
+use std::collections::{BTreeSet, HashMap};
+
+/// One line of a content diff
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DiffLine {
+    /// Line present in both versions
+    Same(String),
+    /// Line only in the newer version
+    Added(String),
+    /// Line only in the older version
+    Removed(String),
+}
+
+/// Changed metadata value
+#[derive(Debug, Clone, PartialEq)]
+pub struct MetadataChange {
+    pub key: String,
+    pub old: serde_json::Value,
+    pub new: serde_json::Value,
+}
+
+/// Differences between two versions of a memory
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct MemoryDiff {
+    /// Line-level content diff, in order
+    pub content: Vec<DiffLine>,
+    /// Metadata keys only in the newer version, with their values
+    pub metadata_added: HashMap<String, serde_json::Value>,
+    /// Metadata keys only in the older version, with their values
+    pub metadata_removed: HashMap<String, serde_json::Value>,
+    /// Metadata keys whose value changed, sorted by key
+    pub metadata_changed: Vec<MetadataChange>,
+    /// Tags only in the newer version, sorted
+    pub tags_added: Vec<String>,
+    /// Tags only in the older version, sorted
+    pub tags_removed: Vec<String>,
+}
+
+impl MemoryDiff {
+    /// Whether the content changed
+    pub fn content_changed(&self) -> bool {
+        self.content
+            .iter()
+            .any(|line| !matches!(line, DiffLine::Same(_)))
+    }
+
+    /// Whether the versions differ at all
+    pub fn is_empty(&self) -> bool {
+        !self.content_changed()
+            && self.metadata_added.is_empty()
+            && self.metadata_removed.is_empty()
+            && self.metadata_changed.is_empty()
+            && self.tags_added.is_empty()
+            && self.tags_removed.is_empty()
+    }
+}
+
+/// Compare version `a` (older) with version `b` (newer)
+pub fn diff_versions(a: &MemoryVersion, b: &MemoryVersion) -> MemoryDiff {
+    let mut diff = MemoryDiff {
+        content: diff_lines(&a.content, &b.content),
+        ..Default::default()
+    };
+
+    for (key, old) in &a.metadata {
+        match b.metadata.get(key) {
+            None => {
+                diff.metadata_removed.insert(key.clone(), old.clone());
+            }
+            Some(new) if new != old => diff.metadata_changed.push(MetadataChange {
+                key: key.clone(),
+                old: old.clone(),
+                new: new.clone(),
+            }),
+            Some(_) => {}
+        }
+    }
+    for (key, new) in &b.metadata {
+        if !a.metadata.contains_key(key) {
+            diff.metadata_added.insert(key.clone(), new.clone());
+        }
+    }
+    diff.metadata_changed.sort_by(|x, y| x.key.cmp(&y.key));
+
+    let old_tags: BTreeSet<&String> = a.tags.iter().collect();
+    let new_tags: BTreeSet<&String> = b.tags.iter().collect();
+    diff.tags_added = new_tags.difference(&old_tags).map(|tag| tag.to_string()).collect();
+    diff.tags_removed = old_tags.difference(&new_tags).map(|tag| tag.to_string()).collect();
+
+    diff
+}
+
+/// Line diff from the longest common subsequence of lines
+fn diff_lines(old: &str, new: &str) -> Vec<DiffLine> {
+    let old: Vec<&str> = old.lines().collect();
+    let new: Vec<&str> = new.lines().collect();
+
+    // common[i][j]: length of the LCS of old[i..] and new[j..]
+    let mut common = vec![vec![0usize; new.len() + 1]; old.len() + 1];
+    for i in (0..old.len()).rev() {
+        for j in (0..new.len()).rev() {
+            common[i][j] = if old[i] == new[j] {
+                common[i + 1][j + 1] + 1
+            } else {
+                common[i + 1][j].max(common[i][j + 1])
+            };
+        }
+    }
+
+    let mut lines = Vec::new();
+    let (mut i, mut j) = (0, 0);
+    while i < old.len() && j < new.len() {
+        if old[i] == new[j] {
+            lines.push(DiffLine::Same(old[i].to_string()));
+            i += 1;
+            j += 1;
+        } else if common[i + 1][j] >= common[i][j + 1] {
+            lines.push(DiffLine::Removed(old[i].to_string()));
+            i += 1;
+        } else {
+            lines.push(DiffLine::Added(new[j].to_string()));
+            j += 1;
+        }
+    }
+    lines.extend(old[i..].iter().map(|line| DiffLine::Removed(line.to_string())));
+    lines.extend(new[j..].iter().map(|line| DiffLine::Added(line.to_string())));
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn version(content: &str, metadata: serde_json::Value, tags: &[&str]) -> MemoryVersion {
+        serde_json::from_value(json!({
+            "version": 1,
+            "content": content,
+            "metadata": metadata,
+            "tags": tags,
+            "timestamp": 0
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_diff_lines() {
+        let a = version("one\ntwo\nthree", json!({}), &[]);
+        let b = version("one\n2\nthree\nfour", json!({}), &[]);
+
+        assert_eq!(
+            diff_versions(&a, &b).content,
+            [
+                DiffLine::Same("one".into()),
+                DiffLine::Removed("two".into()),
+                DiffLine::Added("2".into()),
+                DiffLine::Same("three".into()),
+                DiffLine::Added("four".into()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_diff_metadata_and_tags() {
+        let a = version("same", json!({ "keep": 1, "change": "a", "drop": true }), &["x", "y"]);
+        let b = version("same", json!({ "keep": 1, "change": "b", "add": [1] }), &["y", "z"]);
+
+        let diff = diff_versions(&a, &b);
+        assert!(!diff.content_changed());
+        assert_eq!(diff.metadata_added, HashMap::from([("add".to_string(), json!([1]))]));
+        assert_eq!(diff.metadata_removed, HashMap::from([("drop".to_string(), json!(true))]));
+        assert_eq!(
+            diff.metadata_changed,
+            [MetadataChange {
+                key: "change".into(),
+                old: json!("a"),
+                new: json!("b"),
+            }]
+        );
+        assert_eq!(diff.tags_added, ["z"]);
+        assert_eq!(diff.tags_removed, ["x"]);
+
+        assert!(diff_versions(&a, &a).is_empty());
+    }
+}
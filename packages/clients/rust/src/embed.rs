@@ -0,0 +1,22 @@
+//! UCP Client-Side Embedding
+//!
+//! Pluggable source of embedding vectors computed before a memory is sent,
+//! e.g. by a small local model. When `UcpConfig::embedder` is set,
+//! `store_memory` and `store_memories_batch` fill in
+//! `MemoryRequest::embedding` for requests that lack one, so the server
+//! stores the vector as given instead of embedding the text itself.
+
+use super::types::Result;
+use std::fmt;
+use std::future::Future;
+use std::pin::Pin;
+
+/// Future returned by `Embedder::embed`
+pub type Embedding<'a> = Pin<Box<dyn Future<Output = Result<Vec<f32>>> + Send + 'a>>;
+
+/// Source of embedding vectors for memory content
+pub trait Embedder: fmt::Debug + Send + Sync {
+    /// Embed `text`, which is the content as it will be stored (after
+    /// normalization and redaction)
+    fn embed<'a>(&'a self, text: &'a str) -> Embedding<'a>;
+}
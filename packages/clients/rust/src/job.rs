@@ -0,0 +1,93 @@
+//! UCP Server Jobs
+//!
+//! Handles for long-running operations the server runs in the background,
+//! such as bulk re-embedding. Endpoints that answer `202 Accepted` with a
+//! job ID or a `Location` for the job return a `JobHandle` that can be
+//! polled until the job finishes.
+
+use super::client::UcpClient;
+use super::types::{Result, UcpError};
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+/// Handle to a background job on the server
+#[derive(Debug, Clone)]
+pub struct JobHandle {
+    client: UcpClient,
+    id: String,
+    status_url: String,
+}
+
+/// State of a background job
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "state", rename_all = "snake_case")]
+pub enum JobStatus {
+    /// Queued but not started
+    Pending,
+    /// In progress
+    Running {
+        /// Fraction completed (0.0 to 1.0), if reported
+        #[serde(default)]
+        progress: Option<f32>,
+    },
+    /// Finished successfully
+    Completed {
+        #[serde(default)]
+        result: JobResult,
+    },
+    /// Finished with an error
+    Failed {
+        /// Reason reported by the server
+        error: String,
+    },
+}
+
+/// Outcome of a completed job
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct JobResult {
+    /// Number of items the job processed, if reported
+    pub processed: Option<u64>,
+    /// Any further job-specific output
+    pub details: Option<serde_json::Value>,
+}
+
+impl JobHandle {
+    pub(crate) fn new(client: UcpClient, id: String, status_url: String) -> Self {
+        Self {
+            client,
+            id,
+            status_url,
+        }
+    }
+
+    /// Server-assigned job ID
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    /// Fetch the job's current status
+    pub async fn status(&self) -> Result<JobStatus> {
+        self.client.job_status(&self.status_url).await
+    }
+
+    /// Poll the job's status every `poll_interval` until it finishes
+    ///
+    /// Fails with `UcpError::JobFailed` if the job reports an error.
+    pub async fn await_completion(&self, poll_interval: Duration) -> Result<JobResult> {
+        loop {
+            match self.status().await? {
+                JobStatus::Completed { result } => return Ok(result),
+                JobStatus::Failed { error } => {
+                    return Err(UcpError::JobFailed {
+                        job_id: self.id.clone(),
+                        message: error,
+                    })
+                }
+                JobStatus::Pending | JobStatus::Running { .. } => {
+                    self.client.sleep(poll_interval).await
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,89 @@
+//! UCP Latency Tracking
+//!
+//! Exponential moving averages of observed response latency per HTTP
+//! method, used for adaptive request timeouts.
+
+use super::types::AdaptiveTimeout;
+use reqwest::Method;
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::Duration;
+
+/// Weight of the newest sample when adaptive timeouts are not configured
+const DEFAULT_SMOOTHING: f64 = 0.2;
+
+/// Latency averages shared by all clones of a `UcpClient`
+#[derive(Debug)]
+pub(crate) struct LatencyTracker {
+    smoothing: f64,
+    averages: Mutex<HashMap<Method, f64>>,
+}
+
+impl LatencyTracker {
+    /// Create an empty tracker
+    pub(crate) fn new(settings: Option<&AdaptiveTimeout>) -> Self {
+        let smoothing = settings.map_or(DEFAULT_SMOOTHING, |s| s.smoothing.clamp(0.0, 1.0));
+        Self {
+            smoothing,
+            averages: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Fold a latency sample into the average for `method`
+    pub(crate) fn record(&self, method: &Method, latency: Duration) {
+        let sample = latency.as_secs_f64();
+        let mut averages = self.averages.lock().unwrap_or_else(|e| e.into_inner());
+        averages
+            .entry(method.clone())
+            .and_modify(|average| *average += self.smoothing * (sample - *average))
+            .or_insert(sample);
+    }
+
+    /// Current average latency for `method`, if any request was observed
+    pub(crate) fn average(&self, method: &Method) -> Option<Duration> {
+        let averages = self.averages.lock().unwrap_or_else(|e| e.into_inner());
+        averages.get(method).map(|&secs| Duration::from_secs_f64(secs))
+    }
+}
+
+/// Timeout for a request whose method averages `latency`
+pub(crate) fn adaptive_timeout(settings: &AdaptiveTimeout, latency: Duration) -> Duration {
+    let min = Duration::from_millis(settings.min_timeout_ms);
+    let max = Duration::from_millis(settings.max_timeout_ms).max(min);
+    latency.mul_f64(settings.factor.max(0.0)).clamp(min, max)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_record_moving_average() {
+        let tracker = LatencyTracker::new(Some(&AdaptiveTimeout {
+            smoothing: 0.5,
+            ..Default::default()
+        }));
+        assert_eq!(tracker.average(&Method::GET), None);
+
+        tracker.record(&Method::GET, Duration::from_millis(500));
+        tracker.record(&Method::GET, Duration::from_millis(1_500));
+
+        assert_eq!(tracker.average(&Method::GET), Some(Duration::from_secs(1)));
+        assert_eq!(tracker.average(&Method::POST), None);
+    }
+
+    #[test]
+    fn test_adaptive_timeout_clamped() {
+        let settings = AdaptiveTimeout {
+            factor: 4.0,
+            min_timeout_ms: 500,
+            max_timeout_ms: 10_000,
+            ..Default::default()
+        };
+
+        let timeout = |ms| adaptive_timeout(&settings, Duration::from_millis(ms));
+        assert_eq!(timeout(50), Duration::from_millis(500));
+        assert_eq!(timeout(1_000), Duration::from_millis(4_000));
+        assert_eq!(timeout(5_000), Duration::from_millis(10_000));
+    }
+}
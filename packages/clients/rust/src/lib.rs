@@ -15,19 +15,19 @@
 //!
 //! ## Example
 //!
-//! ```rust
-//! use ucp_client::{UcpClient, UcpConfig, MemoryRequest};
+//! ```rust,no_run
+//! use ucp_client::{UcpClient, UcpConfig, MemoryRequest, DEFAULT_BASE_URL};
 //! 
 //! #[tokio::main]
 //! async fn main() -> Result<(), Box<dyn std::error::Error>> {
-//!     let config = UcpConfig::new("http://localhost:3000".to_string());
+//!     let config = UcpConfig {
+//!         base_url: DEFAULT_BASE_URL.to_string(),
+//!         ..Default::default()
+//!     };
 //!     let client = UcpClient::new(config)?;
 //!     
 //!     // Store memory
-//!     let request = MemoryRequest {
-//!         content: "Hello, world!".to_string(),
-//!         metadata: None,
-//!     };
+//!     let request = MemoryRequest::new("my_project", "my_session", "Hello, world!");
 //!     
 //!     let response = client.store_memory(request).await?;
 //!     println!("Stored memory with ID: {}", response.id);
@@ -36,8 +36,61 @@
 //! }
 //! ```
 
+pub mod auth;
+mod cache;
+mod charset;
+pub mod cipher;
 pub mod client;
+pub mod clock;
+pub mod codec;
+mod compression;
+mod dedup;
+pub mod diagnostics;
+pub mod diff;
+pub mod embed;
+pub mod job;
+#[cfg(feature = "config-file")]
+mod config;
+#[cfg(feature = "coalesce")]
+pub mod coalesce;
+mod latency;
+mod limiter;
+#[cfg(feature = "tracing")]
+mod logging;
+#[cfg(feature = "tracing")]
+mod propagation;
+#[cfg(feature = "normalize")]
+pub mod normalize;
+#[cfg(feature = "redact")]
+pub mod redact;
+mod retry;
 pub mod types;
+pub mod utils;
+mod validate;
+pub mod writer;
 
-pub use client::UcpClient;
-pub use types::{UcpConfig, MemoryRequest, MemoryResponse, VectorQuery, UcpError, Result};
\ No newline at end of file
+pub use auth::TokenProvider;
+pub use cipher::ContentCipher;
+#[cfg(feature = "encrypt")]
+pub use cipher::AesGcmCipher;
+pub use client::{MemoriesPage, PREFETCH_CONCURRENCY, ProjectsPage, SearchResponse, ServerCapabilities, SessionPage, UcpClient};
+pub use clock::{Clock, SystemClock};
+pub use codec::{JsonCodec, WireCodec};
+pub use diagnostics::Diagnostics;
+pub use diff::{diff_versions, DiffLine, MemoryDiff, MetadataChange};
+pub use embed::Embedder;
+pub use job::{JobHandle, JobResult, JobStatus};
+pub use types::{
+    UcpConfig, MemoryRequest, MemoryResponse, MemoryUpdate, VectorQuery, UcpError, Result,
+    StoreSampleHook, RetryPolicy, RetryPredicate, VectorQueryBuilder, Redactor, TagMatch, StreamOptions, ApiVersion, Normalizer,
+    ImportAck, AdaptiveTimeout, DEFAULT_BASE_URL, DEFAULT_PORT, Order, SessionQuery,
+    Metadata, ErrorCode, FilterExpr, DOC_ID_KEY, CHUNK_INDEX_KEY, CONTENT_TYPE_KEY, JSON_CONTENT_TYPE, ActivityEvent, ActivityKind, AggBucket,
+    BatchOptions, BatchResult, ProbeSpec, ProbeResult, RedirectPolicy, MAX_REDIRECTS, MemoryVersion,
+    Priority, RequestOptions, MemoryFilter, AuthHeaderStyle, MemoryBundle, BUNDLE_FORMAT_VERSION, SearchCacheConfig,
+};
+pub use utils::{
+    apply_time_decay, best_match, cosine_similarity, estimate_project_size, estimate_size, format_as_context, group_duplicates, has_tag, hash_content, merge_results, metadata_eq, reassemble_content, refine,
+    score_above, sort_by_chunk_index, sort_by_score_desc, ContextFormat, DedupStrategy, DuplicateGroup, ProjectSizeEstimate, SizeEstimate,
+};
+pub use writer::SessionWriter;
+pub use tokio_util::sync::CancellationToken;
@@ -0,0 +1,145 @@
+//! UCP Concurrency Limiting
+//!
+//! Cap on in-flight requests (`UcpConfig::max_concurrent_requests`) whose
+//! waiters are served by priority: when a slot frees up it goes to the
+//! longest-waiting request of the highest priority waiting.
+
+use super::types::Priority;
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex, MutexGuard};
+use tokio::sync::oneshot;
+
+/// Shared cap on in-flight requests
+#[derive(Debug)]
+pub(crate) struct ConcurrencyLimiter {
+    state: Mutex<State>,
+}
+
+#[derive(Debug)]
+struct State {
+    available: usize,
+    /// Waiting requests, one queue per priority from highest to lowest
+    waiting: [VecDeque<oneshot::Sender<Permit>>; 3],
+}
+
+/// Slot held by an in-flight request, released on drop
+#[derive(Debug)]
+pub(crate) struct Permit {
+    limiter: Option<Arc<ConcurrencyLimiter>>,
+}
+
+impl Drop for Permit {
+    fn drop(&mut self) {
+        if let Some(limiter) = self.limiter.take() {
+            limiter.release();
+        }
+    }
+}
+
+fn queue_index(priority: Priority) -> usize {
+    match priority {
+        Priority::High => 0,
+        Priority::Normal => 1,
+        Priority::Low => 2,
+    }
+}
+
+impl ConcurrencyLimiter {
+    pub(crate) fn new(max: usize) -> Self {
+        Self {
+            state: Mutex::new(State {
+                available: max,
+                waiting: Default::default(),
+            }),
+        }
+    }
+
+    fn lock(&self) -> MutexGuard<'_, State> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Wait for a slot, ahead of any waiting requests of lower priority
+    pub(crate) async fn acquire(self: &Arc<Self>, priority: Priority) -> Permit {
+        let receiver = {
+            let mut state = self.lock();
+            if state.available > 0 {
+                state.available -= 1;
+                return Permit {
+                    limiter: Some(self.clone()),
+                };
+            }
+
+            let (sender, receiver) = oneshot::channel();
+            state.waiting[queue_index(priority)].push_back(sender);
+            receiver
+        };
+
+        receiver
+            .await
+            .expect("waiting senders are only dropped after sending a permit")
+    }
+
+    /// Hand a freed slot to the next waiter, or return it to the pool
+    fn release(self: Arc<Self>) {
+        let mut state = self.lock();
+
+        while let Some(sender) = state.waiting.iter_mut().find_map(VecDeque::pop_front) {
+            let permit = Permit {
+                limiter: Some(self.clone()),
+            };
+            match sender.send(permit) {
+                Ok(()) => return,
+                // The waiter gave up; keep the slot for the next one
+                Err(mut permit) => {
+                    permit.limiter = None;
+                }
+            }
+        }
+
+        state.available += 1;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[tokio::test]
+    async fn test_high_priority_waiters_go_first() {
+        let limiter = Arc::new(ConcurrencyLimiter::new(1));
+        let held = limiter.acquire(Priority::Normal).await;
+
+        let order = Arc::new(Mutex::new(Vec::new()));
+        let mut tasks = Vec::new();
+        for (name, priority) in [("low", Priority::Low), ("normal", Priority::Normal), ("high", Priority::High)] {
+            let (limiter, order) = (limiter.clone(), order.clone());
+            tasks.push(tokio::spawn(async move {
+                let _permit = limiter.acquire(priority).await;
+                order.lock().unwrap().push(name);
+            }));
+            // Let the task start waiting before queueing the next
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+
+        drop(held);
+        for task in tasks {
+            task.await.unwrap();
+        }
+        assert_eq!(*order.lock().unwrap(), ["high", "normal", "low"]);
+    }
+
+    #[tokio::test]
+    async fn test_abandoned_waiter_does_not_leak_slot() {
+        let limiter = Arc::new(ConcurrencyLimiter::new(1));
+        let held = limiter.acquire(Priority::Normal).await;
+
+        let abandoned = tokio::time::timeout(Duration::from_millis(10), limiter.acquire(Priority::High)).await;
+        assert!(abandoned.is_err());
+
+        drop(held);
+        let _again = tokio::time::timeout(Duration::from_millis(100), limiter.acquire(Priority::Low))
+            .await
+            .expect("slot returned to the pool");
+    }
+}
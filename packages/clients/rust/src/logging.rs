@@ -0,0 +1,87 @@
+//! UCP Request Logging
+//!
+//! Debug-level logging of request and response bodies, available with the
+//! `tracing` feature. Nothing is formatted unless `DEBUG` is enabled for
+//! this crate.
+
+use reqwest::header::{HeaderMap, AUTHORIZATION};
+use reqwest::Request;
+
+/// Placeholder logged instead of credential header values
+const REDACTED: &str = "[REDACTED]";
+
+/// Log the method, URL, headers, and body of an outgoing request
+///
+/// Callers check `tracing::enabled!` first, since building the request
+/// for inspection is not free.
+pub(crate) fn log_request(request: &Request, redact_auth: bool) {
+    let body = request
+        .body()
+        .and_then(|body| body.as_bytes())
+        .map(String::from_utf8_lossy)
+        .unwrap_or_default();
+
+    tracing::debug!(
+        method = %request.method(),
+        url = %request.url(),
+        headers = ?loggable_headers(request.headers(), redact_auth),
+        body = %body,
+        "UCP request"
+    );
+}
+
+/// Log a truncated response body, formatting it only if `DEBUG` is enabled
+pub(crate) fn log_response_body(body_snippet: impl FnOnce() -> String) {
+    if !tracing::enabled!(tracing::Level::DEBUG) {
+        return;
+    }
+
+    tracing::debug!(body = %body_snippet(), "UCP response");
+}
+
+/// Header name/value pairs for logging, optionally masking `Authorization`
+/// and other headers marked sensitive (such as a custom API key header)
+fn loggable_headers(headers: &HeaderMap, redact_auth: bool) -> Vec<(String, String)> {
+    headers
+        .iter()
+        .map(|(name, value)| {
+            let value = if redact_auth && (name == AUTHORIZATION || value.is_sensitive()) {
+                REDACTED.to_string()
+            } else {
+                String::from_utf8_lossy(value.as_bytes()).into_owned()
+            };
+            (name.to_string(), value)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use reqwest::header::{HeaderValue, CONTENT_TYPE};
+
+    #[test]
+    fn test_loggable_headers_redacts_authorization() {
+        let mut headers = HeaderMap::new();
+        headers.insert(AUTHORIZATION, HeaderValue::from_static("Bearer secret"));
+        headers.insert(CONTENT_TYPE, HeaderValue::from_static("application/json"));
+
+        let redacted = loggable_headers(&headers, true);
+        assert!(redacted.contains(&("authorization".to_string(), REDACTED.to_string())));
+        assert!(redacted.contains(&("content-type".to_string(), "application/json".to_string())));
+
+        let plain = loggable_headers(&headers, false);
+        assert!(plain.contains(&("authorization".to_string(), "Bearer secret".to_string())));
+    }
+
+    #[test]
+    fn test_loggable_headers_redacts_sensitive_headers() {
+        let mut key = HeaderValue::from_static("secret");
+        key.set_sensitive(true);
+        let mut headers = HeaderMap::new();
+        headers.insert("x-api-key", key);
+
+        let redacted = loggable_headers(&headers, true);
+        assert_eq!(redacted, [("x-api-key".to_string(), REDACTED.to_string())]);
+    }
+}
@@ -0,0 +1,38 @@
+//! UCP Content Normalization
+//!
+//! A standard normalizer that makes equivalent content byte-identical
+//! before it is stored, so near-duplicates embed (and deduplicate) alike:
+//! surrounding whitespace is trimmed, runs of internal whitespace become a
+//! single space, and text is put in Unicode Normalization Form C.
+
+use super::types::Normalizer;
+use std::sync::Arc;
+use unicode_normalization::UnicodeNormalization;
+
+/// Trim, collapse whitespace runs to single spaces, and apply NFC
+pub fn normalize(content: &str) -> String {
+    content
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .nfc()
+        .collect()
+}
+
+/// The default normalizer, suitable for `UcpConfig::content_normalizer`
+pub fn default_normalizer() -> Normalizer {
+    Arc::new(normalize)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_normalize() {
+        assert_eq!(normalize("  two\t words \n\n and   more\n"), "two words and more");
+        // "e" + combining acute accent composes to a single "é"
+        assert_eq!(normalize("cafe\u{301}"), "caf\u{e9}");
+        assert_eq!(normalize(" \n "), "");
+    }
+}
@@ -0,0 +1,82 @@
+//! UCP Trace Context Propagation
+//!
+//! Injection of W3C `traceparent` and `tracestate` headers from the current
+//! `tracing` span, available with the `tracing` feature. Span contexts come
+//! from `tracing-opentelemetry`, so headers are only added when the
+//! application installs its `OpenTelemetryLayer`; otherwise requests are
+//! left unchanged.
+
+use opentelemetry::trace::TraceContextExt;
+use opentelemetry::Context;
+use reqwest::header::{HeaderMap, HeaderValue};
+use reqwest::Request;
+use tracing_opentelemetry::OpenTelemetrySpanExt;
+
+const TRACEPARENT: &str = "traceparent";
+const TRACESTATE: &str = "tracestate";
+
+/// Add trace context headers for the current span to a request
+pub(crate) fn inject_trace_context(request: &mut Request) {
+    let context = tracing::Span::current().context();
+    inject(&context, request.headers_mut());
+}
+
+/// Add trace context headers for `context`, keeping any already set
+fn inject(context: &Context, headers: &mut HeaderMap) {
+    let span = context.span();
+    let span_context = span.span_context();
+    if !span_context.is_valid() {
+        return;
+    }
+
+    let traceparent = format!(
+        "00-{:032x}-{:016x}-{:02x}",
+        span_context.trace_id(),
+        span_context.span_id(),
+        span_context.trace_flags().to_u8()
+    );
+    if let Ok(value) = HeaderValue::from_str(&traceparent) {
+        headers.entry(TRACEPARENT).or_insert(value);
+    }
+
+    let tracestate = span_context.trace_state().header();
+    if !tracestate.is_empty() {
+        if let Ok(value) = HeaderValue::from_str(&tracestate) {
+            headers.entry(TRACESTATE).or_insert(value);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use opentelemetry::trace::{SpanContext, SpanId, TraceFlags, TraceId, TraceState};
+
+    #[test]
+    fn test_inject_w3c_headers() {
+        let span_context = SpanContext::new(
+            TraceId::from_hex("4bf92f3577b34da6a3ce929d0e0e4736").unwrap(),
+            SpanId::from_hex("00f067aa0ba902b7").unwrap(),
+            TraceFlags::SAMPLED,
+            true,
+            TraceState::from_key_value([("vendor", "value")]).unwrap(),
+        );
+        let context = Context::new().with_remote_span_context(span_context);
+
+        let mut headers = HeaderMap::new();
+        inject(&context, &mut headers);
+
+        assert_eq!(
+            headers[TRACEPARENT],
+            "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01"
+        );
+        assert_eq!(headers[TRACESTATE], "vendor=value");
+    }
+
+    #[test]
+    fn test_no_headers_without_span_context() {
+        let mut headers = HeaderMap::new();
+        inject(&Context::new(), &mut headers);
+        assert!(headers.is_empty());
+    }
+}
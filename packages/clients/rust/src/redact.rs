@@ -0,0 +1,69 @@
+//! UCP Content Redaction
+//!
+//! A simple regex-based redactor for stripping common sensitive values
+//! from memory content before it leaves the process.
+//!
+//! Redaction runs client-side on a best-effort basis and is not a
+//! substitute for server-side data policy.
+
+use super::types::Redactor;
+use regex::Regex;
+use std::sync::{Arc, OnceLock};
+
+/// Patterns and their replacements, applied in order
+fn patterns() -> &'static [(Regex, &'static str)] {
+    static PATTERNS: OnceLock<Vec<(Regex, &'static str)>> = OnceLock::new();
+    PATTERNS.get_or_init(|| {
+        [
+            (r"[A-Za-z0-9._%+-]+@[A-Za-z0-9.-]+\.[A-Za-z]{2,}", "[REDACTED_EMAIL]"),
+            (r"\b\d{3}-\d{2}-\d{4}\b", "[REDACTED_SSN]"),
+            (r"\b(?:sk|pk|rk)-[A-Za-z0-9_-]{16,}\b", "[REDACTED_SECRET]"),
+            (r"\bAKIA[0-9A-Z]{16}\b", "[REDACTED_SECRET]"),
+            (r"(?i)\bbearer\s+[A-Za-z0-9._~+/-]+=*", "[REDACTED_TOKEN]"),
+        ]
+        .into_iter()
+        .map(|(pattern, replacement)| {
+            (Regex::new(pattern).expect("valid redaction pattern"), replacement)
+        })
+        .collect()
+    })
+}
+
+/// Replace emails, US social security numbers, and common API key and
+/// bearer token formats with placeholders
+pub fn redact(content: &str) -> String {
+    patterns()
+        .iter()
+        .fold(content.to_string(), |text, (regex, replacement)| {
+            regex.replace_all(&text, *replacement).into_owned()
+        })
+}
+
+/// The default redactor, suitable for `UcpConfig::redactor`
+pub fn default_redactor() -> Redactor {
+    Arc::new(redact)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_redact_common_patterns() {
+        let redacted = redact(
+            "Mail jane.doe@example.com, SSN 123-45-6789, key sk-abcdefghijklmnop1234, \
+             Authorization: Bearer abc.def.ghi",
+        );
+
+        assert_eq!(
+            redacted,
+            "Mail [REDACTED_EMAIL], SSN [REDACTED_SSN], key [REDACTED_SECRET], \
+             Authorization: [REDACTED_TOKEN]"
+        );
+    }
+
+    #[test]
+    fn test_redact_leaves_plain_text() {
+        assert_eq!(redact("Nothing sensitive here"), "Nothing sensitive here");
+    }
+}
@@ -0,0 +1,115 @@
+//! UCP Retry Support
+//!
+//! Backoff computation and a shared retry budget that bounds the overall
+//! retry rate of a client to a ratio of its successful traffic.
+
+use super::types::RetryPolicy;
+use std::sync::Mutex;
+use std::time::Duration;
+
+/// Token bucket shared by all clones of a `UcpClient`
+///
+/// Each retry withdraws one token and each successful request deposits
+/// `RetryPolicy::budget_ratio` tokens, so sustained failures quickly stop
+/// retrying while healthy traffic keeps the budget topped up.
+#[derive(Debug)]
+pub(crate) struct RetryBudget {
+    tokens: Mutex<f64>,
+    max_tokens: f64,
+    ratio: f64,
+}
+
+impl RetryBudget {
+    /// Create a full budget for the given policy
+    pub(crate) fn new(policy: &RetryPolicy) -> Self {
+        let max_tokens = f64::from(policy.budget_max_tokens);
+        Self {
+            tokens: Mutex::new(max_tokens),
+            max_tokens,
+            ratio: f64::from(policy.budget_ratio),
+        }
+    }
+
+    /// Try to withdraw a token for a retry
+    pub(crate) fn try_withdraw(&self) -> bool {
+        let mut tokens = self.tokens.lock().unwrap_or_else(|e| e.into_inner());
+        if *tokens >= 1.0 {
+            *tokens -= 1.0;
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Refill the budget after a successful request
+    pub(crate) fn deposit(&self) {
+        let mut tokens = self.tokens.lock().unwrap_or_else(|e| e.into_inner());
+        *tokens = (*tokens + self.ratio).min(self.max_tokens);
+    }
+}
+
+/// Compute the backoff delay before retry number `attempt` (zero-based)
+pub(crate) fn backoff_delay(policy: &RetryPolicy, attempt: u32) -> Duration {
+    let exp = policy
+        .initial_backoff_ms
+        .saturating_mul(1u64 << attempt.min(32));
+    let capped = exp.min(policy.max_backoff_ms);
+
+    let delay = if policy.jitter && capped > 0 {
+        // Equal jitter: keep half the delay, randomize the other half
+        capped / 2 + rand::random::<u64>() % (capped / 2 + 1)
+    } else {
+        capped
+    };
+
+    Duration::from_millis(delay)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_budget_bounds_retries_to_success_ratio() {
+        let policy = RetryPolicy {
+            budget_max_tokens: 2,
+            budget_ratio: 0.5,
+            ..Default::default()
+        };
+        let budget = RetryBudget::new(&policy);
+
+        assert!(budget.try_withdraw());
+        assert!(budget.try_withdraw());
+        assert!(!budget.try_withdraw());
+
+        // Two successes earn one retry
+        budget.deposit();
+        assert!(!budget.try_withdraw());
+        budget.deposit();
+        assert!(budget.try_withdraw());
+
+        // Deposits never exceed the bucket size
+        for _ in 0..10 {
+            budget.deposit();
+        }
+        assert!(budget.try_withdraw());
+        assert!(budget.try_withdraw());
+        assert!(!budget.try_withdraw());
+    }
+
+    #[test]
+    fn test_backoff_delay_is_exponential_and_capped() {
+        let policy = RetryPolicy {
+            initial_backoff_ms: 100,
+            max_backoff_ms: 1000,
+            jitter: false,
+            ..Default::default()
+        };
+
+        assert_eq!(backoff_delay(&policy, 0), Duration::from_millis(100));
+        assert_eq!(backoff_delay(&policy, 1), Duration::from_millis(200));
+        assert_eq!(backoff_delay(&policy, 3), Duration::from_millis(800));
+        assert_eq!(backoff_delay(&policy, 4), Duration::from_millis(1000));
+        assert_eq!(backoff_delay(&policy, 40), Duration::from_millis(1000));
+    }
+}
@@ -2,29 +2,452 @@
 //!
 //! Common types used by the UCP client and server communication.
 
+use super::auth::TokenProvider;
+use super::cipher::ContentCipher;
+use super::clock::Clock;
+use super::codec::WireCodec;
+use super::embed::Embedder;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use serde::de::DeserializeOwned;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
+use std::fmt;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Hook invoked with a sampled memory request before it is stored
+pub type StoreSampleHook = Arc<dyn Fn(&MemoryRequest) + Send + Sync>;
+
+/// Predicate deciding whether to retry a failed request
+pub type RetryPredicate = Arc<dyn Fn(&UcpError, u32) -> bool + Send + Sync>;
+
+/// Function rewriting memory content before it is sent to the server
+pub type Redactor = Arc<dyn Fn(&str) -> String + Send + Sync>;
+
+/// Function putting memory content into a canonical form before storage
+pub type Normalizer = Arc<dyn Fn(&str) -> String + Send + Sync>;
+
+/// Default port of the UCP server's HTTP API (its `UCP_PORT` default)
+pub const DEFAULT_PORT: u16 = 3000;
+
+/// Default base URL of the UCP server, on `localhost` at `DEFAULT_PORT`
+pub const DEFAULT_BASE_URL: &str = "http://localhost:3000";
+
+/// Metadata key naming the source document a chunk memory belongs to
+pub const DOC_ID_KEY: &str = "doc_id";
+
+/// Metadata key holding a chunk's position within its source document
+pub const CHUNK_INDEX_KEY: &str = "chunk_index";
+
+/// Metadata key holding the media type of structured content
+pub const CONTENT_TYPE_KEY: &str = "content_type";
+
+/// `CONTENT_TYPE_KEY` value marking content that is serialized JSON
+pub const JSON_CONTENT_TYPE: &str = "application/json";
 
 /// Configuration for UCP client
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(default)]
 pub struct UcpConfig {
     /// Base URL of the UCP server
     pub base_url: String,
     /// API key for authentication (optional)
     pub api_key: Option<String>,
+    /// Which header carries `api_key` (defaults to `Authorization: Bearer`)
+    pub auth_header: AuthHeaderStyle,
+    /// Supplies short-lived tokens sent in place of `api_key`, refreshed
+    /// and retried once when a request is rejected with `401` (static
+    /// `api_key` when `None`)
+    #[serde(skip)]
+    pub token_provider: Option<Arc<dyn TokenProvider>>,
+    /// API version used in request paths (`/api/{version}/...`)
+    pub api_version: ApiVersion,
     /// Timeout for requests in seconds
+    ///
+    /// The timeout covers the whole exchange, from connecting until the
+    /// response body has been read, so it must allow for transferring large
+    /// or slowly streamed bodies (including streaming search results).
+    /// Running out while reading the body fails with `UcpError::BodyTimeout`.
     pub timeout_secs: u64,
     /// Maximum retry attempts
     pub max_retries: u32,
+    /// Cap on simultaneous requests awaiting a response across clones of a
+    /// client; further requests wait for a slot (unlimited when `None`)
+    pub max_concurrent_requests: Option<usize>,
+    /// Derive request timeouts from observed latency instead of using
+    /// `timeout_secs` (fixed timeout when `None`)
+    pub adaptive_timeout: Option<AdaptiveTimeout>,
+    /// Backoff and retry budget settings
+    #[serde(default)]
+    pub retry_policy: RetryPolicy,
+    /// Fraction of stored memories passed to `on_store_sampled` (0.0 to 1.0)
+    #[serde(default)]
+    pub sample_rate: f32,
+    /// Hook receiving a random sample of stored memories, e.g. for auditing
+    #[serde(skip)]
+    pub on_store_sampled: Option<StoreSampleHook>,
+    /// Wire format for request and response bodies (defaults to JSON)
+    #[serde(skip)]
+    pub codec: Option<Arc<dyn WireCodec>>,
+    /// Time source for retry backoff, latency tracking, and timestamps of
+    /// pending stores (defaults to `SystemClock`); replace it in tests to
+    /// avoid real sleeps
+    #[serde(skip)]
+    pub clock: Option<Arc<dyn Clock>>,
+    /// Metadata filter merged into every search; keys set on the query
+    /// take precedence
+    pub default_search_filter: Option<HashMap<String, serde_json::Value>>,
+    /// Rewrites content before storage, e.g. to strip emails or secrets
+    ///
+    /// Redaction runs client-side and is not a substitute for server policy.
+    #[serde(skip)]
+    pub redactor: Option<Redactor>,
+    /// Rewrites content into a canonical form before storage, e.g.
+    /// `normalize::default_normalizer()` (`normalize` feature); applied
+    /// before `redactor` (disabled when `None`)
+    #[serde(skip)]
+    pub content_normalizer: Option<Normalizer>,
+    /// Computes embeddings client-side for stored memories that lack one,
+    /// after normalization and redaction (server-side embedding when `None`)
+    #[serde(skip)]
+    pub embedder: Option<Arc<dyn Embedder>>,
+    /// Encrypts content before it is sent and decrypts received content,
+    /// e.g. `cipher::AesGcmCipher` (`encrypt` feature); encrypted memories
+    /// can be fetched by ID but not searched (disabled when `None`)
+    #[serde(skip)]
+    pub content_cipher: Option<Arc<dyn ContentCipher>>,
+    /// Which HTTP redirects to follow (defaults to same-origin only)
+    pub redirect_policy: RedirectPolicy,
+    /// Mask the `Authorization` header, and the `auth_header` carrying the
+    /// API key, in debug request logs (`tracing` feature)
+    #[serde(default = "default_true")]
+    pub redact_auth_in_logs: bool,
+    /// Fail stores whose content is empty or whitespace-only instead of
+    /// sending them
+    pub reject_empty_content: bool,
+    /// Number of recent stores remembered to skip repeats (disabled when
+    /// `None`)
+    ///
+    /// A `store_memory` whose content matches one of the last
+    /// `dedup_window` stores to the same project and session returns that
+    /// store's response without sending a request. This is best-effort,
+    /// within a single client instance and its clones.
+    pub dedup_window: Option<usize>,
+    /// Cache search results client-side, so repeated and prefetched
+    /// queries are answered without a request (disabled when `None`)
+    pub search_cache: Option<SearchCacheConfig>,
+    /// Send PATCH and DELETE requests as POST with an
+    /// `X-HTTP-Method-Override` header naming the real method, for proxies
+    /// that block those verbs; the server must honor the header
+    pub method_override: bool,
+    /// Validate requests client-side (non-empty identifiers and tags,
+    /// search limit and threshold ranges) and fail with a `ConfigError`
+    /// before sending invalid ones; intended for development
+    pub strict: bool,
+}
+
+impl fmt::Debug for UcpConfig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("UcpConfig")
+            .field("base_url", &self.base_url)
+            .field("api_key", &self.api_key.as_ref().map(|_| "<redacted>"))
+            .field("auth_header", &self.auth_header)
+            .field("token_provider", &self.token_provider)
+            .field("api_version", &self.api_version)
+            .field("timeout_secs", &self.timeout_secs)
+            .field("max_retries", &self.max_retries)
+            .field("max_concurrent_requests", &self.max_concurrent_requests)
+            .field("adaptive_timeout", &self.adaptive_timeout)
+            .field("retry_policy", &self.retry_policy)
+            .field("sample_rate", &self.sample_rate)
+            .field("on_store_sampled", &self.on_store_sampled.as_ref().map(|_| "<hook>"))
+            .field("codec", &self.codec)
+            .field("clock", &self.clock)
+            .field("default_search_filter", &self.default_search_filter)
+            .field("redactor", &self.redactor.as_ref().map(|_| "<redactor>"))
+            .field("content_normalizer", &self.content_normalizer.as_ref().map(|_| "<normalizer>"))
+            .field("embedder", &self.embedder)
+            .field("content_cipher", &self.content_cipher)
+            .field("redirect_policy", &self.redirect_policy)
+            .field("redact_auth_in_logs", &self.redact_auth_in_logs)
+            .field("reject_empty_content", &self.reject_empty_content)
+            .field("dedup_window", &self.dedup_window)
+            .field("search_cache", &self.search_cache)
+            .field("method_override", &self.method_override)
+            .field("strict", &self.strict)
+            .finish()
+    }
 }
 
 impl Default for UcpConfig {
     fn default() -> Self {
         Self {
-            base_url: "http://localhost:3001".to_string(),
+            base_url: DEFAULT_BASE_URL.to_string(),
             api_key: None,
+            auth_header: AuthHeaderStyle::default(),
+            token_provider: None,
+            api_version: ApiVersion::default(),
             timeout_secs: 30,
             max_retries: 3,
+            max_concurrent_requests: None,
+            adaptive_timeout: None,
+            retry_policy: RetryPolicy::default(),
+            sample_rate: 0.0,
+            on_store_sampled: None,
+            codec: None,
+            clock: None,
+            default_search_filter: None,
+            redactor: None,
+            content_normalizer: None,
+            embedder: None,
+            content_cipher: None,
+            redirect_policy: RedirectPolicy::default(),
+            redact_auth_in_logs: true,
+            reject_empty_content: false,
+            dedup_window: None,
+            search_cache: None,
+            method_override: false,
+            strict: false,
+        }
+    }
+}
+
+impl UcpConfig {
+    /// Development config for a server at `DEFAULT_BASE_URL`, with a
+    /// short timeout and retries disabled
+    pub fn localhost() -> Self {
+        Self::localhost_port(DEFAULT_PORT)
+    }
+
+    /// Development config for a server on `localhost` at `port`, with a
+    /// short timeout and retries disabled
+    pub fn localhost_port(port: u16) -> Self {
+        Self {
+            base_url: format!("http://localhost:{}", port),
+            timeout_secs: 5,
+            max_retries: 0,
+            ..Default::default()
+        }
+    }
+}
+
+/// Server API version, selecting the `/api/{version}` path prefix
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(from = "String", into = "String")]
+pub enum ApiVersion {
+    /// `/api/v1` (default)
+    #[default]
+    V1,
+    /// `/api/v2`
+    V2,
+    /// Any other path segment, e.g. `"v3-beta"`
+    Custom(String),
+}
+
+impl ApiVersion {
+    /// Path segment for this version
+    pub fn as_str(&self) -> &str {
+        match self {
+            ApiVersion::V1 => "v1",
+            ApiVersion::V2 => "v2",
+            ApiVersion::Custom(version) => version,
+        }
+    }
+}
+
+impl fmt::Display for ApiVersion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl From<String> for ApiVersion {
+    fn from(version: String) -> Self {
+        match version.as_str() {
+            "v1" => ApiVersion::V1,
+            "v2" => ApiVersion::V2,
+            _ => ApiVersion::Custom(version),
+        }
+    }
+}
+
+impl From<ApiVersion> for String {
+    fn from(version: ApiVersion) -> Self {
+        version.as_str().to_string()
+    }
+}
+
+fn default_true() -> bool {
+    true
+}
+
+/// Adaptive request timeout settings
+///
+/// Each request's timeout becomes `factor` times the moving average of
+/// response latency observed for its HTTP method, clamped to
+/// `[min_timeout_ms, max_timeout_ms]`. Until a method has been observed,
+/// `UcpConfig::timeout_secs` applies.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AdaptiveTimeout {
+    /// Multiple of the average latency allowed before timing out
+    pub factor: f64,
+    /// Lower bound on the timeout in milliseconds
+    pub min_timeout_ms: u64,
+    /// Upper bound on the timeout in milliseconds
+    pub max_timeout_ms: u64,
+    /// Weight of the newest sample in the moving average (0.0 to 1.0)
+    pub smoothing: f64,
+}
+
+impl Default for AdaptiveTimeout {
+    fn default() -> Self {
+        Self {
+            factor: 4.0,
+            min_timeout_ms: 1_000,
+            max_timeout_ms: 30_000,
+            smoothing: 0.2,
+        }
+    }
+}
+
+/// Client-side search result cache settings
+///
+/// Results are kept per distinct query for `ttl_ms` and are not
+/// invalidated by writes, so a cached search can be up to `ttl_ms` stale.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SearchCacheConfig {
+    /// Maximum number of cached queries; the oldest is dropped when full
+    pub capacity: usize,
+    /// How long results stay fresh, in milliseconds
+    pub ttl_ms: u64,
+}
+
+impl Default for SearchCacheConfig {
+    fn default() -> Self {
+        Self {
+            capacity: 256,
+            ttl_ms: 30_000,
+        }
+    }
+}
+
+/// Maximum number of redirects followed in one request
+pub const MAX_REDIRECTS: usize = 10;
+
+/// How `UcpConfig::api_key` is attached to requests
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AuthHeaderStyle {
+    /// `Authorization: Bearer <key>` (default)
+    #[default]
+    Bearer,
+    /// The bare key in the named header, e.g. `X-Api-Key: <key>`
+    ApiKeyHeader(String),
+    /// The named header set to `format` with `{key}` replaced by the key,
+    /// e.g. `Authorization: ApiKey {key}`
+    Custom { name: String, format: String },
+}
+
+impl AuthHeaderStyle {
+    /// Header name and value carrying `api_key`
+    pub fn header(&self, api_key: &str) -> (String, String) {
+        match self {
+            AuthHeaderStyle::Bearer => ("Authorization".to_string(), format!("Bearer {}", api_key)),
+            AuthHeaderStyle::ApiKeyHeader(name) => (name.clone(), api_key.to_string()),
+            AuthHeaderStyle::Custom { name, format } => (name.clone(), format.replace("{key}", api_key)),
+        }
+    }
+}
+
+/// Which HTTP redirects the client follows
+///
+/// Whenever a redirect leads to another origin (scheme, host or port), the
+/// `Authorization` header is dropped from the redirected request. Other
+/// headers are not, so with a non-`Authorization` `AuthHeaderStyle`,
+/// `Limited` refuses to leave the original origin, like `SameHostOnly`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RedirectPolicy {
+    /// Never follow redirects; the redirect response is returned as an error
+    None,
+    /// Follow up to this many redirects to any origin
+    Limited(usize),
+    /// Follow up to `MAX_REDIRECTS` redirects within the origin of the
+    /// original request, failing on any redirect elsewhere (default)
+    #[default]
+    SameHostOnly,
+}
+
+/// Backoff and retry throttling settings
+///
+/// The number of attempts per request is capped by `UcpConfig::max_retries`;
+/// in addition, retries draw from a token bucket shared by all clones of a
+/// client, so the overall retry rate is bounded to `budget_ratio` retries per
+/// successful request (similar to gRPC retry throttling).
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RetryPolicy {
+    /// Delay before the first retry in milliseconds
+    pub initial_backoff_ms: u64,
+    /// Upper bound on the delay between retries in milliseconds
+    pub max_backoff_ms: u64,
+    /// Randomize delays to avoid synchronized retries
+    pub jitter: bool,
+    /// Retry tokens earned per successful request
+    pub budget_ratio: f32,
+    /// Capacity of the retry token bucket
+    pub budget_max_tokens: u32,
+    /// Also retry non-idempotent requests such as stores
+    ///
+    /// Off by default: a POST that times out after the server committed it
+    /// would be applied twice. Requests carrying an `Idempotency-Key`
+    /// header are retried regardless.
+    pub retry_non_idempotent: bool,
+    /// Custom decision whether to retry, replacing `UcpError::is_retryable`
+    ///
+    /// Called with the error and the number of attempts made so far;
+    /// `max_retries` and the retry budget still apply.
+    #[serde(skip)]
+    pub retry_if: Option<RetryPredicate>,
+}
+
+impl RetryPolicy {
+    /// Whether a failed request should be retried
+    pub(crate) fn should_retry(&self, error: &UcpError, attempts: u32) -> bool {
+        match self.retry_if {
+            Some(ref retry_if) => retry_if(error, attempts),
+            None => error.is_retryable(),
+        }
+    }
+}
+
+impl fmt::Debug for RetryPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RetryPolicy")
+            .field("initial_backoff_ms", &self.initial_backoff_ms)
+            .field("max_backoff_ms", &self.max_backoff_ms)
+            .field("jitter", &self.jitter)
+            .field("budget_ratio", &self.budget_ratio)
+            .field("budget_max_tokens", &self.budget_max_tokens)
+            .field("retry_non_idempotent", &self.retry_non_idempotent)
+            .field("retry_if", &self.retry_if.as_ref().map(|_| "<predicate>"))
+            .finish()
+    }
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            initial_backoff_ms: 100,
+            max_backoff_ms: 5_000,
+            jitter: true,
+            budget_ratio: 0.1,
+            budget_max_tokens: 10,
+            retry_non_idempotent: false,
+            retry_if: None,
         }
     }
 }
@@ -42,9 +465,146 @@ pub struct MemoryRequest {
     pub metadata: HashMap<String, serde_json::Value>,
     /// Tags for categorization
     pub tags: Vec<String>,
+    /// Named collection within the project to store into (optional)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub collection: Option<String>,
+    /// ID of the memory this one is a child of (optional)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub parent_id: Option<String>,
+    /// Precomputed embedding, stored verbatim instead of embedding the
+    /// content on the server (optional)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub embedding: Option<Vec<f32>>,
+    /// Seconds after which the server expires the memory (optional; kept
+    /// until deleted when unset)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ttl_secs: Option<u64>,
+}
+
+impl MemoryRequest {
+    /// Create a request with no metadata or tags
+    pub fn new(
+        project: impl Into<String>,
+        session: impl Into<String>,
+        content: impl Into<String>,
+    ) -> Self {
+        Self {
+            project: project.into(),
+            session: session.into(),
+            content: content.into(),
+            metadata: HashMap::new(),
+            tags: Vec::new(),
+            collection: None,
+            parent_id: None,
+            embedding: None,
+            ttl_secs: None,
+        }
+    }
+
+    /// Create a request whose content is `value` serialized as JSON
+    ///
+    /// The content travels as a JSON string and `CONTENT_TYPE_KEY` is set
+    /// to `JSON_CONTENT_TYPE`; read it back with `MemoryResponse::content_as`.
+    pub fn from_json<T: Serialize>(
+        project: impl Into<String>,
+        session: impl Into<String>,
+        value: &T,
+    ) -> Result<Self> {
+        let content = serde_json::to_string(value)?;
+        Ok(Self::new(project, session, content).with_metadata(CONTENT_TYPE_KEY, JSON_CONTENT_TYPE))
+    }
+
+    /// Add a tag
+    pub fn with_tag(mut self, tag: impl Into<String>) -> Self {
+        self.tags.push(tag.into());
+        self
+    }
+
+    /// Store into a named collection within the project
+    pub fn with_collection(mut self, collection: impl Into<String>) -> Self {
+        self.collection = Some(collection.into());
+        self
+    }
+
+    /// Link the memory as a child of another memory
+    pub fn with_parent(mut self, parent_id: impl Into<String>) -> Self {
+        self.parent_id = Some(parent_id.into());
+        self
+    }
+
+    /// Store a precomputed embedding with the content
+    pub fn with_embedding(mut self, embedding: Vec<f32>) -> Self {
+        self.embedding = Some(embedding);
+        self
+    }
+
+    /// Have the server expire the memory `ttl_secs` after storing it
+    pub fn with_ttl(mut self, ttl_secs: u64) -> Self {
+        self.ttl_secs = Some(ttl_secs);
+        self
+    }
+
+    /// Set a metadata entry holding raw bytes (see `Metadata::bytes`)
+    pub fn with_metadata_bytes(mut self, key: impl Into<String>, data: &[u8]) -> Self {
+        let (key, value) = Metadata::bytes(key, data);
+        self.metadata.insert(key, value);
+        self
+    }
+
+    /// Set a metadata entry, replacing any existing value for the key
+    pub fn with_metadata(
+        mut self,
+        key: impl Into<String>,
+        value: impl Into<serde_json::Value>,
+    ) -> Self {
+        self.metadata.insert(key.into(), value.into());
+        self
+    }
+}
+
+/// Key of the tagged object holding base64-encoded bytes in metadata
+const METADATA_BYTES_TAG: &str = "__b64";
+
+/// Helpers for metadata values that need an encoding convention
+pub struct Metadata;
+
+impl Metadata {
+    /// Metadata entry holding raw bytes as `{ "__b64": "<base64>" }`, so
+    /// binary values survive JSON round trips
+    ///
+    /// Read them back with `MemoryResponse::metadata_bytes`.
+    pub fn bytes(key: impl Into<String>, data: &[u8]) -> (String, serde_json::Value) {
+        let value = serde_json::json!({ METADATA_BYTES_TAG: BASE64.encode(data) });
+        (key.into(), value)
+    }
+
+    /// Decode a value produced by `Metadata::bytes`
+    fn decode_bytes(value: &serde_json::Value) -> Option<Vec<u8>> {
+        let object = value.as_object().filter(|object| object.len() == 1)?;
+        let encoded = object.get(METADATA_BYTES_TAG)?.as_str()?;
+        BASE64.decode(encoded).ok()
+    }
+}
+
+/// Partial update of an existing memory; unset fields are left unchanged
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct MemoryUpdate {
+    /// Replacement content
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub content: Option<String>,
+    /// Replacement metadata
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub metadata: Option<HashMap<String, serde_json::Value>>,
+    /// Replacement tags
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tags: Option<Vec<String>>,
 }
 
 /// Response from memory operations
+///
+/// Fields the server may omit fall back to their defaults, and fields this
+/// client does not know are kept in `extra`, so newer servers adding
+/// fields do not break deserialization.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct MemoryResponse {
     /// Unique identifier for the memory entry
@@ -52,13 +612,125 @@ pub struct MemoryResponse {
     /// Memory content
     pub content: String,
     /// Similarity score (for search results)
+    #[serde(default)]
     pub score: Option<f64>,
+    /// Project the memory belongs to (set on cross-project search results)
+    #[serde(default)]
+    pub project: Option<String>,
+    /// Session the memory belongs to, when the server returns it
+    #[serde(default)]
+    pub session: Option<String>,
     /// Metadata associated with the memory
+    #[serde(default)]
     pub metadata: HashMap<String, serde_json::Value>,
     /// Tags for categorization
+    #[serde(default)]
     pub tags: Vec<String>,
     /// Timestamp of creation/retrieval
     pub timestamp: u64,
+    /// Version for optimistic concurrency (sent back as `If-Match`)
+    #[serde(default)]
+    pub version: Option<u64>,
+    /// ID of the memory this one is a child of
+    #[serde(default)]
+    pub parent_id: Option<String>,
+    /// Embedding vector, when the server returns it
+    #[serde(default)]
+    pub embedding: Option<Vec<f32>>,
+    /// Unix time at which the server expires the memory, if it has a TTL
+    #[serde(default)]
+    pub expires_at: Option<u64>,
+    /// Fingerprint of the content, as computed by `utils::hash_content`,
+    /// when the server returns it
+    #[serde(default)]
+    pub content_hash: Option<String>,
+    /// Whether the memory is archived, i.e. kept but left out of searches
+    /// unless they set `include_archived`
+    #[serde(default)]
+    pub archived: bool,
+    /// Fields sent by the server that this client does not know, kept so
+    /// they survive a round trip
+    #[serde(flatten)]
+    pub extra: HashMap<String, serde_json::Value>,
+}
+
+impl MemoryResponse {
+    /// Whether this is a placeholder for a store the server accepted
+    /// asynchronously (202 with no body), so no ID has been assigned yet
+    pub fn is_pending(&self) -> bool {
+        self.id.is_empty()
+    }
+
+    /// Deserialize content stored as JSON (see `MemoryRequest::from_json`)
+    ///
+    /// Fails with `UcpError::JsonError` if the content is not JSON of the
+    /// expected shape.
+    pub fn content_as<T: DeserializeOwned>(&self) -> Result<T> {
+        Ok(serde_json::from_str(&self.content)?)
+    }
+
+    /// Whether `CONTENT_TYPE_KEY` marks the content as serialized JSON
+    pub fn is_json(&self) -> bool {
+        self.metadata.get(CONTENT_TYPE_KEY).and_then(|v| v.as_str()) == Some(JSON_CONTENT_TYPE)
+    }
+
+    /// Bytes stored under `key` with `Metadata::bytes`
+    ///
+    /// Returns `None` if the key is missing or does not hold encoded bytes.
+    pub fn metadata_bytes(&self, key: &str) -> Option<Vec<u8>> {
+        self.metadata.get(key).and_then(Metadata::decode_bytes)
+    }
+
+    /// Cosine similarity between the embeddings of two memories
+    ///
+    /// Returns `None` unless both carry embeddings of the same length.
+    pub fn similarity_to(&self, other: &MemoryResponse) -> Option<f32> {
+        let (a, b) = (self.embedding.as_ref()?, other.embedding.as_ref()?);
+        crate::utils::cosine_similarity(a, b).ok()
+    }
+
+    /// Similarity score, treating a missing or NaN score as zero
+    pub fn score_or_zero(&self) -> f64 {
+        match self.score {
+            Some(score) if !score.is_nan() => score,
+            _ => 0.0,
+        }
+    }
+
+    /// Portable copy of this memory, for moving it to another server or
+    /// project with `UcpClient::store_bundle`
+    pub fn to_bundle(&self) -> MemoryBundle {
+        MemoryBundle {
+            format_version: BUNDLE_FORMAT_VERSION,
+            content: self.content.clone(),
+            metadata: self.metadata.clone(),
+            tags: self.tags.clone(),
+            embedding: self.embedding.clone(),
+        }
+    }
+}
+
+/// Current `MemoryBundle` format version
+pub const BUNDLE_FORMAT_VERSION: u32 = 1;
+
+/// Self-contained, serializable form of a single memory
+///
+/// Holds what defines the memory itself, leaving out server-assigned
+/// fields (ID, timestamps, version) and its project and session, which are
+/// chosen on import. `format_version` is bumped on incompatible changes, so
+/// readers can detect bundles they do not understand.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MemoryBundle {
+    /// Bundle format, `BUNDLE_FORMAT_VERSION` when written by this client
+    pub format_version: u32,
+    pub content: String,
+    #[serde(default)]
+    pub metadata: HashMap<String, serde_json::Value>,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    /// Embedding to store verbatim, if the source memory had one
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub embedding: Option<Vec<f32>>,
 }
 
 /// Vector similarity search query
@@ -76,6 +748,585 @@ pub struct VectorQuery {
     pub threshold: f64,
     /// Tags to filter by (optional)
     pub tags: Option<Vec<String>>,
+    /// Whether results must carry any or all of `tags`
+    #[serde(default)]
+    pub tag_match: TagMatch,
+    /// Only match memories with a tag starting with this prefix, e.g.
+    /// `"topic:"` (optional)
+    ///
+    /// Applied in addition to `tags`: when both are set, results must
+    /// satisfy both filters.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tag_prefix: Option<String>,
+    /// Only match memories whose metadata has each key set to the given
+    /// value (optional)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub metadata_filter: Option<HashMap<String, serde_json::Value>>,
+    /// Additional projects to search across in one call (optional)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub projects: Option<Vec<String>>,
+    /// Named collection within the project to search (optional)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub collection: Option<String>,
+    /// Boolean filter over tags and metadata (optional)
+    ///
+    /// Applied in addition to the flat `tags`, `tag_prefix` and
+    /// `metadata_filter` fields.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub filter: Option<FilterExpr>,
+    /// Weighted query vectors for the server to blend, e.g. the query's
+    /// embedding and that of a hypothetical answer (optional)
+    ///
+    /// Serialized as `[vector, weight]` pairs. All vectors must have the
+    /// same dimension.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub embeddings: Option<Vec<(Vec<f32>, f32)>>,
+    /// Ask the server to order results by session and to include each
+    /// result's `session`, as `UcpClient::search_grouped` does
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub group_by_session: bool,
+    /// Also match archived memories (excluded by default)
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub include_archived: bool,
+}
+
+/// Filter expression in the server's filter DSL
+///
+/// Leaves test a single tag or metadata key; `and`, `or` and `!` combine
+/// them. Serialized with the operator as the key, e.g.
+/// `{"and": [{"tag_eq": "rust"}, {"not": {"tag_eq": "draft"}}]}`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FilterExpr {
+    /// Every sub-expression matches
+    And(Vec<FilterExpr>),
+    /// At least one sub-expression matches
+    Or(Vec<FilterExpr>),
+    /// The sub-expression does not match
+    Not(Box<FilterExpr>),
+    /// The memory carries this tag
+    TagEq(String),
+    /// Metadata `key` equals `value`
+    MetadataEq {
+        key: String,
+        value: serde_json::Value,
+    },
+    /// Metadata `key` is a number within the inclusive bounds
+    MetadataRange {
+        key: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        gte: Option<f64>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        lte: Option<f64>,
+    },
+}
+
+impl FilterExpr {
+    /// Match memories carrying `tag`
+    pub fn tag(tag: impl Into<String>) -> Self {
+        FilterExpr::TagEq(tag.into())
+    }
+
+    /// Match memories whose metadata has `key` set to `value`
+    pub fn metadata_eq(key: impl Into<String>, value: impl Into<serde_json::Value>) -> Self {
+        FilterExpr::MetadataEq {
+            key: key.into(),
+            value: value.into(),
+        }
+    }
+
+    /// Match memories whose numeric metadata `key` lies within the
+    /// inclusive bounds (either may be left open)
+    pub fn metadata_range(key: impl Into<String>, gte: Option<f64>, lte: Option<f64>) -> Self {
+        FilterExpr::MetadataRange {
+            key: key.into(),
+            gte,
+            lte,
+        }
+    }
+
+    /// Match only when both this and `other` match
+    ///
+    /// Chained calls extend a single `And` rather than nesting.
+    pub fn and(self, other: FilterExpr) -> Self {
+        match self {
+            FilterExpr::And(mut exprs) => {
+                exprs.push(other);
+                FilterExpr::And(exprs)
+            }
+            expr => FilterExpr::And(vec![expr, other]),
+        }
+    }
+
+    /// Match when either this or `other` matches
+    ///
+    /// Chained calls extend a single `Or` rather than nesting.
+    pub fn or(self, other: FilterExpr) -> Self {
+        match self {
+            FilterExpr::Or(mut exprs) => {
+                exprs.push(other);
+                FilterExpr::Or(exprs)
+            }
+            expr => FilterExpr::Or(vec![expr, other]),
+        }
+    }
+}
+
+impl std::ops::Not for FilterExpr {
+    type Output = FilterExpr;
+
+    fn not(self) -> FilterExpr {
+        FilterExpr::Not(Box::new(self))
+    }
+}
+
+/// How the `tags` filter of a search is applied
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TagMatch {
+    /// Match memories carrying at least one of the tags (default)
+    #[default]
+    Any,
+    /// Match only memories carrying every one of the tags
+    All,
+}
+
+/// Default number of results returned by a search
+pub const DEFAULT_SEARCH_LIMIT: u32 = 10;
+
+/// Default minimum similarity for search results
+pub const DEFAULT_SEARCH_THRESHOLD: f64 = 0.7;
+
+impl VectorQuery {
+    /// Start building a query with default limit and threshold
+    pub fn builder(project: impl Into<String>, query: impl Into<String>) -> VectorQueryBuilder {
+        VectorQueryBuilder {
+            query: VectorQuery {
+                project: project.into(),
+                session: None,
+                query: query.into(),
+                limit: DEFAULT_SEARCH_LIMIT,
+                threshold: DEFAULT_SEARCH_THRESHOLD,
+                tags: None,
+                tag_match: TagMatch::default(),
+                tag_prefix: None,
+                metadata_filter: None,
+                filter: None,
+                projects: None,
+                collection: None,
+                embeddings: None,
+                group_by_session: false,
+                include_archived: false,
+            },
+        }
+    }
+}
+
+/// Builder for `VectorQuery`
+#[derive(Debug, Clone)]
+pub struct VectorQueryBuilder {
+    query: VectorQuery,
+}
+
+impl VectorQueryBuilder {
+    /// Restrict the search to a session
+    pub fn session(mut self, session: impl Into<String>) -> Self {
+        self.query.session = Some(session.into());
+        self
+    }
+
+    /// Restrict the search to a named collection within the project
+    pub fn collection(mut self, collection: impl Into<String>) -> Self {
+        self.query.collection = Some(collection.into());
+        self
+    }
+
+    /// Set the number of results to return
+    pub fn limit(mut self, limit: u32) -> Self {
+        self.query.limit = limit;
+        self
+    }
+
+    /// Set the minimum similarity threshold
+    pub fn threshold(mut self, threshold: f64) -> Self {
+        self.query.threshold = threshold;
+        self
+    }
+
+    /// Filter results by tags
+    pub fn tags<I, S>(mut self, tags: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.query.tags = Some(tags.into_iter().map(Into::into).collect());
+        self
+    }
+
+    /// Set whether results must carry any or all of the tags
+    pub fn tag_match(mut self, tag_match: TagMatch) -> Self {
+        self.query.tag_match = tag_match;
+        self
+    }
+
+    /// Filter results to memories with a tag starting with `prefix`
+    pub fn tag_prefix(mut self, prefix: impl Into<String>) -> Self {
+        self.query.tag_prefix = Some(prefix.into());
+        self
+    }
+
+    /// Only match memories whose metadata has `key` set to `value`
+    pub fn metadata_filter(
+        mut self,
+        key: impl Into<String>,
+        value: impl Into<serde_json::Value>,
+    ) -> Self {
+        self.query
+            .metadata_filter
+            .get_or_insert_with(HashMap::new)
+            .insert(key.into(), value.into());
+        self
+    }
+
+    /// Also match archived memories
+    pub fn include_archived(mut self, include_archived: bool) -> Self {
+        self.query.include_archived = include_archived;
+        self
+    }
+
+    /// Add a query vector with its blending weight
+    pub fn embedding(mut self, vector: Vec<f32>, weight: f32) -> Self {
+        self.query
+            .embeddings
+            .get_or_insert_with(Vec::new)
+            .push((vector, weight));
+        self
+    }
+
+    /// Only match memories satisfying a filter expression
+    pub fn filter(mut self, filter: FilterExpr) -> Self {
+        self.query.filter = Some(filter);
+        self
+    }
+
+    /// Search across additional projects
+    pub fn projects<I, S>(mut self, projects: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.query.projects = Some(projects.into_iter().map(Into::into).collect());
+        self
+    }
+
+    /// Finish building the query
+    pub fn build(self) -> VectorQuery {
+        self.query
+    }
+}
+
+/// Timestamp order of listed memories
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Order {
+    /// Oldest first (default)
+    #[default]
+    Asc,
+    /// Newest first
+    Desc,
+}
+
+/// Ordering and paging of a session fetch
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SessionQuery {
+    /// Timestamp order, ascending by default
+    #[serde(default)]
+    pub order: Order,
+    /// Maximum number of memories per page (server default when `None`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub limit: Option<u32>,
+    /// Cursor from a previous page's `next_cursor`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cursor: Option<String>,
+}
+
+/// Which memories `UcpClient::iter_memories` returns; unset fields match
+/// everything
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct MemoryFilter {
+    /// Only memories from this session
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub session: Option<String>,
+    /// Only memories from this collection
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub collection: Option<String>,
+    /// Only memories carrying all of these tags
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+    /// Only memories whose metadata has all of these values
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub metadata: HashMap<String, serde_json::Value>,
+}
+
+impl MemoryFilter {
+    /// Match every memory in the project
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Only match memories from `session`
+    pub fn with_session(mut self, session: impl Into<String>) -> Self {
+        self.session = Some(session.into());
+        self
+    }
+
+    /// Only match memories from `collection`
+    pub fn with_collection(mut self, collection: impl Into<String>) -> Self {
+        self.collection = Some(collection.into());
+        self
+    }
+
+    /// Only match memories carrying `tag`, in addition to any other tags
+    pub fn with_tag(mut self, tag: impl Into<String>) -> Self {
+        self.tags.push(tag.into());
+        self
+    }
+
+    /// Only match memories whose metadata `key` equals `value`
+    pub fn with_metadata(
+        mut self,
+        key: impl Into<String>,
+        value: impl Into<serde_json::Value>,
+    ) -> Self {
+        self.metadata.insert(key.into(), value.into());
+        self
+    }
+}
+
+/// Server acknowledgement of one row of a streamed import
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ImportAck {
+    /// Line number of the row in the upload, starting at 1
+    pub line: u64,
+    /// ID assigned to the stored memory
+    #[serde(default)]
+    pub id: Option<String>,
+    /// Why the row was rejected
+    #[serde(default)]
+    pub error: Option<String>,
+}
+
+impl ImportAck {
+    /// Whether the row was stored
+    pub fn is_ok(&self) -> bool {
+        self.error.is_none()
+    }
+}
+
+/// Stored version of a memory, from its version history
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MemoryVersion {
+    /// Version number, increasing with each update
+    pub version: u64,
+    /// Content as of this version
+    pub content: String,
+    /// Metadata as of this version
+    #[serde(default)]
+    pub metadata: HashMap<String, serde_json::Value>,
+    /// Tags as of this version
+    #[serde(default)]
+    pub tags: Vec<String>,
+    /// Unix time the version was written
+    pub timestamp: u64,
+}
+
+/// Known memory searched for by `UcpClient::probe_retrieval`
+#[derive(Debug, Clone, PartialEq)]
+pub struct ProbeSpec {
+    /// Session the canary is stored in
+    pub session: String,
+    /// Canary content
+    pub content: String,
+    /// ID of an already stored canary; when `None`, the canary is stored
+    /// before searching
+    pub memory_id: Option<String>,
+    /// Search text (defaults to `content`)
+    pub query: Option<String>,
+    /// Number of results searched for the canary
+    pub limit: u32,
+}
+
+impl ProbeSpec {
+    /// Probe for `content`, storing it in `session` first
+    pub fn new(session: impl Into<String>, content: impl Into<String>) -> Self {
+        Self {
+            session: session.into(),
+            content: content.into(),
+            memory_id: None,
+            query: None,
+            limit: DEFAULT_SEARCH_LIMIT,
+        }
+    }
+
+    /// Probe for a canary already stored under `memory_id`
+    pub fn with_memory_id(mut self, memory_id: impl Into<String>) -> Self {
+        self.memory_id = Some(memory_id.into());
+        self
+    }
+
+    /// Search with `query` instead of the canary's content
+    pub fn with_query(mut self, query: impl Into<String>) -> Self {
+        self.query = Some(query.into());
+        self
+    }
+}
+
+/// Where a probed canary ranked in search results
+#[derive(Debug, Clone, PartialEq)]
+pub struct ProbeResult {
+    /// ID of the canary memory (empty if the server stored it
+    /// asynchronously without assigning one yet)
+    pub memory_id: String,
+    /// 1-based position of the canary in the results, if found
+    pub rank: Option<usize>,
+    /// Similarity score of the canary, if found and scored
+    pub score: Option<f64>,
+}
+
+impl ProbeResult {
+    /// Whether the canary was the top result
+    pub fn is_top(&self) -> bool {
+        self.rank == Some(1)
+    }
+}
+
+/// Outcome of a batch store, item by item
+///
+/// Each entry carries the index of the memory in the submitted batch, so
+/// failed items can be retried on their own.
+#[derive(Debug, Default)]
+pub struct BatchResult {
+    /// Stored memories with their batch indices
+    pub succeeded: Vec<(usize, MemoryResponse)>,
+    /// Rejected memories' batch indices and errors
+    pub failed: Vec<(usize, UcpError)>,
+}
+
+impl BatchResult {
+    /// Whether every memory in the batch was stored
+    pub fn is_complete(&self) -> bool {
+        self.failed.is_empty()
+    }
+}
+
+/// Number of memories sharing one value of a metadata field, from
+/// `UcpClient::aggregate`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AggBucket {
+    /// Metadata value shared by the memories (`null` when the field is
+    /// missing)
+    pub key: serde_json::Value,
+    /// Number of memories with this value
+    pub count: u64,
+}
+
+/// Entry in a project's activity history
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ActivityEvent {
+    /// What happened
+    pub kind: ActivityKind,
+    /// ID of the memory affected
+    pub memory_id: String,
+    /// Session of the memory affected, if known
+    #[serde(default)]
+    pub session: Option<String>,
+    /// Unix time of the event
+    pub timestamp: u64,
+}
+
+/// Kind of change recorded in a project's activity history
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ActivityKind {
+    /// A memory was stored
+    MemoryCreated,
+    /// A memory's content, metadata or tags changed
+    MemoryUpdated,
+    /// A memory was deleted
+    MemoryDeleted,
+    /// A memory expired after its TTL
+    MemoryExpired,
+    /// Any kind this client does not know about
+    #[serde(other)]
+    Other,
+}
+
+/// Relative urgency of a request
+///
+/// Sent to the server as an `X-Priority` header; whether it affects
+/// scheduling there depends on server support. With
+/// `max_concurrent_requests` set, the client also serves waiting
+/// higher-priority requests first.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Priority {
+    /// Interactive requests that should not wait behind background work
+    High,
+    /// Default priority
+    #[default]
+    Normal,
+    /// Background work such as bulk imports
+    Low,
+}
+
+impl Priority {
+    /// Header value for this priority
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Priority::High => "high",
+            Priority::Normal => "normal",
+            Priority::Low => "low",
+        }
+    }
+}
+
+/// Options applied to every request of a client handle; see
+/// `UcpClient::with_options`
+#[derive(Debug, Clone, Default)]
+pub struct RequestOptions {
+    /// Priority hint (no header is sent when `None`)
+    pub priority: Option<Priority>,
+}
+
+/// Options for batch stores
+#[derive(Debug, Clone, Default)]
+pub struct BatchOptions {
+    /// Stop at the first rejected memory and return its error, instead of
+    /// collecting every memory's outcome in a `BatchResult` (default)
+    ///
+    /// Whether memories stored before the failure are rolled back depends
+    /// on the server; without such support they stay stored.
+    pub fail_fast: bool,
+}
+
+/// Options for streaming search
+#[derive(Debug, Clone, Default)]
+pub struct StreamOptions {
+    /// Transparently re-issue the request if the connection drops mid-stream
+    pub reconnect: bool,
+    /// Fail the stream with `UcpError::Timeout` if no bytes arrive for this
+    /// long (no idle limit when `None`)
+    ///
+    /// Each chunk received restarts the window, so a slow but steady stream
+    /// stays alive; this catches half-dead connections that the overall
+    /// request timeout would only notice much later.
+    pub stream_idle_timeout: Option<Duration>,
+    /// End the stream after this many results, closing the connection
+    /// (read to the end when `None`)
+    ///
+    /// Unlike the query's `limit`, which the server applies, this bounds
+    /// how much of the stream the client consumes.
+    pub take: Option<usize>,
 }
 
 /// Error types for UCP operations
@@ -85,26 +1336,457 @@ pub enum UcpError {
     #[error("HTTP request failed: {0}")]
     HttpError(#[from] reqwest::Error),
     
+    /// The server could not be reached
+    #[error("Could not connect to UCP server: {0}")]
+    ConnectionError(reqwest::Error),
+    
+    /// Response headers arrived, but the request timeout elapsed before
+    /// the body finished arriving
+    ///
+    /// Timeouts while connecting or waiting for the response headers are
+    /// reported as `HttpError` instead.
+    #[error("Timed out reading response body: {0}")]
+    BodyTimeout(reqwest::Error),
+    
+    /// A stream received no data within its idle timeout
+    #[error("Stream idle for {0:?}")]
+    Timeout(Duration),
+    
+    /// A stream closed cleanly partway through a record, so its data is
+    /// incomplete rather than malformed; holds the bytes of the partial
+    /// record received
+    #[error("Stream ended mid-record after {0} bytes of a partial record")]
+    TruncatedStream(usize),
+    
+    /// The caller cancelled the request before it completed
+    #[error("Request cancelled")]
+    Cancelled,
+    
     /// JSON serialization/deserialization failed
     #[error("JSON error: {0}")]
     JsonError(#[from] serde_json::Error),
     
+    /// Response body could not be deserialized into the expected type
+    #[error("Failed to deserialize response: {source} (body: {body_snippet:?})")]
+    DeserializationError {
+        source: serde_json::Error,
+        /// Leading portion of the raw response body, truncated for display
+        body_snippet: String,
+    },
+    
     /// UCP server returned an error
     #[error("UCP server error: {message}")]
-    ServerError { message: String },
+    ServerError {
+        /// HTTP status code, when the error came from a response
+        status: Option<u16>,
+        /// Machine-readable error code, when the server sent one
+        code: Option<String>,
+        message: String,
+    },
     
     /// Authentication failed
     #[error("Authentication failed")]
     AuthenticationError,
     
+    /// The server is temporarily unavailable (HTTP 503), e.g. for
+    /// planned maintenance
+    #[error("Service unavailable: {message}")]
+    ServiceUnavailable {
+        /// When to try again, from the `Retry-After` header
+        retry_after: Option<Duration>,
+        /// Reason reported by the server
+        message: String,
+    },
+    
     /// Rate limit exceeded
     #[error("Rate limit exceeded")]
     RateLimitError,
     
+    /// Requested resource does not exist
+    #[error("Not found")]
+    NotFound,
+    
+    /// The memory exists but has no version with this number
+    #[error("Version {version} of memory {memory_id} not found")]
+    VersionNotFound {
+        memory_id: String,
+        version: u64,
+    },
+    
+    /// Memory was modified since the version given in `If-Match`
+    #[error("Version conflict: memory was modified concurrently")]
+    Conflict,
+    
+    /// Project storage quota exceeded
+    #[error("Storage quota exceeded")]
+    QuotaExceeded {
+        /// Project whose quota was hit, if reported
+        project: Option<String>,
+        /// Quota limit in bytes, if reported
+        limit: Option<u64>,
+    },
+    
+    /// A background job on the server finished with an error
+    #[error("Job {job_id} failed: {message}")]
+    JobFailed {
+        job_id: String,
+        message: String,
+    },
+    
+    /// Error of a search that this caller joined instead of sending its own
+    /// (`coalesce` feature); the caller that sent it gets the error unwrapped
+    #[error(transparent)]
+    Coalesced(Arc<UcpError>),
+    
     /// Invalid configuration
     #[error("Invalid configuration: {0}")]
     ConfigError(String),
+    
+    /// Body could not be encoded or decoded by the configured wire codec
+    #[error("Codec error: {0}")]
+    CodecError(String),
+    
+    /// Content could not be encrypted or decrypted by the configured cipher
+    #[error("Cipher error: {0}")]
+    CipherError(String),
+}
+
+impl UcpError {
+    /// Whether the failed request may succeed if retried
+    pub fn is_retryable(&self) -> bool {
+        match self {
+            UcpError::HttpError(e) => e.is_timeout() || e.is_connect() || e.is_request(),
+            UcpError::ConnectionError(_) | UcpError::BodyTimeout(_) | UcpError::Timeout(_) => true,
+            UcpError::ServerError { status: Some(status), .. } => {
+                matches!(status, 500 | 502 | 503 | 504)
+            }
+            UcpError::RateLimitError | UcpError::ServiceUnavailable { .. } => true,
+            UcpError::Coalesced(inner) => inner.is_retryable(),
+            _ => false,
+        }
+    }
+
+    /// Server error code, parsed into the known codes
+    pub fn error_code(&self) -> Option<ErrorCode> {
+        match self {
+            UcpError::ServerError { code: Some(code), .. } => Some(ErrorCode::from(code.as_str())),
+            UcpError::QuotaExceeded { .. } => Some(ErrorCode::QuotaExceeded),
+            UcpError::Coalesced(inner) => inner.error_code(),
+            _ => None,
+        }
+    }
+}
+
+/// Error codes from the server's error catalog
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ErrorCode {
+    /// `QUOTA_EXCEEDED`: the project's storage quota is used up
+    QuotaExceeded,
+    /// `INVALID_PROJECT`: the project does not exist or is not accessible
+    InvalidProject,
+    /// `EMBEDDING_FAILED`: the server could not embed the content
+    EmbeddingFailed,
+    /// `VERSION_NOT_FOUND`: the memory has no such version
+    VersionNotFound,
+    /// Any code this client does not know
+    Unknown(String),
+}
+
+impl ErrorCode {
+    /// Code as sent by the server
+    pub fn as_str(&self) -> &str {
+        match self {
+            ErrorCode::QuotaExceeded => "QUOTA_EXCEEDED",
+            ErrorCode::InvalidProject => "INVALID_PROJECT",
+            ErrorCode::EmbeddingFailed => "EMBEDDING_FAILED",
+            ErrorCode::VersionNotFound => "VERSION_NOT_FOUND",
+            ErrorCode::Unknown(code) => code,
+        }
+    }
+}
+
+impl From<&str> for ErrorCode {
+    /// Parse a code, ignoring case
+    fn from(code: &str) -> Self {
+        [
+            ErrorCode::QuotaExceeded,
+            ErrorCode::InvalidProject,
+            ErrorCode::EmbeddingFailed,
+            ErrorCode::VersionNotFound,
+        ]
+        .into_iter()
+        .find(|known| known.as_str().eq_ignore_ascii_case(code))
+        .unwrap_or_else(|| ErrorCode::Unknown(code.to_string()))
+    }
+}
+
+impl fmt::Display for ErrorCode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
 }
 
 /// Result type for UCP operations
-pub type Result<T> = std::result::Result<T, UcpError>;
\ No newline at end of file
+pub type Result<T> = std::result::Result<T, UcpError>;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_default_base_url_uses_default_port() {
+        assert_eq!(DEFAULT_BASE_URL, format!("http://localhost:{}", DEFAULT_PORT));
+        assert_eq!(UcpConfig::default().base_url, DEFAULT_BASE_URL);
+    }
+
+    #[test]
+    fn test_localhost_config() {
+        let config = UcpConfig::localhost();
+        assert_eq!(config.base_url, UcpConfig::default().base_url);
+        assert_eq!(config.max_retries, 0);
+        assert!(config.timeout_secs < UcpConfig::default().timeout_secs);
+
+        assert_eq!(UcpConfig::localhost_port(8080).base_url, "http://localhost:8080");
+    }
+
+    #[test]
+    fn test_metadata_bytes_round_trip() {
+        let data = [0u8, 159, 146, 150, 255];
+        let request = MemoryRequest::new("p", "s", "c")
+            .with_metadata_bytes("thumbnail", &data)
+            .with_metadata("plain", "text");
+        assert_eq!(
+            request.metadata["thumbnail"],
+            serde_json::json!({ "__b64": "AJ+Slv8=" })
+        );
+
+        let response: MemoryResponse = serde_json::from_value(serde_json::json!({
+            "id": "mem_1",
+            "content": "c",
+            "metadata": request.metadata,
+            "tags": [],
+            "timestamp": 0
+        }))
+        .unwrap();
+
+        assert_eq!(response.metadata_bytes("thumbnail"), Some(data.to_vec()));
+        assert_eq!(response.metadata_bytes("plain"), None);
+        assert_eq!(response.metadata_bytes("missing"), None);
+    }
+
+    #[test]
+    fn test_error_code_parsing() {
+        assert_eq!(ErrorCode::from("quota_exceeded"), ErrorCode::QuotaExceeded);
+        assert_eq!(ErrorCode::from("INVALID_PROJECT"), ErrorCode::InvalidProject);
+        assert_eq!(
+            ErrorCode::from("SOMETHING_NEW"),
+            ErrorCode::Unknown("SOMETHING_NEW".to_string())
+        );
+
+        let err = UcpError::ServerError {
+            status: Some(500),
+            code: Some("EMBEDDING_FAILED".to_string()),
+            message: "embedding model unavailable".to_string(),
+        };
+        assert_eq!(err.error_code(), Some(ErrorCode::EmbeddingFailed));
+        assert_eq!(UcpError::NotFound.error_code(), None);
+    }
+
+    #[test]
+    fn test_vector_query_builder_defaults() {
+        let query = VectorQuery::builder("test_project", "test query").build();
+
+        assert_eq!(query.project, "test_project");
+        assert_eq!(query.query, "test query");
+        assert_eq!(query.limit, DEFAULT_SEARCH_LIMIT);
+        assert_eq!(query.threshold, DEFAULT_SEARCH_THRESHOLD);
+        assert!(query.session.is_none());
+        assert!(query.tags.is_none());
+    }
+
+    #[test]
+    fn test_tag_match_serialization() {
+        let any = VectorQuery::builder("p", "q").tags(["a", "b"]).build();
+        let all = VectorQuery::builder("p", "q")
+            .tags(["a", "b"])
+            .tag_match(TagMatch::All)
+            .build();
+
+        let any = serde_json::to_value(&any).unwrap();
+        let all = serde_json::to_value(&all).unwrap();
+
+        assert_eq!(any["tag_match"], "any");
+        assert_eq!(all["tag_match"], "all");
+        assert_ne!(any, all);
+    }
+
+    #[test]
+    fn test_tag_prefix_serialization() {
+        let query = serde_json::to_value(VectorQuery::builder("p", "q").build()).unwrap();
+        assert!(query.get("tag_prefix").is_none());
+
+        let query = VectorQuery::builder("p", "q")
+            .tags(["lang:en"])
+            .tag_prefix("topic:")
+            .build();
+        let query = serde_json::to_value(&query).unwrap();
+        assert_eq!(query["tag_prefix"], "topic:");
+        assert_eq!(query["tags"], serde_json::json!(["lang:en"]));
+    }
+
+    #[test]
+    fn test_ttl_serialization() {
+        let request = serde_json::to_value(MemoryRequest::new("p", "s", "c")).unwrap();
+        assert!(request.get("ttl_secs").is_none());
+
+        let request = serde_json::to_value(MemoryRequest::new("p", "s", "c").with_ttl(60)).unwrap();
+        assert_eq!(request["ttl_secs"], 60);
+    }
+
+    #[test]
+    fn test_memory_response_keeps_unknown_fields() {
+        let body = serde_json::json!({
+            "id": "mem_1",
+            "content": "c",
+            "timestamp": 1,
+            "importance": 0.8,
+            "source": { "kind": "chat" }
+        });
+
+        let memory: MemoryResponse = serde_json::from_value(body).unwrap();
+        assert!(memory.metadata.is_empty() && memory.tags.is_empty());
+        assert_eq!(memory.extra["importance"], 0.8);
+        assert_eq!(memory.extra["source"]["kind"], "chat");
+
+        let round_trip = serde_json::to_value(&memory).unwrap();
+        assert_eq!(round_trip["importance"], 0.8);
+        assert!(!memory.extra.contains_key("id"));
+    }
+
+    #[test]
+    fn test_json_content_round_trip() {
+        #[derive(Debug, PartialEq, Serialize, Deserialize)]
+        struct Task {
+            title: String,
+            done: bool,
+        }
+
+        let task = Task { title: "Ship it".to_string(), done: false };
+        let request = MemoryRequest::from_json("p", "s", &task).unwrap();
+        assert_eq!(request.metadata[CONTENT_TYPE_KEY], JSON_CONTENT_TYPE);
+
+        let memory: MemoryResponse = serde_json::from_value(serde_json::json!({
+            "id": "mem_1",
+            "content": request.content,
+            "metadata": request.metadata,
+            "timestamp": 1
+        }))
+        .unwrap();
+        assert!(memory.is_json());
+        assert_eq!(memory.content_as::<Task>().unwrap(), task);
+        assert!(matches!(memory.content_as::<Vec<u32>>(), Err(UcpError::JsonError(_))));
+    }
+
+    #[test]
+    fn test_memory_bundle_round_trip() {
+        let memory: MemoryResponse = serde_json::from_value(serde_json::json!({
+            "id": "mem_1",
+            "content": "Portable note",
+            "metadata": { "source": "chat" },
+            "tags": ["shared"],
+            "timestamp": 1,
+            "embedding": [0.5, 0.25]
+        }))
+        .unwrap();
+
+        let bundle = memory.to_bundle();
+        assert_eq!(bundle.format_version, BUNDLE_FORMAT_VERSION);
+        let json = serde_json::to_string(&bundle).unwrap();
+        assert!(!json.contains("mem_1"));
+        assert_eq!(serde_json::from_str::<MemoryBundle>(&json).unwrap(), bundle);
+    }
+
+    #[test]
+    fn test_filter_expr_serialization() {
+        let filter = FilterExpr::tag("rust")
+            .and(FilterExpr::metadata_eq("lang", "en"))
+            .and(!FilterExpr::tag("draft"))
+            .and(FilterExpr::metadata_range("priority", Some(2.0), None).or(FilterExpr::tag("pinned")));
+        let query = VectorQuery::builder("p", "q").filter(filter.clone()).build();
+
+        let value = serde_json::to_value(&query).unwrap();
+        assert_eq!(
+            value["filter"],
+            serde_json::json!({
+                "and": [
+                    { "tag_eq": "rust" },
+                    { "metadata_eq": { "key": "lang", "value": "en" } },
+                    { "not": { "tag_eq": "draft" } },
+                    { "or": [
+                        { "metadata_range": { "key": "priority", "gte": 2.0 } },
+                        { "tag_eq": "pinned" }
+                    ] }
+                ]
+            })
+        );
+
+        let round_trip: FilterExpr = serde_json::from_value(value["filter"].clone()).unwrap();
+        assert_eq!(round_trip, filter);
+        assert!(serde_json::to_value(VectorQuery::builder("p", "q").build())
+            .unwrap()
+            .get("filter")
+            .is_none());
+    }
+
+    #[test]
+    fn test_vector_query_builder_setters() {
+        let query = VectorQuery::builder("test_project", "test query")
+            .session("test_session")
+            .limit(5)
+            .threshold(0.9)
+            .tags(["a", "b"])
+            .build();
+
+        assert_eq!(query.session.as_deref(), Some("test_session"));
+        assert_eq!(query.limit, 5);
+        assert_eq!(query.threshold, 0.9);
+        assert_eq!(query.tags, Some(vec!["a".to_string(), "b".to_string()]));
+    }
+
+    #[test]
+    fn test_memory_request_new_and_setters() {
+        let request = MemoryRequest::new("test_project", "test_session", "Hello")
+            .with_tag("greeting")
+            .with_tag("test")
+            .with_metadata("source", "unit")
+            .with_metadata("attempt", 1);
+
+        assert_eq!(request.project, "test_project");
+        assert_eq!(request.session, "test_session");
+        assert_eq!(request.content, "Hello");
+        assert_eq!(request.tags, ["greeting", "test"]);
+        assert_eq!(request.metadata["source"], "unit");
+        assert_eq!(request.metadata["attempt"], 1);
+
+        let bare = MemoryRequest::new("p", "s", "c");
+        assert!(bare.metadata.is_empty());
+        assert!(bare.tags.is_empty());
+    }
+
+    #[test]
+    fn test_collection_serialized_only_when_set() {
+        let request = serde_json::to_value(MemoryRequest::new("p", "s", "c")).unwrap();
+        assert!(request.get("collection").is_none());
+
+        let request =
+            serde_json::to_value(MemoryRequest::new("p", "s", "c").with_collection("docs")).unwrap();
+        assert_eq!(request["collection"], "docs");
+
+        let query = serde_json::to_value(VectorQuery::builder("p", "q").build()).unwrap();
+        assert!(query.get("collection").is_none());
+
+        let query =
+            serde_json::to_value(VectorQuery::builder("p", "q").collection("chat").build()).unwrap();
+        assert_eq!(query["collection"], "chat");
+    }
+}
@@ -0,0 +1,737 @@
+//! UCP Client Utilities
+//!
+//! Pure client-side helpers that operate on UCP types without touching
+//! the network.
+
+use super::types::{MemoryRequest, MemoryResponse, Result, UcpError, CHUNK_INDEX_KEY};
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::time::Duration;
+
+/// Average number of characters per token used for rough token estimates
+const CHARS_PER_TOKEN: usize = 4;
+
+/// Estimated serialized size of a memory request
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SizeEstimate {
+    /// Size of the content in bytes
+    pub content_bytes: u64,
+    /// Size of the serialized metadata in bytes
+    pub metadata_bytes: u64,
+    /// Size of the full serialized JSON payload in bytes
+    pub total_bytes: u64,
+    /// Rough token count of the content (about 4 characters per token)
+    pub estimated_tokens: Option<u64>,
+}
+
+/// Estimate the size of a memory request before storing it
+///
+/// Sizes are measured against the JSON encoding; binary codecs will
+/// usually produce smaller payloads.
+pub fn estimate_size(request: &MemoryRequest) -> SizeEstimate {
+    let content_bytes = request.content.len() as u64;
+    let metadata_bytes = serde_json::to_vec(&request.metadata)
+        .map(|bytes| bytes.len() as u64)
+        .unwrap_or(0);
+    let total_bytes = serde_json::to_vec(request)
+        .map(|bytes| bytes.len() as u64)
+        .unwrap_or(0);
+
+    let chars = request.content.chars().count();
+    let estimated_tokens = (chars > 0).then(|| chars.div_ceil(CHARS_PER_TOKEN) as u64);
+
+    SizeEstimate {
+        content_bytes,
+        metadata_bytes,
+        total_bytes,
+        estimated_tokens,
+    }
+}
+
+/// Estimated footprint of a set of memories, overall and per tag
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ProjectSizeEstimate {
+    /// Number of memories estimated
+    pub memories: u64,
+    /// Sum of the estimates of every memory
+    pub total: SizeEstimate,
+    /// Sum of the estimates of the memories carrying each tag
+    ///
+    /// A memory with several tags counts toward each of them, so these can
+    /// add up to more than `total`; untagged memories appear only there.
+    pub by_tag: HashMap<String, SizeEstimate>,
+}
+
+/// Estimate the storage footprint of memories before importing them
+///
+/// Sums `estimate_size` over `memories`, for capacity planning without
+/// contacting the server. Compare with `ProjectStats::total_size_bytes`
+/// for a project already stored.
+pub fn estimate_project_size(memories: &[MemoryRequest]) -> ProjectSizeEstimate {
+    let mut estimate = ProjectSizeEstimate::default();
+
+    for request in memories {
+        let size = estimate_size(request);
+        estimate.memories += 1;
+        add_size(&mut estimate.total, &size);
+
+        let mut seen = Vec::with_capacity(request.tags.len());
+        for tag in &request.tags {
+            // Count a memory once per tag even if the tag repeats
+            if !seen.contains(&tag) {
+                seen.push(tag);
+                add_size(estimate.by_tag.entry(tag.clone()).or_default(), &size);
+            }
+        }
+    }
+
+    estimate
+}
+
+fn add_size(sum: &mut SizeEstimate, size: &SizeEstimate) {
+    sum.content_bytes += size.content_bytes;
+    sum.metadata_bytes += size.metadata_bytes;
+    sum.total_bytes += size.total_bytes;
+    sum.estimated_tokens = match (sum.estimated_tokens, size.estimated_tokens) {
+        (Some(a), Some(b)) => Some(a + b),
+        (a, b) => a.or(b),
+    };
+}
+
+/// Stable fingerprint of memory content, matching the server's
+/// `MemoryResponse::content_hash`
+///
+/// The algorithm is part of the API contract: SHA-256 over the UTF-8 bytes
+/// of the content exactly as stored (after any client-side normalization
+/// or redaction), written as 64 lowercase hex digits. Equal content hashes
+/// equally across sessions and projects.
+pub fn hash_content(content: &str) -> String {
+    use sha2::{Digest, Sha256};
+    use std::fmt::Write;
+
+    let digest = Sha256::digest(content.as_bytes());
+    let mut hex = String::with_capacity(digest.len() * 2);
+    for byte in digest {
+        let _ = write!(hex, "{:02x}", byte);
+    }
+    hex
+}
+
+/// Score used for ranking; missing or NaN scores sort below any real score
+fn ranking_score(memory: &MemoryResponse) -> f64 {
+    match memory.score {
+        Some(score) if !score.is_nan() => score,
+        _ => f64::NEG_INFINITY,
+    }
+}
+
+/// Compare two results by score, highest first
+fn by_score_desc(a: &MemoryResponse, b: &MemoryResponse) -> Ordering {
+    ranking_score(b).total_cmp(&ranking_score(a))
+}
+
+/// Find the highest-scoring result
+///
+/// Results without a score (or with a NaN score) only win when no result
+/// has a real score; ties keep the earliest result.
+pub fn best_match(results: &[MemoryResponse]) -> Option<&MemoryResponse> {
+    results.iter().reduce(|best, candidate| {
+        if by_score_desc(candidate, best) == Ordering::Less {
+            candidate
+        } else {
+            best
+        }
+    })
+}
+
+/// Sort results by score, highest first, keeping unscored results last
+///
+/// The sort is stable, so results with equal scores keep their order.
+pub fn sort_by_score_desc(results: &mut [MemoryResponse]) {
+    results.sort_by(by_score_desc);
+}
+
+/// Weight scores by recency and re-sort, highest first
+///
+/// Each score is multiplied by `0.5^(age / half_life)`, where age is the
+/// time from the result's `timestamp` to `now` (both Unix seconds), so a
+/// result loses half its score per `half_life` of age. Timestamps after
+/// `now` count as age zero. Unscored results stay unscored and sort last.
+pub fn apply_time_decay(results: &mut [MemoryResponse], half_life: Duration, now: u64) {
+    let half_life = half_life.as_secs_f64();
+
+    for result in results.iter_mut() {
+        let age = now.saturating_sub(result.timestamp) as f64;
+        let decay = if age == 0.0 {
+            1.0
+        } else if half_life == 0.0 {
+            0.0
+        } else {
+            0.5f64.powf(age / half_life)
+        };
+        if let Some(ref mut score) = result.score {
+            *score *= decay;
+        }
+    }
+
+    sort_by_score_desc(results);
+}
+
+/// Sort document chunks by their `chunk_index` metadata
+///
+/// Chunks without a numeric index go last; the sort is stable.
+pub fn sort_by_chunk_index(chunks: &mut [MemoryResponse]) {
+    chunks.sort_by_key(|chunk| {
+        chunk
+            .metadata
+            .get(CHUNK_INDEX_KEY)
+            .and_then(|index| index.as_u64())
+            .unwrap_or(u64::MAX)
+    });
+}
+
+/// Join the content of document chunks, in the order given, back into the
+/// source document's text
+pub fn reassemble_content(chunks: &[MemoryResponse]) -> String {
+    chunks.iter().map(|chunk| chunk.content.as_str()).collect()
+}
+
+/// How `merge_results` identifies duplicate results
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum DedupStrategy {
+    /// Results with the same memory ID are duplicates
+    #[default]
+    Id,
+    /// Results with identical content (ignoring surrounding whitespace) are
+    /// duplicates, even across different memory IDs
+    ContentHash,
+}
+
+/// Merge result sets from several searches into one ranked list
+///
+/// Duplicates keep their highest-scoring copy, and the merged list is
+/// sorted by score, highest first.
+pub fn merge_results(
+    results: Vec<Vec<MemoryResponse>>,
+    dedup_by: DedupStrategy,
+) -> Vec<MemoryResponse> {
+    let mut merged: Vec<MemoryResponse> = Vec::new();
+    let mut positions: HashMap<String, usize> = HashMap::new();
+
+    for memory in results.into_iter().flatten() {
+        let key = match dedup_by {
+            DedupStrategy::Id => memory.id.clone(),
+            DedupStrategy::ContentHash => memory.content.trim().to_string(),
+        };
+
+        match positions.get(&key) {
+            Some(&pos) => {
+                if by_score_desc(&memory, &merged[pos]) == Ordering::Less {
+                    merged[pos] = memory;
+                }
+            }
+            None => {
+                positions.insert(key, merged.len());
+                merged.push(memory);
+            }
+        }
+    }
+
+    sort_by_score_desc(&mut merged);
+    merged
+}
+
+/// Options for `format_as_context`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ContextFormat {
+    /// Text placed between results (default: a blank line)
+    pub separator: String,
+    /// Prefix each result with a citation number, `[1]`, `[2]`, ...
+    pub citations: bool,
+    /// Append each result's score, e.g. `(score: 0.92)`
+    pub show_scores: bool,
+    /// Maximum length of the block in characters (unlimited when `None`)
+    ///
+    /// Lowest-scoring results are dropped until the block fits; if the
+    /// best result alone is too long, it is cut off at the budget.
+    pub max_chars: Option<usize>,
+}
+
+impl Default for ContextFormat {
+    fn default() -> Self {
+        Self {
+            separator: "\n\n".to_string(),
+            citations: false,
+            show_scores: false,
+            max_chars: None,
+        }
+    }
+}
+
+/// Format search results as a text block for an LLM prompt
+///
+/// Results keep their order; citation numbers follow the results that fit
+/// within `max_chars`.
+pub fn format_as_context(results: &[MemoryResponse], opts: ContextFormat) -> String {
+    let mut kept: Vec<&MemoryResponse> = results.iter().collect();
+
+    loop {
+        let block = render_context(&kept, &opts);
+        let Some(max_chars) = opts.max_chars else {
+            return block;
+        };
+        if block.chars().count() <= max_chars {
+            return block;
+        }
+        if kept.len() <= 1 {
+            return block.chars().take(max_chars).collect();
+        }
+
+        // Drop the lowest-scoring result; among ties, the last one
+        let lowest = kept
+            .iter()
+            .enumerate()
+            .min_by(|(i, a), (j, b)| by_score_desc(b, a).then(i.cmp(j)))
+            .map(|(index, _)| index)
+            .unwrap_or(0);
+        kept.remove(lowest);
+    }
+}
+
+fn render_context(results: &[&MemoryResponse], opts: &ContextFormat) -> String {
+    results
+        .iter()
+        .enumerate()
+        .map(|(index, memory)| {
+            let mut item = String::new();
+            if opts.citations {
+                item.push_str(&format!("[{}] ", index + 1));
+            }
+            item.push_str(&memory.content);
+            if let (true, Some(score)) = (opts.show_scores, memory.score) {
+                item.push_str(&format!(" (score: {:.2})", score));
+            }
+            item
+        })
+        .collect::<Vec<_>>()
+        .join(&opts.separator)
+}
+
+/// Cosine similarity of two vectors, from -1.0 to 1.0
+///
+/// Fails with `UcpError::ConfigError` if the lengths differ. A zero vector
+/// has similarity 0.0 to everything.
+pub fn cosine_similarity(a: &[f32], b: &[f32]) -> Result<f32> {
+    if a.len() != b.len() {
+        return Err(UcpError::ConfigError(format!(
+            "cannot compare vectors of length {} and {}",
+            a.len(),
+            b.len()
+        )));
+    }
+
+    let (mut dot, mut norm_a, mut norm_b) = (0.0f32, 0.0f32, 0.0f32);
+    for (x, y) in a.iter().zip(b) {
+        dot += x * y;
+        norm_a += x * x;
+        norm_b += y * y;
+    }
+
+    let norms = norm_a.sqrt() * norm_b.sqrt();
+    if norms == 0.0 {
+        return Ok(0.0);
+    }
+    // Rounding can push parallel vectors slightly past the bounds
+    Ok((dot / norms).clamp(-1.0, 1.0))
+}
+
+/// Memories whose embeddings are near-identical
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DuplicateGroup {
+    /// IDs of the memories in the group, oldest first, so the first is a
+    /// natural one to keep
+    pub memory_ids: Vec<String>,
+}
+
+/// Group memories whose embeddings have a cosine similarity of at least
+/// `threshold`
+///
+/// Grouping is transitive: if A matches B and B matches C, all three form
+/// one group even when A and C are further apart. Memories without an
+/// embedding, or with one of a different length, are never grouped. Every
+/// pair is compared, so the cost grows with the square of the number of
+/// memories. Only groups of two or more are returned.
+pub fn group_duplicates(memories: &[MemoryResponse], threshold: f64) -> Vec<DuplicateGroup> {
+    // Union-find over memory indices
+    let mut parent: Vec<usize> = (0..memories.len()).collect();
+    fn root(parent: &mut [usize], mut i: usize) -> usize {
+        while parent[i] != i {
+            parent[i] = parent[parent[i]];
+            i = parent[i];
+        }
+        i
+    }
+
+    for (i, a) in memories.iter().enumerate() {
+        let Some(ref a) = a.embedding else { continue };
+        for (j, b) in memories.iter().enumerate().skip(i + 1) {
+            let Some(ref b) = b.embedding else { continue };
+            if cosine_similarity(a, b).is_ok_and(|sim| f64::from(sim) >= threshold) {
+                let (ri, rj) = (root(&mut parent, i), root(&mut parent, j));
+                parent[rj] = ri;
+            }
+        }
+    }
+
+    let mut groups: HashMap<usize, Vec<&MemoryResponse>> = HashMap::new();
+    for (i, memory) in memories.iter().enumerate() {
+        groups.entry(root(&mut parent, i)).or_default().push(memory);
+    }
+
+    let mut groups: Vec<Vec<&MemoryResponse>> =
+        groups.into_values().filter(|group| group.len() > 1).collect();
+    for group in &mut groups {
+        group.sort_by(|a, b| a.timestamp.cmp(&b.timestamp).then_with(|| a.id.cmp(&b.id)));
+    }
+    groups.sort_by(|a, b| a[0].timestamp.cmp(&b[0].timestamp).then_with(|| a[0].id.cmp(&b[0].id)));
+
+    groups
+        .into_iter()
+        .map(|group| DuplicateGroup {
+            memory_ids: group.into_iter().map(|memory| memory.id.clone()).collect(),
+        })
+        .collect()
+}
+
+/// Narrow an already-fetched result set to results matching `predicate`
+///
+/// Results keep their order. Predicates such as `has_tag`, `metadata_eq`,
+/// and `score_above` can be combined in a closure.
+pub fn refine(
+    results: &[MemoryResponse],
+    predicate: impl Fn(&MemoryResponse) -> bool,
+) -> Vec<MemoryResponse> {
+    results
+        .iter()
+        .filter(|memory| predicate(memory))
+        .cloned()
+        .collect()
+}
+
+/// Predicate matching results carrying `tag`
+pub fn has_tag(tag: impl Into<String>) -> impl Fn(&MemoryResponse) -> bool {
+    let tag = tag.into();
+    move |memory| memory.tags.contains(&tag)
+}
+
+/// Predicate matching results whose metadata has `key` set to `value`
+pub fn metadata_eq(
+    key: impl Into<String>,
+    value: impl Into<serde_json::Value>,
+) -> impl Fn(&MemoryResponse) -> bool {
+    let key = key.into();
+    let value = value.into();
+    move |memory| memory.metadata.get(&key) == Some(&value)
+}
+
+/// Predicate matching results scoring strictly above `min`
+///
+/// Results without a score (or with a NaN score) never match.
+pub fn score_above(min: f64) -> impl Fn(&MemoryResponse) -> bool {
+    move |memory| memory.score.is_some_and(|score| score > min)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request(content: &str) -> MemoryRequest {
+        MemoryRequest {
+            project: "test_project".to_string(),
+            session: "test_session".to_string(),
+            content: content.to_string(),
+            metadata: HashMap::new(),
+            tags: vec![],
+            collection: None,
+            parent_id: None,
+            embedding: None,
+            ttl_secs: None,
+        }
+    }
+
+    fn response(id: &str, score: Option<f64>) -> MemoryResponse {
+        MemoryResponse {
+            id: id.to_string(),
+            content: String::new(),
+            score,
+            project: None,
+            session: None,
+            metadata: HashMap::new(),
+            tags: vec![],
+            timestamp: 0,
+            version: None,
+            parent_id: None,
+            embedding: None,
+            expires_at: None,
+            content_hash: None,
+            archived: false,
+            extra: HashMap::new(),
+        }
+    }
+
+    #[test]
+    fn test_format_as_context() {
+        let result = |content: &str, score: f64| {
+            let mut result = response(content, Some(score));
+            result.content = content.to_string();
+            result
+        };
+        let results = vec![result("alpha", 0.9), result("beta", 0.5), result("gamma", 0.7)];
+
+        assert_eq!(
+            format_as_context(&results, ContextFormat::default()),
+            "alpha\n\nbeta\n\ngamma"
+        );
+
+        let opts = ContextFormat {
+            separator: "\n".to_string(),
+            citations: true,
+            show_scores: true,
+            max_chars: None,
+        };
+        assert_eq!(
+            format_as_context(&results, opts),
+            "[1] alpha (score: 0.90)\n[2] beta (score: 0.50)\n[3] gamma (score: 0.70)"
+        );
+
+        // The lowest-scoring result is dropped first, then the block is cut
+        let opts = ContextFormat {
+            citations: true,
+            max_chars: Some(20),
+            ..Default::default()
+        };
+        assert_eq!(format_as_context(&results, opts), "[1] alpha\n\n[2] gamma");
+        let opts = ContextFormat {
+            max_chars: Some(3),
+            ..Default::default()
+        };
+        assert_eq!(format_as_context(&results, opts), "alp");
+        assert_eq!(format_as_context(&[], ContextFormat::default()), "");
+    }
+
+    #[test]
+    fn test_apply_time_decay() {
+        let result = |id: &str, score: Option<f64>, timestamp: u64| {
+            let mut result = response(id, score);
+            result.timestamp = timestamp;
+            result
+        };
+        let now = 1_000_000;
+        let day = 86_400;
+        let mut results = vec![
+            result("old", Some(0.9), now - 2 * day),
+            result("unscored", None, now),
+            result("fresh", Some(0.6), now),
+            result("future", Some(0.5), now + day),
+        ];
+
+        apply_time_decay(&mut results, Duration::from_secs(day), now);
+
+        let ids: Vec<&str> = results.iter().map(|r| r.id.as_str()).collect();
+        assert_eq!(ids, ["fresh", "future", "old", "unscored"]);
+        assert_eq!(results[0].score, Some(0.6));
+        assert_eq!(results[1].score, Some(0.5));
+        assert!((results[2].score.unwrap() - 0.225).abs() < 1e-12);
+        assert_eq!(results[3].score, None);
+    }
+
+    #[test]
+    fn test_chunks_reassemble_in_index_order() {
+        let chunk = |content: &str, index: Option<u64>| {
+            let mut chunk = response(content, None);
+            chunk.content = content.to_string();
+            if let Some(index) = index {
+                chunk.metadata.insert(CHUNK_INDEX_KEY.to_string(), index.into());
+            }
+            chunk
+        };
+        let mut chunks = vec![
+            chunk("!", None),
+            chunk("world", Some(2)),
+            chunk("Hello, ", Some(0)),
+            chunk("", Some(1)),
+        ];
+
+        sort_by_chunk_index(&mut chunks);
+        assert_eq!(reassemble_content(&chunks), "Hello, world!");
+        assert_eq!(reassemble_content(&[]), "");
+    }
+
+    #[test]
+    fn test_score_helpers_handle_missing_and_nan() {
+        let mut results = vec![
+            response("none", None),
+            response("low", Some(0.2)),
+            response("nan", Some(f64::NAN)),
+            response("high", Some(0.9)),
+        ];
+
+        assert_eq!(results[0].score_or_zero(), 0.0);
+        assert_eq!(results[2].score_or_zero(), 0.0);
+        assert_eq!(best_match(&results).unwrap().id, "high");
+
+        sort_by_score_desc(&mut results);
+        let ids: Vec<_> = results.iter().map(|r| r.id.as_str()).collect();
+        assert_eq!(ids, ["high", "low", "none", "nan"]);
+
+        assert!(best_match(&[]).is_none());
+        assert_eq!(best_match(&[response("only", None)]).unwrap().id, "only");
+    }
+
+    #[test]
+    fn test_merge_results_dedupes_by_id() {
+        let merged = merge_results(
+            vec![
+                vec![response("a", Some(0.5)), response("b", Some(0.7))],
+                vec![response("a", Some(0.9)), response("c", None)],
+            ],
+            DedupStrategy::Id,
+        );
+
+        let ranked: Vec<_> = merged.iter().map(|r| (r.id.as_str(), r.score)).collect();
+        assert_eq!(ranked, [("a", Some(0.9)), ("b", Some(0.7)), ("c", None)]);
+    }
+
+    #[test]
+    fn test_merge_results_dedupes_by_content() {
+        let mut first = response("a", Some(0.4));
+        first.content = "same text".to_string();
+        let mut second = response("b", Some(0.8));
+        second.content = "same text\n".to_string();
+
+        let merged = merge_results(vec![vec![first], vec![second]], DedupStrategy::ContentHash);
+        assert_eq!(merged.len(), 1);
+        assert_eq!(merged[0].id, "b");
+    }
+
+    #[test]
+    fn test_group_duplicates() {
+        let memory = |id: &str, timestamp: u64, embedding: Option<Vec<f32>>| {
+            let mut memory = response(id, None);
+            memory.timestamp = timestamp;
+            memory.embedding = embedding;
+            memory
+        };
+        let memories = [
+            memory("c", 3, Some(vec![1.0, 0.01])),
+            memory("a", 1, Some(vec![1.0, 0.0])),
+            memory("other", 2, Some(vec![0.0, 1.0])),
+            memory("b", 2, Some(vec![1.0, 0.02])),
+            memory("unembedded", 0, None),
+            memory("short", 0, Some(vec![1.0])),
+        ];
+
+        let groups = group_duplicates(&memories, 0.99);
+        assert_eq!(groups, [DuplicateGroup { memory_ids: vec!["a".into(), "b".into(), "c".into()] }]);
+        assert!(group_duplicates(&memories, 1.01).is_empty());
+    }
+
+    #[test]
+    fn test_refine_with_predicates() {
+        let mut tagged = response("tagged", Some(0.9));
+        tagged.tags.push("rust".to_string());
+        tagged.metadata.insert("lang".to_string(), serde_json::json!("en"));
+        let mut low = response("low", Some(0.3));
+        low.tags.push("rust".to_string());
+        let results = vec![tagged, low, response("unscored", None)];
+
+        let ids = |refined: Vec<MemoryResponse>| -> Vec<String> {
+            refined.into_iter().map(|r| r.id).collect()
+        };
+
+        assert_eq!(ids(refine(&results, has_tag("rust"))), ["tagged", "low"]);
+        assert_eq!(ids(refine(&results, metadata_eq("lang", "en"))), ["tagged"]);
+        assert_eq!(ids(refine(&results, score_above(0.5))), ["tagged"]);
+
+        let rust = has_tag("rust");
+        let relevant = score_above(0.2);
+        let both = refine(&results, |r| rust(r) && relevant(r));
+        assert_eq!(ids(both), ["tagged", "low"]);
+    }
+
+    #[test]
+    fn test_cosine_similarity() {
+        assert_eq!(cosine_similarity(&[1.0, 0.0], &[2.0, 0.0]).unwrap(), 1.0);
+        assert_eq!(cosine_similarity(&[1.0, 0.0], &[0.0, 3.0]).unwrap(), 0.0);
+        assert_eq!(cosine_similarity(&[1.0, 1.0], &[-1.0, -1.0]).unwrap(), -1.0);
+        assert_eq!(cosine_similarity(&[0.0, 0.0], &[1.0, 1.0]).unwrap(), 0.0);
+        assert!(cosine_similarity(&[1.0], &[1.0, 0.0]).is_err());
+    }
+
+    #[test]
+    fn test_similarity_to_uses_embeddings() {
+        let mut a = response("a", None);
+        let mut b = response("b", None);
+        assert_eq!(a.similarity_to(&b), None);
+
+        a.embedding = Some(vec![1.0, 0.0]);
+        b.embedding = Some(vec![1.0, 0.0]);
+        assert_eq!(a.similarity_to(&b), Some(1.0));
+
+        b.embedding = Some(vec![1.0, 0.0, 0.0]);
+        assert_eq!(a.similarity_to(&b), None);
+    }
+
+    #[test]
+    fn test_estimate_size() {
+        let mut req = request("Hello, world!");
+        req.metadata.insert("source".to_string(), serde_json::json!("test"));
+
+        let estimate = estimate_size(&req);
+        assert_eq!(estimate.content_bytes, 13);
+        assert_eq!(estimate.metadata_bytes, r#"{"source":"test"}"#.len() as u64);
+        assert_eq!(estimate.total_bytes, serde_json::to_vec(&req).unwrap().len() as u64);
+        assert_eq!(estimate.estimated_tokens, Some(4));
+    }
+
+    #[test]
+    fn test_estimate_project_size_by_tag() {
+        let a = request("hello").with_tag("chat").with_tag("chat");
+        let b = request("world!!").with_tag("chat").with_tag("docs");
+        let c = request("");
+
+        let estimate = estimate_project_size(&[a.clone(), b.clone(), c.clone()]);
+        assert_eq!(estimate.memories, 3);
+        assert_eq!(estimate.total.content_bytes, 12);
+        assert_eq!(
+            estimate.total.total_bytes,
+            [&a, &b, &c].iter().map(|r| estimate_size(r).total_bytes).sum::<u64>()
+        );
+        assert_eq!(estimate.total.estimated_tokens, Some(4));
+        assert_eq!(estimate.by_tag["chat"].content_bytes, 12);
+        assert_eq!(estimate.by_tag["docs"].content_bytes, 7);
+        assert_eq!(estimate.by_tag.len(), 2);
+
+        assert_eq!(estimate_project_size(&[]), ProjectSizeEstimate::default());
+    }
+
+    #[test]
+    fn test_hash_content() {
+        assert_eq!(
+            hash_content(""),
+            "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855"
+        );
+        assert_eq!(
+            hash_content("abc"),
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+        assert_ne!(hash_content("abc"), hash_content("abc "));
+    }
+
+    #[test]
+    fn test_estimate_size_empty_content() {
+        let estimate = estimate_size(&request(""));
+        assert_eq!(estimate.content_bytes, 0);
+        assert_eq!(estimate.estimated_tokens, None);
+    }
+}
@@ -0,0 +1,148 @@
+//! UCP Request Validation
+//!
+//! Client-side checks of request invariants, run before sending when
+//! `UcpConfig::strict` is enabled. Every problem found is reported in a
+//! single `UcpError::ConfigError`.
+
+use super::types::{FilterExpr, MemoryRequest, Result, UcpError, VectorQuery};
+
+/// Check a memory request before storing it
+pub(crate) fn memory_request(request: &MemoryRequest) -> Result<()> {
+    let mut problems = Vec::new();
+
+    require_non_empty(&mut problems, "project", &request.project);
+    require_non_empty(&mut problems, "session", &request.session);
+    check_tags(&mut problems, &request.tags);
+    if let Some(ref collection) = request.collection {
+        require_non_empty(&mut problems, "collection", collection);
+    }
+
+    report("memory request", problems)
+}
+
+/// Check a search query before sending it
+pub(crate) fn vector_query(query: &VectorQuery) -> Result<()> {
+    let mut problems = Vec::new();
+
+    require_non_empty(&mut problems, "project", &query.project);
+    require_non_empty(&mut problems, "query", &query.query);
+    if let Some(ref session) = query.session {
+        require_non_empty(&mut problems, "session", session);
+    }
+    if query.limit == 0 {
+        problems.push("limit must be at least 1".to_string());
+    }
+    if !(0.0..=1.0).contains(&query.threshold) {
+        problems.push(format!(
+            "threshold must be between 0.0 and 1.0, got {}",
+            query.threshold
+        ));
+    }
+    if let Some(ref tags) = query.tags {
+        if tags.is_empty() {
+            problems.push("tags filter is set but empty".to_string());
+        }
+        check_tags(&mut problems, tags);
+    }
+    if let Some(ref prefix) = query.tag_prefix {
+        require_non_empty(&mut problems, "tag_prefix", prefix);
+    }
+    if let Some(ref projects) = query.projects {
+        for (index, project) in projects.iter().enumerate() {
+            require_non_empty(&mut problems, &format!("projects[{}]", index), project);
+        }
+    }
+    if let Some(ref collection) = query.collection {
+        require_non_empty(&mut problems, "collection", collection);
+    }
+    if let Some(ref filter) = query.filter {
+        check_filter(&mut problems, "filter", filter);
+    }
+
+    report("search query", problems)
+}
+
+fn require_non_empty(problems: &mut Vec<String>, field: &str, value: &str) {
+    if value.trim().is_empty() {
+        problems.push(format!("{} is empty", field));
+    }
+}
+
+fn check_tags(problems: &mut Vec<String>, tags: &[String]) {
+    for (index, tag) in tags.iter().enumerate() {
+        require_non_empty(problems, &format!("tags[{}]", index), tag);
+    }
+}
+
+fn check_filter(problems: &mut Vec<String>, path: &str, filter: &FilterExpr) {
+    match filter {
+        FilterExpr::And(exprs) | FilterExpr::Or(exprs) => {
+            if exprs.is_empty() {
+                problems.push(format!("{} has no sub-expressions", path));
+            }
+            for (index, expr) in exprs.iter().enumerate() {
+                check_filter(problems, &format!("{}[{}]", path, index), expr);
+            }
+        }
+        FilterExpr::Not(expr) => check_filter(problems, path, expr),
+        FilterExpr::TagEq(tag) => require_non_empty(problems, path, tag),
+        FilterExpr::MetadataEq { key, .. } => require_non_empty(problems, &format!("{} key", path), key),
+        FilterExpr::MetadataRange { key, gte, lte } => {
+            require_non_empty(problems, &format!("{} key", path), key);
+            match (gte, lte) {
+                (None, None) => problems.push(format!("{} range has no bounds", path)),
+                (Some(gte), Some(lte)) if gte > lte => {
+                    problems.push(format!("{} range is empty ({} > {})", path, gte, lte))
+                }
+                _ => {}
+            }
+        }
+    }
+}
+
+fn report(what: &str, problems: Vec<String>) -> Result<()> {
+    if problems.is_empty() {
+        Ok(())
+    } else {
+        Err(UcpError::ConfigError(format!(
+            "invalid {}: {}",
+            what,
+            problems.join("; ")
+        )))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_memory_request_reports_every_problem() {
+        let request = MemoryRequest::new("", "test_session", "content").with_tag(" ");
+
+        match memory_request(&request) {
+            Err(UcpError::ConfigError(message)) => {
+                assert_eq!(message, "invalid memory request: project is empty; tags[0] is empty");
+            }
+            other => panic!("expected ConfigError, got {:?}", other),
+        }
+
+        assert!(memory_request(&MemoryRequest::new("p", "s", "c")).is_ok());
+    }
+
+    #[test]
+    fn test_vector_query_ranges() {
+        assert!(vector_query(&VectorQuery::builder("p", "q").build()).is_ok());
+        assert!(vector_query(&VectorQuery::builder("p", "q").limit(0).build()).is_err());
+        assert!(vector_query(&VectorQuery::builder("p", "q").threshold(1.5).build()).is_err());
+        assert!(vector_query(&VectorQuery::builder("p", "q").threshold(f64::NAN).build()).is_err());
+        assert!(vector_query(&VectorQuery::builder("p", "q").tags(Vec::<String>::new()).build()).is_err());
+        assert!(vector_query(&VectorQuery::builder("p", "q").filter(FilterExpr::And(vec![])).build()).is_err());
+        assert!(vector_query(
+            &VectorQuery::builder("p", "q")
+                .filter(FilterExpr::metadata_range("n", Some(2.0), Some(1.0)))
+                .build()
+        )
+        .is_err());
+    }
+}
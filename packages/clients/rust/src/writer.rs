@@ -0,0 +1,323 @@
+//! UCP Session Writers
+//!
+//! Buffered appends to a single session, for agents that record a
+//! conversation turn by turn. Appended memories are sent together through
+//! the batch endpoint once `max_items` are waiting or every
+//! `flush_interval`, whichever comes first, trading a little latency for
+//! far fewer requests.
+
+use super::client::UcpClient;
+use super::types::{BatchResult, MemoryRequest, Result, UcpError};
+use std::collections::HashMap;
+use std::sync::{Arc, OnceLock};
+use std::time::Duration;
+use tokio::sync::Mutex;
+use tokio::task::JoinHandle;
+use tokio_util::sync::CancellationToken;
+
+/// Default number of buffered memories that triggers a flush
+pub const DEFAULT_WRITER_MAX_ITEMS: usize = 32;
+
+/// Default time between background flushes
+pub const DEFAULT_WRITER_FLUSH_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Buffered writer appending memories to one session
+///
+/// Created by `UcpClient::session_writer`. Results are numbered by append
+/// order, starting at 0: `flush` and `close` return those of every batch
+/// sent since the previous call, including batches sent in the background.
+/// A batch request that fails as a whole drops its memories, and the error
+/// is returned by the next call. Call `close` when done; dropping the
+/// writer discards anything still buffered, though a background flush
+/// already in flight still completes.
+#[derive(Debug)]
+pub struct SessionWriter {
+    inner: Arc<Inner>,
+    max_items: usize,
+    flush_interval: Duration,
+    /// Background flush task, started by the first append
+    timer: OnceLock<JoinHandle<()>>,
+    /// Stops the background flush task between flushes
+    stop: CancellationToken,
+}
+
+#[derive(Debug)]
+struct Inner {
+    client: UcpClient,
+    project: String,
+    session: String,
+    state: Mutex<State>,
+}
+
+#[derive(Debug, Default)]
+struct State {
+    buffer: Vec<MemoryRequest>,
+    /// Append index the next memory will get
+    next_index: usize,
+    /// Outcomes of batches sent since the last `flush`
+    results: BatchResult,
+    /// First error from a background flush, not yet reported
+    error: Option<UcpError>,
+}
+
+impl SessionWriter {
+    pub(crate) fn new(client: UcpClient, project: String, session: String) -> Self {
+        Self {
+            inner: Arc::new(Inner {
+                client,
+                project,
+                session,
+                state: Mutex::default(),
+            }),
+            max_items: DEFAULT_WRITER_MAX_ITEMS,
+            flush_interval: DEFAULT_WRITER_FLUSH_INTERVAL,
+            timer: OnceLock::new(),
+            stop: CancellationToken::new(),
+        }
+    }
+
+    /// Flush whenever this many memories are buffered (at least 1)
+    pub fn max_items(mut self, max_items: usize) -> Self {
+        self.max_items = max_items.max(1);
+        self
+    }
+
+    /// Flush buffered memories in the background this often
+    pub fn flush_interval(mut self, flush_interval: Duration) -> Self {
+        self.flush_interval = flush_interval;
+        self
+    }
+
+    /// Buffer a memory for the session, flushing if the buffer is full
+    pub async fn append(
+        &self,
+        content: impl Into<String>,
+        metadata: HashMap<String, serde_json::Value>,
+        tags: Vec<String>,
+    ) -> Result<()> {
+        self.start_timer();
+
+        let mut request =
+            MemoryRequest::new(self.inner.project.as_str(), self.inner.session.as_str(), content);
+        request.metadata = metadata;
+        request.tags = tags;
+
+        let mut state = self.inner.state.lock().await;
+        if let Some(error) = state.error.take() {
+            return Err(error);
+        }
+        state.buffer.push(request);
+        state.next_index += 1;
+
+        if state.buffer.len() >= self.max_items {
+            self.inner.send(&mut state).await?;
+        }
+        Ok(())
+    }
+
+    /// Send anything buffered now, returning the outcomes of all batches
+    /// sent since the last call
+    pub async fn flush(&self) -> Result<BatchResult> {
+        let mut state = self.inner.state.lock().await;
+        if let Some(error) = state.error.take() {
+            return Err(error);
+        }
+
+        self.inner.send(&mut state).await?;
+        Ok(std::mem::take(&mut state.results))
+    }
+
+    /// Stop background flushing and send anything still buffered
+    ///
+    /// A background flush in flight is awaited first, so its outcome is
+    /// included in the result.
+    pub async fn close(mut self) -> Result<BatchResult> {
+        self.stop.cancel();
+        if let Some(timer) = self.timer.take() {
+            // The task only panics if a send panicked; its batch is lost
+            // either way, so there is nothing more to report
+            let _ = timer.await;
+        }
+        self.flush().await
+    }
+
+    fn start_timer(&self) {
+        self.timer.get_or_init(|| {
+            let inner = Arc::downgrade(&self.inner);
+            let interval = self.flush_interval;
+            let stop = self.stop.clone();
+
+            tokio::spawn(async move {
+                loop {
+                    tokio::select! {
+                        _ = stop.cancelled() => return,
+                        _ = tokio::time::sleep(interval) => {}
+                    }
+                    let Some(inner) = inner.upgrade() else {
+                        return;
+                    };
+
+                    let mut state = inner.state.lock().await;
+                    if let Err(error) = inner.send(&mut state).await {
+                        state.error.get_or_insert(error);
+                    }
+                }
+            })
+        });
+    }
+}
+
+impl Drop for SessionWriter {
+    fn drop(&mut self) {
+        // Cooperative, so a flush in flight is not cut off mid-request
+        self.stop.cancel();
+    }
+}
+
+impl Inner {
+    /// Send the buffer as one batch, recording outcomes by append index
+    async fn send(&self, state: &mut State) -> Result<()> {
+        if state.buffer.is_empty() {
+            return Ok(());
+        }
+
+        let first_index = state.next_index - state.buffer.len();
+        let batch = std::mem::take(&mut state.buffer);
+        let result = self.client.store_memories_batch(batch).await?;
+
+        let results = &mut state.results;
+        results.succeeded.extend(
+            result
+                .succeeded
+                .into_iter()
+                .map(|(index, memory)| (first_index + index, memory)),
+        );
+        results.failed.extend(
+            result
+                .failed
+                .into_iter()
+                .map(|(index, error)| (first_index + index, error)),
+        );
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::UcpConfig;
+    use mockito::Server;
+    use serde_json::json;
+
+    fn stored(id: &str) -> serde_json::Value {
+        json!({ "id": id, "content": "turn", "metadata": {}, "tags": [], "timestamp": 1 })
+    }
+
+    fn client(url: String) -> UcpClient {
+        UcpClient::new(UcpConfig {
+            base_url: url,
+            ..Default::default()
+        })
+        .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_flushes_when_full_and_on_close() {
+        let mut server = Server::new_async().await;
+
+        let full = server
+            .mock("POST", "/api/v1/memory/batch")
+            .match_body(mockito::Matcher::PartialJson(json!({
+                "memories": [{ "content": "one" }, { "content": "two" }]
+            })))
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(json!({ "results": [stored("m1"), stored("m2")] }).to_string())
+            .create_async()
+            .await;
+        let rest = server
+            .mock("POST", "/api/v1/memory/batch")
+            .match_body(mockito::Matcher::PartialJson(json!({
+                "memories": [{ "content": "three", "session": "chat" }]
+            })))
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(json!({ "results": [stored("m3")] }).to_string())
+            .create_async()
+            .await;
+
+        let writer = client(server.url())
+            .session_writer("test_project", "chat")
+            .max_items(2)
+            .flush_interval(Duration::from_secs(60));
+        for content in ["one", "two", "three"] {
+            writer.append(content, HashMap::new(), Vec::new()).await.unwrap();
+        }
+        full.assert_async().await;
+
+        let result = writer.close().await.unwrap();
+        rest.assert_async().await;
+        let indices: Vec<usize> = result.succeeded.iter().map(|(index, _)| *index).collect();
+        assert_eq!(indices, [0, 1, 2]);
+        assert_eq!(result.succeeded[2].1.id, "m3");
+    }
+
+    #[tokio::test]
+    async fn test_close_waits_for_background_flush() {
+        let mut server = Server::new_async().await;
+
+        let body = json!({ "results": [stored("m1")] }).to_string();
+        let slow = server
+            .mock("POST", "/api/v1/memory/batch")
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_chunked_body(move |w| {
+                std::thread::sleep(Duration::from_millis(300));
+                w.write_all(body.as_bytes())
+            })
+            .expect(1)
+            .create_async()
+            .await;
+
+        let writer = client(server.url())
+            .session_writer("test_project", "chat")
+            .flush_interval(Duration::from_millis(20));
+        writer.append("hello", HashMap::new(), Vec::new()).await.unwrap();
+
+        // Close while the background flush is waiting on the server
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        let result = writer.close().await.unwrap();
+
+        slow.assert_async().await;
+        assert_eq!(result.succeeded.len(), 1);
+        assert_eq!(result.succeeded[0].1.id, "m1");
+    }
+
+    #[tokio::test]
+    async fn test_flushes_in_background_after_interval() {
+        let mut server = Server::new_async().await;
+
+        let batch = server
+            .mock("POST", "/api/v1/memory/batch")
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(json!({ "results": [stored("m1")] }).to_string())
+            .expect(1)
+            .create_async()
+            .await;
+
+        let writer = client(server.url())
+            .session_writer("test_project", "chat")
+            .flush_interval(Duration::from_millis(20));
+        writer
+            .append("hello", HashMap::new(), vec!["chat".to_string()])
+            .await
+            .unwrap();
+
+        tokio::time::sleep(Duration::from_millis(200)).await;
+        batch.assert_async().await;
+
+        let result = writer.close().await.unwrap();
+        assert_eq!(result.succeeded.len(), 1);
+    }
+}